@@ -0,0 +1,1240 @@
+use super::diagnostics::*;
+use super::lexer::Identifier;
+use super::parser::*;
+use super::span::*;
+use super::types::*;
+
+use std::collections::HashMap;
+
+/// What the checker knows about the type of an expression
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+#[derive(Clone)]
+enum Ty {
+    KNOWN(TypeExpr),
+    /// an unsuffixed integer literal, which fits every integer type wide enough
+    INT_LITERAL(i128),
+    /// an unsuffixed float literal, which fits both float types
+    FLOAT_LITERAL,
+}
+
+impl Ty {
+    fn describe(&self) -> String {
+        return match self {
+            Ty::KNOWN(t) => t.to_string(),
+            Ty::INT_LITERAL(_) => "integer".to_string(),
+            Ty::FLOAT_LITERAL => "float".to_string(),
+        };
+    }
+}
+
+/// What the exhaustiveness check knows about the values of a column of patterns
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+#[derive(Clone)]
+enum Column<'a> {
+    ENUM(&'a EnumDecl),
+    /// tuples with a column for each element
+    TUPLE(Vec<Column<'a>>),
+    /// numbers, strings and values of unknown type
+    OTHER,
+}
+
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+#[derive(Clone)]
+enum Binding<'a> {
+    /// a variable, with its type if it is known
+    VALUE(Option<Ty>),
+    FUNCTION(&'a FuncDecl),
+    STRUCT(&'a StructDecl),
+    INTERFACE(&'a InterfaceDecl),
+    ENUM(&'a EnumDecl),
+}
+
+/// A loop around the code being checked
+struct LoopFrame<'a> {
+    label: Option<&'a Spanned<Identifier>>,
+    /// `loop`, `while`, `for` or `do`, or `match` for the arms of a `match`
+    /// used as a value, which no `break` targets
+    kind: &'static str,
+    /// a `loop` or `match` used as a value, which nothing inside may leave but
+    /// the `break` of the `loop`
+    value: bool,
+}
+
+/// Checks function calls and returns against the declared signatures before
+/// the program runs. Only what is known for sure is reported: expressions of
+/// unknown type, like untyped parameters, are left to the runtime.
+struct Checker<'a, 'd> {
+    scopes: Vec<HashMap<String, Binding<'a>>>,
+    /// the function whose body is being checked
+    function: Option<&'a FuncDecl>,
+    /// the loops around the code being checked, innermost last
+    loops: Vec<LoopFrame<'a>>,
+    /// the methods of the `impl` blocks by the name of their struct, and the
+    /// methods of the interfaces by theirs
+    methods: HashMap<String, HashMap<String, &'a FuncDecl>>,
+    /// the interfaces implemented, by the name of the struct
+    implements: HashMap<String, Vec<String>>,
+    diags: &'d mut Diagnostics,
+}
+
+fn takes_self(fd: &FuncDecl) -> bool {
+    return fd.arg_list.first().is_some_and(|x| x.node.is_self());
+}
+
+/// The type of the value a reference of type `ty` refers to, or `ty` if it is
+/// not a reference. Strings are always used through references.
+fn dereferenced(ty: Option<Ty>) -> Option<Ty> {
+    return match ty {
+        Some(Ty::KNOWN(TypeExpr::REF(t))) if !matches!(t.node, TypeExpr::STR | TypeExpr::CSTR) => Some(Ty::KNOWN(t.node)),
+        ty => ty,
+    };
+}
+
+/// The type of an item of a value of type `ty`, if it is indexable
+fn item_of(ty: &Option<Ty>) -> Option<Ty> {
+    return match ty {
+        Some(Ty::KNOWN(TypeExpr::ARRAY(t, _) | TypeExpr::C_ARRAY(t, _))) => Some(Ty::KNOWN(t.node.clone())),
+        Some(Ty::KNOWN(TypeExpr::REF(t))) if matches!(t.node, TypeExpr::STR | TypeExpr::CSTR) => Some(Ty::KNOWN(TypeExpr::CHAR)),
+        _ => None,
+    };
+}
+
+/// Collects the names bound by `pattern`
+fn bindings<'p>(pattern: &'p Spanned<Pattern>, names: &mut Vec<&'p Spanned<Pattern>>) {
+    match &pattern.node {
+        Pattern::BIND(_) => names.push(pattern),
+        Pattern::VARIANT(_, _, elements) | Pattern::TUPLE(elements) => {
+            for x in elements.iter() {
+                bindings(x, names);
+            }
+        }
+        Pattern::WILDCARD | Pattern::LITERAL(_) => {}
+    }
+}
+
+/// Types compare by their canonical spelling, so that `int` and `i32` are the same
+fn same_type(a: &TypeExpr, b: &TypeExpr) -> bool {
+    return a.to_string() == b.to_string();
+}
+
+/// Whether the items of types `a` and `b` can be in the same array, as
+/// literals fit all the numbers of their kind
+fn same_item_type(a: &Ty, b: &Ty) -> bool {
+    return match (a, b) {
+        (Ty::KNOWN(t), Ty::KNOWN(u)) => same_type(t, u),
+        (Ty::INT_LITERAL(_), Ty::INT_LITERAL(_)) | (Ty::FLOAT_LITERAL, Ty::FLOAT_LITERAL) => true,
+        (Ty::KNOWN(TypeExpr::INT(_)), Ty::INT_LITERAL(_)) | (Ty::INT_LITERAL(_), Ty::KNOWN(TypeExpr::INT(_))) => true,
+        (Ty::KNOWN(TypeExpr::FLOAT(_)), Ty::FLOAT_LITERAL) | (Ty::FLOAT_LITERAL, Ty::KNOWN(TypeExpr::FLOAT(_))) => true,
+        _ => false,
+    };
+}
+
+impl<'a, 'd> Checker<'a, 'd> {
+    fn lookup(&self, name: &str) -> Option<&Binding<'a>> {
+        return self.scopes.iter().rev().find_map(|x| x.get(name));
+    }
+
+    fn declare(&mut self, name: &str, binding: Binding<'a>) {
+        self.scopes.last_mut().unwrap().insert(name.to_string(), binding);
+    }
+
+    /// Whether a value of type `t` can be used as a `e`: objects are also of
+    /// the interfaces their struct implements, and are passed by reference
+    fn conforms(&self, t: &TypeExpr, e: &TypeExpr) -> bool {
+        return match (t, e) {
+            (TypeExpr::INFER, _) => true,
+            (TypeExpr::NAMED(s, _), TypeExpr::NAMED(i, _)) if s != i => {
+                self.implements.get(&s.0).is_some_and(|x| x.contains(&i.0))
+            }
+            (TypeExpr::NAMED(_, _), TypeExpr::REF(x)) => self.conforms(t, &x.node),
+            (TypeExpr::REF(a), TypeExpr::REF(b)) | (TypeExpr::POINTER(a), TypeExpr::POINTER(b)) => self.conforms(&a.node, &b.node),
+            (TypeExpr::ARRAY(a, n), TypeExpr::ARRAY(b, m)) | (TypeExpr::C_ARRAY(a, n), TypeExpr::C_ARRAY(b, m)) |
+            (TypeExpr::ARRAY(a, n), TypeExpr::C_ARRAY(b, m)) => {
+                // lengths that are not fixed are only known when running
+                let fits = match (n, m) {
+                    (ArrayLen::FIXED(n), ArrayLen::FIXED(m)) => n == m,
+                    _ => true,
+                };
+                fits && self.conforms(&a.node, &b.node)
+            }
+            _ => same_type(t, e),
+        };
+    }
+
+    /// Whether `id` names a struct, interface or enum
+    fn is_type(&self, id: &Identifier) -> bool {
+        return matches!(self.lookup(&id.0), Some(Binding::STRUCT(_) | Binding::INTERFACE(_) | Binding::ENUM(_)));
+    }
+
+    /// Reports the names in the type `ty` that are not types
+    fn resolve(&mut self, ty: &Type) {
+        match &ty.node {
+            TypeExpr::NAMED(id, args) => {
+                if !self.is_type(id) {
+                    self.diags.emit(Diagnostic::error(&format!("cannot find type `{}` in this scope", id.0))
+                        .with_label(&ty.span, "not found in this scope"));
+                }
+                for x in args.iter() {
+                    self.resolve(x);
+                }
+            }
+            TypeExpr::POINTER(x) | TypeExpr::REF(x) | TypeExpr::ARRAY(x, _) | TypeExpr::C_ARRAY(x, _) => self.resolve(x),
+            TypeExpr::TUPLE(types) => {
+                for x in types.iter() {
+                    self.resolve(x);
+                }
+            }
+            TypeExpr::FUNCTION(args, ret) => {
+                for x in args.iter().chain(ret.iter().map(|x| &**x)) {
+                    self.resolve(x);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Reports `expr`, of type `ty`, if it cannot be used where `expected` is required
+    fn expect(&mut self, ty: &Option<Ty>, expected: &Type, expr: &Span, why: &str) {
+        let ok = match (ty, &expected.node) {
+            (None, _) | (_, TypeExpr::INFER) => true,
+            // reported by `resolve`
+            (_, TypeExpr::NAMED(id, _)) | (Some(Ty::KNOWN(TypeExpr::NAMED(id, _))), _) if !self.is_type(id) => true,
+            (Some(Ty::KNOWN(t)), e) => self.conforms(t, e),
+            (Some(Ty::INT_LITERAL(x)), TypeExpr::INT(t)) => {
+                if !t.contains(*x) {
+                    self.diags.emit(Diagnostic::error(&format!("literal out of range for {}", t))
+                        .with_label(expr, &format!("the range of {} is {}..={}", t, t.min(), t.max()))
+                        .with_secondary(&expected.span, why));
+                    return;
+                }
+                true
+            }
+            (Some(Ty::FLOAT_LITERAL), TypeExpr::FLOAT(_)) => true,
+            _ => false,
+        };
+        if !ok {
+            let found = ty.as_ref().unwrap().describe();
+            self.diags.emit(Diagnostic::error(&format!("mismatched types: expected {}, found {}", expected.node, found))
+                .with_label(expr, &format!("expected {}, found {}", expected.node, found))
+                .with_secondary(&expected.span, why));
+        }
+    }
+
+    fn loop_body(&mut self, label: &'a Option<Spanned<Identifier>>, kind: &'static str, value: bool, blk: &'a Block) {
+        self.loops.push(LoopFrame { label: label.as_ref(), kind, value });
+        self.block(blk);
+        self.loops.pop();
+    }
+
+    /// Checks that the `break` or `continue` at `span` has a loop to leave
+    fn jump(&mut self, what: &str, label: &Option<Spanned<Identifier>>, value: Option<&Expr>, span: &Span) {
+        let target = match label {
+            Some(l) => self.loops.iter().rposition(|x| x.label.is_some_and(|x| x.node == l.node)),
+            None => self.loops.iter().rposition(|x| x.kind != "match"),
+        };
+        let target = match (target, label) {
+            (Some(i), _) => i,
+            (None, Some(l)) => {
+                self.diags.emit(Diagnostic::error(&format!("use of undeclared label `'{}`", l.node.0))
+                    .with_label(&l.span, "no enclosing loop has this label"));
+                return;
+            }
+            (None, None) => {
+                self.diags.emit(Diagnostic::error(&format!("`{}` outside of a loop", what))
+                    .with_label(span, &format!("cannot `{}` outside of a loop", what)));
+                return;
+            }
+        };
+        if let Some(inner) = self.loops[target + 1..].iter().find(|x| x.value) {
+            self.diags.emit(Diagnostic::error(&format!("`{}` cannot leave a `{}` used as a value", what, inner.kind))
+                .with_label(span, &format!("this would skip the value of the inner `{}`", inner.kind)));
+            return;
+        }
+        if let Some(value) = value {
+            if self.loops[target].kind != "loop" {
+                self.diags.emit(Diagnostic::error(&format!("`break` with a value from a `{}` loop", self.loops[target].kind))
+                    .with_label(&value.span, "can only break with a value from `loop`"));
+            }
+        }
+    }
+
+    fn block(&mut self, blk: &'a Block) {
+        self.scopes.push(HashMap::new());
+        // functions and structs can be used before their declaration in the same block
+        for stmt in blk.0.iter() {
+            match &stmt.node {
+                Statement::FUNC_DECL(fd) => self.declare(&fd.func_name.0, Binding::FUNCTION(fd)),
+                Statement::STRUCT(sd) => self.declare(&sd.name.0, Binding::STRUCT(sd)),
+                Statement::ENUM(x) => self.declare(&x.name.0, Binding::ENUM(x)),
+                Statement::INTERFACE(x) => {
+                    self.declare(&x.name.0, Binding::INTERFACE(x));
+                    let methods = x.required.iter().chain(x.provided.iter());
+                    self.methods.insert(x.name.0.clone(), methods.map(|m| (m.node.func_name.0.clone(), &*m.node)).collect());
+                }
+                _ => {}
+            }
+        }
+        for stmt in blk.0.iter() {
+            if let Statement::IMPL(x) = &stmt.node {
+                self.implement(x);
+            }
+        }
+        for stmt in blk.0.iter() {
+            self.statement(stmt);
+        }
+        self.scopes.pop();
+    }
+
+    /// Registers the methods of an `impl` block
+    fn implement(&mut self, x: &'a Impl) {
+        let sd = match self.lookup(&x.target.node.0) {
+            Some(Binding::STRUCT(sd)) => *sd,
+            _ => {
+                self.diags.emit(Diagnostic::error(&format!("cannot find struct `{}` in this scope", x.target.node.0))
+                    .with_label(&x.target.span, "not a struct"));
+                return;
+            }
+        };
+        let methods = self.methods.entry(sd.name.0.clone()).or_default();
+        for m in x.methods.iter() {
+            if methods.insert(m.node.func_name.0.clone(), &m.node).is_some() {
+                self.diags.emit(Diagnostic::error(&format!("duplicate definitions with name `{}`", m.node.func_name.0))
+                    .with_label(&m.span, &format!("`{}` is already declared for `{}`", m.node.func_name.0, sd.name.0)));
+            }
+        }
+        if let Some(interface) = &x.interface {
+            self.implement_interface(sd, interface, x);
+        }
+    }
+
+    /// Checks an `impl Interface for Struct` block against the interface, and
+    /// registers the provided methods it does not define
+    fn implement_interface(&mut self, sd: &'a StructDecl, interface: &Spanned<Identifier>, x: &'a Impl) {
+        let decl = match self.lookup(&interface.node.0) {
+            Some(Binding::INTERFACE(decl)) => *decl,
+            _ => {
+                self.diags.emit(Diagnostic::error(&format!("cannot find interface `{}` in this scope", interface.node.0))
+                    .with_label(&interface.span, "not an interface"));
+                return;
+            }
+        };
+        let implemented = self.implements.entry(sd.name.0.clone()).or_default();
+        if implemented.contains(&decl.name.0) {
+            self.diags.emit(Diagnostic::error(&format!("conflicting implementations of `{}` for `{}`", decl.name.0, sd.name.0))
+                .with_label(&interface.span, "implemented again here"));
+            return;
+        }
+        implemented.push(decl.name.0.clone());
+        let find = |name: &Identifier| x.methods.iter().find(|m| m.node.func_name == *name);
+        let missing: Vec<_> = decl.required.iter().filter(|r| find(&r.node.func_name).is_none()).collect();
+        if !missing.is_empty() {
+            let names: Vec<_> = missing.iter().map(|r| format!("`{}`", r.node.func_name.0)).collect();
+            let mut e = Diagnostic::error(&format!("not all methods of `{}` are implemented: {}", decl.name.0, names.join(", ")))
+                .with_label(&x.target.span, &format!("missing {}", names.join(", ")));
+            for r in missing {
+                e = e.with_secondary(&r.span, &format!("`{}` declared here", r.node.func_name.0));
+            }
+            self.diags.emit(e);
+        }
+        for m in x.methods.iter() {
+            let declared = decl.required.iter().chain(decl.provided.iter()).find(|r| r.node.func_name == m.node.func_name);
+            match declared {
+                Some(r) if r.node.arg_list.len() != m.node.arg_list.len() => {
+                    self.diags.emit(Diagnostic::error(&format!("method `{}` has {} but the declaration in interface `{}` has {}",
+                                                               m.node.func_name.0, count(m.node.arg_list.len(), "parameter"), decl.name.0, count(r.node.arg_list.len(), "parameter")))
+                        .with_label(&m.span, &format!("expected {}", count(r.node.arg_list.len(), "parameter")))
+                        .with_secondary(&r.span, "declared here"));
+                }
+                Some(_) => {}
+                None => {
+                    self.diags.emit(Diagnostic::error(&format!("method `{}` is not a member of interface `{}`", m.node.func_name.0, decl.name.0))
+                        .with_label(&m.span, &format!("not a member of `{}`", decl.name.0)));
+                }
+            }
+        }
+        let methods = self.methods.entry(sd.name.0.clone()).or_default();
+        for p in decl.provided.iter() {
+            methods.entry(p.node.func_name.0.clone()).or_insert(&p.node);
+        }
+    }
+
+    /// Reports the fields of `sd` whose type does not implement an interface
+    /// `sd` derives, as the derived methods use those of the fields
+    fn derived(&mut self, sd: &StructDecl) {
+        for interface in sd.derive.iter() {
+            let i = &interface.node.0;
+            for field in sd.fields.iter() {
+                let name = match self.lacking(&field.node.ty.node, i) {
+                    Some(x) => x,
+                    None => continue,
+                };
+                let note = match self.lookup(&name.0) {
+                    Some(Binding::ENUM(_)) => "the variants of an enum cannot be compared with `==`".to_string(),
+                    Some(Binding::INTERFACE(_)) => format!("the struct of a `{}` is only known when running", name.0),
+                    _ => format!("implement `{}` for `{}`, or derive it with `#[derive({})]`", i, name.0, i),
+                };
+                self.diags.emit(Diagnostic::error(&format!("cannot derive `{}` for `{}`", i, sd.name.0))
+                    .with_label(&field.node.ty.span, &format!("`{}` does not implement `{}`", name.0, i))
+                    .with_secondary(&interface.span, "derived here")
+                    .with_note(&note));
+            }
+        }
+    }
+
+    /// The struct, interface or enum in the type `ty` that does not implement
+    /// `interface`. Enums, like numbers and references, are copied and printed
+    /// as they are, but only numbers and references are compared.
+    fn lacking<'t>(&self, ty: &'t TypeExpr, interface: &str) -> Option<&'t Identifier> {
+        return match ty {
+            TypeExpr::NAMED(id, _) => match self.lookup(&id.0) {
+                Some(Binding::STRUCT(_)) if self.implements.get(&id.0).is_some_and(|x| x.iter().any(|x| x == interface)) => None,
+                Some(Binding::STRUCT(_) | Binding::INTERFACE(_)) => Some(id),
+                Some(Binding::ENUM(_)) if interface == "Eq" => Some(id),
+                _ => None,
+            },
+            TypeExpr::ARRAY(item, _) | TypeExpr::C_ARRAY(item, _) => self.lacking(&item.node, interface),
+            TypeExpr::TUPLE(types) => types.iter().find_map(|x| self.lacking(&x.node, interface)),
+            _ => None,
+        };
+    }
+
+    /// The declaration of the struct `ty` is an instance of, if it is known
+    fn struct_of(&self, ty: &Option<Ty>) -> Option<&'a StructDecl> {
+        if let Some(Ty::KNOWN(TypeExpr::NAMED(id, _))) = ty {
+            if let Some(Binding::STRUCT(sd)) = self.lookup(&id.0) {
+                return Some(*sd);
+            }
+        }
+        return None;
+    }
+
+    /// The declaration of the interface `ty` is, if it is known
+    fn interface_of(&self, ty: &Option<Ty>) -> Option<&'a InterfaceDecl> {
+        if let Some(Ty::KNOWN(TypeExpr::NAMED(id, _))) = ty {
+            if let Some(Binding::INTERFACE(x)) = self.lookup(&id.0) {
+                return Some(*x);
+            }
+        }
+        return None;
+    }
+
+    fn method(&self, sd: &StructDecl, name: &str) -> Option<&'a FuncDecl> {
+        return self.methods.get(&sd.name.0).and_then(|x| x.get(name)).copied();
+    }
+
+    /// Checks `x.name` outside of a call, returning the declaration of the field if it is known
+    fn field(&mut self, x: &'a Expr, name: &Identifier, span: &Span) -> Option<&'a Spanned<FieldDecl>> {
+        let ty = self.expr(x);
+        let sd = self.struct_of(&ty)?;
+        if let Some(field) = sd.fields.iter().find(|x| x.node.id == *name) {
+            return Some(field);
+        }
+        if self.method(sd, &name.0).is_some() {
+            self.diags.emit(Diagnostic::error(&format!("attempted to take value of method `{}` on type {}", name.0, sd.name.0))
+                .with_label(span, "method, not a field")
+                .with_note("use parentheses to call the method"));
+        } else {
+            self.diags.emit(Diagnostic::error(&format!("no field `{}` on type {}", name.0, sd.name.0))
+                .with_label(span, "unknown field"));
+        }
+        return None;
+    }
+
+    /// Checks `ty::name`, returning the function if it is known
+    fn path(&mut self, ty: &Identifier, name: &Identifier, span: &Span) -> Option<&'a FuncDecl> {
+        match self.lookup(&ty.0) {
+            Some(Binding::STRUCT(sd)) => {
+                let sd = *sd;
+                let fd = self.method(sd, &name.0);
+                if fd.is_none() {
+                    self.diags.emit(Diagnostic::error(&format!("no function named `{}` in struct `{}`", name.0, ty.0))
+                        .with_label(span, "function not found in the impl blocks"));
+                }
+                return fd;
+            }
+            Some(_) => {
+                self.diags.emit(Diagnostic::error(&format!("`{}` is not a struct", ty.0))
+                    .with_label(span, "expected a struct before '::'"));
+            }
+            None => {}
+        }
+        return None;
+    }
+
+    /// Checks a constructor call of the struct `sd`, which takes every field or none
+    fn construct(&mut self, sd: &'a StructDecl, fc: &FuncCall, args: Vec<Option<Ty>>, span: &Span) {
+        if !args.is_empty() && args.len() != sd.fields.len() {
+            let mut e = Diagnostic::error(&format!("struct `{}` has {} but {}", sd.name.0, count(sd.fields.len(), "field"), supplied(args.len())))
+                .with_label(span, &format!("expected {}", count(sd.fields.len(), "argument")));
+            if let (Some(first), Some(last)) = (sd.fields.first(), sd.fields.last()) {
+                e = e.with_secondary(&first.span.to(&last.span), "fields declared here");
+            }
+            self.diags.emit(e.with_note(&format!("call `{}()` without arguments to zero all the fields", sd.name.0)));
+            return;
+        }
+        for (i, ty) in args.iter().enumerate() {
+            let field = &sd.fields[i];
+            self.expect(ty, &field.node.ty, &fc.arg_list[i].span, &format!("field `{}` declared here", field.node.id.0));
+        }
+    }
+
+    /// The declaration of the enum `ty` names, if it is one
+    fn enum_named(&self, ty: &TypeExpr) -> Option<&'a EnumDecl> {
+        if let TypeExpr::NAMED(id, _) = ty {
+            if let Some(Binding::ENUM(x)) = self.lookup(&id.0) {
+                return Some(*x);
+            }
+        }
+        return None;
+    }
+
+    /// Checks `Enum::Variant(args)`, where `args` are empty for a variant
+    /// without payload, returning whether the variant exists
+    fn construct_variant(&mut self, decl: &'a EnumDecl, name: &Identifier, arg_list: &[Expr], args: Vec<Option<Ty>>, span: &Span) -> bool {
+        let variant = match decl.variant(name) {
+            Some(i) => &decl.variants[i],
+            None => {
+                self.diags.emit(Diagnostic::error(&format!("no variant named `{}` in enum `{}`", name.0, decl.name.0))
+                    .with_label(span, "variant not found"));
+                return false;
+            }
+        };
+        if args.len() != variant.node.fields.len() {
+            self.diags.emit(Diagnostic::error(&format!("variant `{}::{}` has {} but {}", decl.name.0, name.0, count(variant.node.fields.len(), "field"), supplied(args.len())))
+                .with_label(span, &format!("expected {}", count(variant.node.fields.len(), "argument")))
+                .with_secondary(&variant.span, "variant declared here"));
+            return true;
+        }
+        for (i, ty) in args.iter().enumerate() {
+            self.expect(ty, &variant.node.fields[i], &arg_list[i].span, "expected due to the type of this field");
+        }
+        return true;
+    }
+
+    /// Checks a pattern against the type `ty` of the value it matches,
+    /// declaring the names it binds with that type, which values assigned to
+    /// them later must have too
+    fn pattern(&mut self, pattern: &'a Spanned<Pattern>, ty: Option<Ty>) {
+        match &pattern.node {
+            Pattern::BIND(id) => self.declare(&id.0, Binding::VALUE(ty)),
+            Pattern::WILDCARD => {}
+            Pattern::TUPLE(elements) => {
+                let types = match &ty {
+                    Some(Ty::KNOWN(TypeExpr::TUPLE(types))) => Some(types),
+                    Some(Ty::KNOWN(TypeExpr::INFER)) | None => None,
+                    Some(t) => {
+                        self.diags.emit(Diagnostic::error(&format!("mismatched types: expected {}, found a tuple", t.describe()))
+                            .with_label(&pattern.span, &format!("expected {}", t.describe())));
+                        None
+                    }
+                };
+                if let (Some(types), Some(t)) = (types, &ty) {
+                    if types.len() != elements.len() {
+                        self.diags.emit(Diagnostic::error(&format!("mismatched types: expected a tuple with {}, found {}", count(elements.len(), "element"), t.describe()))
+                            .with_label(&pattern.span, &format!("expected a tuple with {}", count(elements.len(), "element"))));
+                    }
+                }
+                for (i, x) in elements.iter().enumerate() {
+                    let ty = types.and_then(|t| t.get(i)).map(|t| Ty::KNOWN(t.node.clone()));
+                    self.pattern(x, ty);
+                }
+            }
+            Pattern::LITERAL(x) => {
+                let found = self.expr(x);
+                let ok = match (&ty, &found) {
+                    (Some(Ty::KNOWN(t)), Some(Ty::KNOWN(f))) => self.conforms(f, t),
+                    (Some(Ty::KNOWN(t)), Some(Ty::INT_LITERAL(_))) => matches!(t, TypeExpr::INT(_) | TypeExpr::INFER),
+                    (Some(Ty::KNOWN(t)), Some(Ty::FLOAT_LITERAL)) => matches!(t, TypeExpr::FLOAT(_) | TypeExpr::INFER),
+                    _ => true,
+                };
+                if let (false, Some(t), Some(f)) = (ok, &ty, &found) {
+                    self.diags.emit(Diagnostic::error(&format!("mismatched types: expected {}, found {}", t.describe(), f.describe()))
+                        .with_label(&pattern.span, &format!("expected {}, found {}", t.describe(), f.describe())));
+                }
+            }
+            Pattern::VARIANT(e, v, fields) => {
+                let decl = match self.lookup(&e.0) {
+                    Some(Binding::ENUM(decl)) => *decl,
+                    _ => {
+                        self.diags.emit(Diagnostic::error(&format!("cannot find enum `{}` in this scope", e.0))
+                            .with_label(&pattern.span, "not an enum"));
+                        return;
+                    }
+                };
+                if let Some(Ty::KNOWN(t)) = &ty {
+                    if !self.conforms(t, &TypeExpr::NAMED(e.clone(), vec![])) {
+                        self.diags.emit(Diagnostic::error(&format!("mismatched types: expected {}, found enum `{}`", t, e.0))
+                            .with_label(&pattern.span, &format!("expected {}", t)));
+                    }
+                }
+                let variant = match decl.variant(v) {
+                    Some(i) => &decl.variants[i],
+                    None => {
+                        self.diags.emit(Diagnostic::error(&format!("no variant named `{}` in enum `{}`", v.0, e.0))
+                            .with_label(&pattern.span, "variant not found"));
+                        return;
+                    }
+                };
+                if fields.len() != variant.node.fields.len() {
+                    self.diags.emit(Diagnostic::error(&format!("this pattern has {}, but the variant `{}::{}` has {}", count(fields.len(), "field"), e.0, v.0, count(variant.node.fields.len(), "field")))
+                        .with_label(&pattern.span, &format!("expected {}", count(variant.node.fields.len(), "field")))
+                        .with_secondary(&variant.span, "variant declared here"));
+                    return;
+                }
+                for (i, field) in fields.iter().enumerate() {
+                    self.pattern(field, Some(Ty::KNOWN(variant.node.fields[i].node.clone())));
+                }
+            }
+        }
+    }
+
+    /// What is known of the values of type `ty` matched by `patterns`. The
+    /// type may be unknown, but not that of the patterns.
+    fn column(&self, ty: Option<&TypeExpr>, patterns: &[&'a Pattern]) -> Column<'a> {
+        if let Some(decl) = ty.and_then(|t| self.enum_named(t)) {
+            return Column::ENUM(decl);
+        }
+        let arity = match ty {
+            Some(TypeExpr::TUPLE(types)) => Some(types.len()),
+            _ => patterns.iter().find_map(|x| match x {
+                Pattern::TUPLE(elements) => Some(elements.len()),
+                _ => None,
+            }),
+        };
+        if let Some(arity) = arity {
+            let columns = (0..arity).map(|i| {
+                let elements: Vec<&Pattern> = patterns.iter().filter_map(|x| match x {
+                    Pattern::TUPLE(elements) if elements.len() == arity => Some(&elements[i].node),
+                    _ => None,
+                }).collect();
+                let ty = match ty {
+                    Some(TypeExpr::TUPLE(types)) => Some(&types[i].node),
+                    _ => None,
+                };
+                self.column(ty, &elements)
+            });
+            return Column::TUPLE(columns.collect());
+        }
+        return match patterns.iter().find_map(|x| match x {
+            Pattern::VARIANT(e, _, _) => self.enum_named(&TypeExpr::NAMED(e.clone(), vec![])),
+            _ => None,
+        }) {
+            Some(decl) => Column::ENUM(decl),
+            None => Column::OTHER,
+        };
+    }
+
+    /// A value that no row of patterns matches, if there is one, written as
+    /// a pattern for each column. `None` in a row matches anything.
+    fn uncovered<'p>(&self, rows: &[Vec<Option<&'p Pattern>>], columns: &[Column<'p>]) -> Option<Vec<String>> where 'a: 'p {
+        if columns.is_empty() {
+            return if rows.is_empty() { Some(vec![]) } else { None };
+        }
+        let catch_all = |x: &Option<&Pattern>| matches!(x, None | Some(Pattern::BIND(_)) | Some(Pattern::WILDCARD));
+        let decl = match &columns[0] {
+            Column::ENUM(decl) => *decl,
+            Column::TUPLE(elements) => {
+                let arity = elements.len();
+                let mut specialized = vec![];
+                for row in rows.iter() {
+                    let mut x: Vec<Option<&Pattern>> = match row[0] {
+                        Some(Pattern::TUPLE(x)) if x.len() == arity => x.iter().map(|x| Some(&x.node)).collect(),
+                        x if catch_all(&x) => vec![None; arity],
+                        _ => continue,
+                    };
+                    x.extend_from_slice(&row[1..]);
+                    specialized.push(x);
+                }
+                let mut inner = elements.clone();
+                inner.extend_from_slice(&columns[1..]);
+                let mut uncovered = self.uncovered(&specialized, &inner)?;
+                let rest = uncovered.split_off(arity);
+                let mut value = format!("({})", uncovered.join(", "));
+                if arity == 1 {
+                    value = format!("({},)", uncovered[0]);
+                }
+                let mut uncovered = vec![value];
+                uncovered.extend(rest);
+                return Some(uncovered);
+            }
+            Column::OTHER => {
+                // numbers and strings are only all matched by a pattern matching anything
+                let rest: Vec<_> = rows.iter().filter(|x| catch_all(&x[0])).map(|x| x[1..].to_vec()).collect();
+                let mut uncovered = self.uncovered(&rest, &columns[1..])?;
+                uncovered.insert(0, "_".to_string());
+                return Some(uncovered);
+            }
+        };
+        for variant in decl.variants.iter() {
+            let arity = variant.node.fields.len();
+            let mut specialized = vec![];
+            for row in rows.iter() {
+                let mut x: Vec<Option<&Pattern>> = match row[0] {
+                    Some(Pattern::VARIANT(e, v, fields)) if *e == decl.name && *v == variant.node.name && fields.len() == arity => {
+                        fields.iter().map(|x| Some(&x.node)).collect()
+                    }
+                    x if catch_all(&x) => vec![None; arity],
+                    _ => continue,
+                };
+                x.extend_from_slice(&row[1..]);
+                specialized.push(x);
+            }
+            let mut inner: Vec<_> = variant.node.fields.iter().map(|x| self.column(Some(&x.node), &[])).collect();
+            inner.extend_from_slice(&columns[1..]);
+            if let Some(mut fields) = self.uncovered(&specialized, &inner) {
+                let rest = fields.split_off(arity);
+                let mut value = format!("{}::{}", decl.name.0, variant.node.name.0);
+                if arity > 0 {
+                    value = format!("{}({})", value, fields.join(", "));
+                }
+                let mut uncovered = vec![value];
+                uncovered.extend(rest);
+                return Some(uncovered);
+            }
+        }
+        return None;
+    }
+
+    /// Checks a `match` and that its arms cover every value, returning the
+    /// type of its value if all the arms agree on it
+    fn match_(&mut self, x: &'a Match, value: bool) -> Option<Ty> {
+        let ty = self.expr(&x.value);
+        let mut arm_ty: Option<(Ty, &Span)> = None;
+        let mut agree = true;
+        for arm in x.arms.iter() {
+            self.scopes.push(HashMap::new());
+            self.bound_once(&[&arm.node.pattern], "in the same pattern", "used in a pattern more than once");
+            self.pattern(&arm.node.pattern, ty.clone());
+            if let Some(guard) = &arm.node.guard {
+                self.expr(guard);
+            }
+            match &arm.node.body {
+                ArmBody::EXPR(e) => {
+                    let t = self.expr(e);
+                    match (&arm_ty, t) {
+                        (_, Some(Ty::KNOWN(TypeExpr::INFER))) => agree = false,
+                        (None, Some(Ty::KNOWN(t))) if agree => arm_ty = Some((Ty::KNOWN(t), &e.span)),
+                        (Some((Ty::KNOWN(first), span)), Some(Ty::KNOWN(t))) if value && !same_type(first, &t) => {
+                            self.diags.emit(Diagnostic::error("`match` arms have incompatible types")
+                                .with_label(&e.span, &format!("expected {}, found {}", first, t))
+                                .with_secondary(span, &format!("this is {}", first)));
+                            agree = false;
+                        }
+                        (_, Some(Ty::KNOWN(_))) => {}
+                        _ => agree = false,
+                    }
+                }
+                ArmBody::BLOCK(b) => {
+                    agree = false;
+                    if value {
+                        self.loops.push(LoopFrame { label: None, kind: "match", value: true });
+                        self.block(b);
+                        self.loops.pop();
+                    } else {
+                        self.block(b);
+                    }
+                }
+            }
+            self.scopes.pop();
+        }
+
+        let rows: Vec<Vec<Option<&Pattern>>> = x.arms.iter()
+            .filter(|x| x.node.guard.is_none())
+            .map(|x| vec![Some(&x.node.pattern.node)])
+            .collect();
+        let patterns: Vec<&Pattern> = x.arms.iter().map(|x| &x.node.pattern.node).collect();
+        let column = match &ty {
+            Some(Ty::KNOWN(t)) => self.column(Some(t), &patterns),
+            _ => self.column(None, &patterns),
+        };
+        if let Some(uncovered) = self.uncovered(&rows, &[column]) {
+            let uncovered = &uncovered[0];
+            self.diags.emit(Diagnostic::error(&format!("non-exhaustive patterns: `{}` not covered", uncovered))
+                .with_label(&x.value.span, &format!("pattern `{}` not covered", uncovered))
+                .with_note("add an arm for it, or a `_` arm matching anything"));
+        }
+        return if agree { arm_ty.map(|x| x.0) } else { None };
+    }
+
+    /// Reports the names that `patterns` bind more than once, `place` such as
+    /// "in this parameter list"
+    fn bound_once(&mut self, patterns: &[&Spanned<Pattern>], place: &str, label: &str) {
+        let mut names = vec![];
+        for x in patterns.iter() {
+            bindings(x, &mut names);
+        }
+        for (i, x) in names.iter().enumerate() {
+            if let Some(first) = names[..i].iter().find(|y| y.node == x.node) {
+                self.diags.emit(Diagnostic::error(&format!("identifier `{}` is bound more than once {}", x.span.text(), place))
+                    .with_label(&x.span, label)
+                    .with_secondary(&first.span, "first bound here"));
+            }
+        }
+    }
+
+    /// Checks the body of a declared function or lambda, which no loop around it reaches into
+    fn function(&mut self, fd: &'a FuncDecl) {
+        let outer = self.function.replace(fd);
+        let outer_loops = std::mem::take(&mut self.loops);
+        self.scopes.push(HashMap::new());
+        self.resolve(&fd.ret);
+        let params: Vec<_> = fd.arg_list.iter().map(|x| &x.node.pattern).collect();
+        self.bound_once(&params, "in this parameter list", "used as parameter more than once");
+        for arg in fd.arg_list.iter() {
+            if let Some(ty) = &arg.node.ty {
+                self.resolve(ty);
+            }
+            let ty = arg.node.ty.as_ref().map(|x| Ty::KNOWN(x.node.clone()));
+            self.pattern(&arg.node.pattern, ty);
+        }
+        self.block(&fd.body);
+        self.scopes.pop();
+        self.function = outer;
+        self.loops = outer_loops;
+    }
+
+    fn statement(&mut self, stmt: &'a Stmt) {
+        match &stmt.node {
+            Statement::LET(x) => {
+                if let Some(declared) = &x.ty {
+                    self.resolve(declared);
+                }
+                let ty = x.init.as_ref().and_then(|init| self.expr(init));
+                if let (Some(declared), Some(init)) = (&x.ty, &x.init) {
+                    self.expect(&ty, declared, &init.span, "expected due to this type");
+                }
+                let ty = match &x.ty {
+                    Some(declared) => Some(Ty::KNOWN(declared.node.clone())),
+                    None => match ty {
+                        // a negated literal has the type of its value, like when running it
+                        Some(Ty::INT_LITERAL(v)) => Some(Ty::KNOWN(TypeExpr::INT(IntType::of_literal(v)))),
+                        Some(Ty::FLOAT_LITERAL) => Some(Ty::KNOWN(TypeExpr::FLOAT(FloatType::F64))),
+                        ty => ty,
+                    },
+                };
+                self.bound_once(&[&x.pattern], "in the same pattern", "used in a pattern more than once");
+                self.pattern(&x.pattern, ty);
+            }
+            Statement::RETURN(Return(x)) => {
+                if let Some(inner) = self.loops.iter().find(|x| x.value) {
+                    self.diags.emit(Diagnostic::error(&format!("`return` cannot leave a `{}` used as a value", inner.kind))
+                        .with_label(&stmt.span, &format!("this would skip the value of the `{}`", inner.kind)));
+                }
+                let ty = x.as_ref().and_then(|x| self.expr(x));
+                match (self.function, x) {
+                    (Some(fd), Some(x)) if fd.ret.node == TypeExpr::VOID => {
+                        self.diags.emit(Diagnostic::error(&format!("mismatched types: `{}` returns void but a value is returned", fd.func_name.0))
+                            .with_label(&x.span, "unexpected return value")
+                            .with_secondary(&fd.ret.span, "add a return type here, as in `-> int`"));
+                    }
+                    (Some(fd), Some(x)) => {
+                        self.expect(&ty, &fd.ret, &x.span, "expected due to this return type");
+                    }
+                    (None, Some(x)) => {
+                        self.diags.emit(Diagnostic::error("cannot return a value outside of a function")
+                            .with_label(&x.span, "unexpected return value")
+                            .with_note("`return;` ends the program without a value"));
+                    }
+                    (Some(fd), None) if fd.ret.node != TypeExpr::VOID && fd.ret.node != TypeExpr::INFER => {
+                        self.diags.emit(Diagnostic::error(&format!("mismatched types: `{}` returns {} but no value is returned", fd.func_name.0, fd.ret.node))
+                            .with_label(&stmt.span, &format!("expected a value of type {}", fd.ret.node))
+                            .with_secondary(&fd.ret.span, "expected due to this return type"));
+                    }
+                    _ => {}
+                }
+            }
+            Statement::EXPRESSION(x) => {
+                self.expr(x);
+            }
+            Statement::FUNC_DECL(fd) => {
+                self.function(fd);
+            }
+            Statement::IF(x) => {
+                for cond in x.cond.iter() {
+                    self.expr(cond);
+                }
+                for then in x.then.iter() {
+                    self.block(then);
+                }
+            }
+            Statement::WHILE(x) => {
+                self.expr(&x.cond);
+                self.loop_body(&x.label, "while", false, &x.then);
+            }
+            Statement::LOOP(x) => {
+                self.loop_body(&x.label, "loop", false, &x.then);
+            }
+            Statement::DO_UNTIL(x) => {
+                self.loop_body(&x.label, "do", false, &x.then);
+                self.expr(&x.cond);
+            }
+            Statement::BREAK(x) => {
+                if let Some(value) = &x.value {
+                    self.expr(value);
+                }
+                self.jump("break", &x.label, x.value.as_ref(), &stmt.span);
+            }
+            Statement::CONTINUE(label) => {
+                self.jump("continue", label, None, &stmt.span);
+            }
+            Statement::FOR(x) => {
+                let item = match &x.iter {
+                    Iteration::COUNTED(c) => {
+                        self.expr(&c.from);
+                        self.expr(&c.to);
+                        if let Some(step) = &c.step {
+                            self.expr(step);
+                        }
+                        None
+                    }
+                    Iteration::IN(iterable) => {
+                        let ty = dereferenced(self.expr(iterable));
+                        item_of(&ty)
+                    }
+                };
+                self.scopes.push(HashMap::new());
+                self.bound_once(&[&x.var], "in the same pattern", "used in a pattern more than once");
+                self.pattern(&x.var, item);
+                self.loop_body(&x.label, "for", false, &x.then);
+                self.scopes.pop();
+            }
+            Statement::STRUCT(sd) => {
+                for (i, field) in sd.fields.iter().enumerate() {
+                    self.resolve(&field.node.ty);
+                    if let Some(first) = sd.fields[..i].iter().find(|x| x.node.id == field.node.id) {
+                        self.diags.emit(Diagnostic::error(&format!("field `{}` is already declared", field.node.id.0))
+                            .with_label(&field.span, "field already declared")
+                            .with_secondary(&first.span, "first declared here"));
+                    }
+                }
+                self.derived(sd);
+            }
+            Statement::ENUM(x) => {
+                for ty in x.variants.iter().flat_map(|v| v.node.fields.iter()) {
+                    self.resolve(ty);
+                }
+                for (i, variant) in x.variants.iter().enumerate() {
+                    if let Some(first) = x.variants[..i].iter().find(|v| v.node.name == variant.node.name) {
+                        self.diags.emit(Diagnostic::error(&format!("variant `{}` is already declared", variant.node.name.0))
+                            .with_label(&variant.span, "variant already declared")
+                            .with_secondary(&first.span, "first declared here"));
+                    }
+                }
+            }
+            Statement::MATCH(x) => {
+                self.match_(x, false);
+            }
+            Statement::INTERFACE(x) => {
+                let methods: Vec<_> = x.required.iter().chain(x.provided.iter()).collect();
+                for (i, m) in methods.iter().enumerate() {
+                    if let Some(first) = methods[..i].iter().find(|x| x.node.func_name == m.node.func_name) {
+                        self.diags.emit(Diagnostic::error(&format!("duplicate definitions with name `{}`", m.node.func_name.0))
+                            .with_label(&m.span, &format!("`{}` is already declared in `{}`", m.node.func_name.0, x.name.0))
+                            .with_secondary(&first.span, "first declared here"));
+                    }
+                }
+                for m in x.provided.iter() {
+                    self.function(&m.node);
+                }
+            }
+            Statement::IMPL(x) => {
+                for m in x.methods.iter() {
+                    self.function(&m.node);
+                }
+            }
+            Statement::NOTHING | Statement::ERROR => {}
+        }
+    }
+
+    /// Checks a call of the declared function `fd` named `name`. The first
+    /// `offset` parameters are not in `arg_list`, like the `self` of a method.
+    fn call(&mut self, fd: &'a FuncDecl, name: &str, arg_list: &[Expr], args: Vec<Option<Ty>>, offset: usize, span: &Span) {
+        let params = &fd.arg_list[offset..];
+        if args.len() != params.len() {
+            let decl = match (params.first(), params.last()) {
+                (Some(first), Some(last)) => first.span.to(&last.span),
+                _ => fd.ret.span.clone(),
+            };
+            self.diags.emit(Diagnostic::error(&format!("function `{}` takes {} but {}", name, count(params.len(), "argument"), supplied(args.len())))
+                .with_label(span, &format!("expected {}", count(params.len(), "argument")))
+                .with_secondary(&decl, "parameters declared here"));
+            return;
+        }
+        for (i, ty) in args.iter().enumerate() {
+            if let Some(expected) = &params[i].node.ty {
+                self.expect(ty, expected, &arg_list[i].span, &format!("parameter `{}` declared here", params[i].node.pattern.span.text()));
+            }
+        }
+    }
+
+    /// Checks `e` and what it contains, returning its type if it is known
+    fn expr(&mut self, e: &'a Expr) -> Option<Ty> {
+        return match &e.node {
+            Value::INT(x, None) => Some(Ty::INT_LITERAL(*x as i128)),
+            Value::INT(x, Some(t)) => {
+                if !t.contains(*x as i128) {
+                    self.diags.emit(Diagnostic::error(&format!("literal out of range for {}, whose maximum is {}", t, t.max()))
+                        .with_label(&e.span, &format!("the range of {} is {}..={}", t, t.min(), t.max())));
+                }
+                Some(Ty::KNOWN(TypeExpr::INT(*t)))
+            }
+            Value::FLOAT(_, None) => Some(Ty::FLOAT_LITERAL),
+            Value::FLOAT(_, Some(t)) => Some(Ty::KNOWN(TypeExpr::FLOAT(*t))),
+            Value::BOOL(_) => Some(Ty::KNOWN(TypeExpr::INT(IntType::I32))),
+            Value::STRING(_) => Some(Ty::KNOWN(TypeExpr::REF(Box::new(Spanned::new(TypeExpr::STR, e.span.clone()))))),
+            Value::CSTRING(_) => Some(Ty::KNOWN(TypeExpr::REF(Box::new(Spanned::new(TypeExpr::CSTR, e.span.clone()))))),
+            Value::CHAR(_) => Some(Ty::KNOWN(TypeExpr::CHAR)),
+            Value::VAR(id) => match self.lookup(&id.0) {
+                Some(Binding::VALUE(ty)) => ty.clone(),
+                _ => None,
+            },
+            Value::FUNC_CALL(fc) => {
+                let args: Vec<Option<Ty>> = match (fc.func_name.0.as_str(), fc.arg_list.as_slice()) {
+                    // the minimum of a signed type is only in range once negated
+                    ("-", [Spanned { node: Value::INT(_, Some(t)), .. }]) => vec![Some(Ty::KNOWN(TypeExpr::INT(*t)))],
+                    _ => fc.arg_list.iter().map(|x| self.expr(x)).collect(),
+                };
+                match self.lookup(&fc.func_name.0).cloned() {
+                    Some(Binding::FUNCTION(fd)) => {
+                        self.call(fd, &fc.func_name.0, &fc.arg_list, args, 0, &e.span);
+                        Some(Ty::KNOWN(fd.ret.node.clone()))
+                    }
+                    Some(Binding::STRUCT(sd)) => {
+                        self.construct(sd, fc, args, &e.span);
+                        Some(Ty::KNOWN(TypeExpr::NAMED(sd.name.clone(), vec![])))
+                    }
+                    _ => match fc.func_name.0.as_str() {
+                        "==" | "!=" | "<" | "<=" | ">" | ">=" | "!" => Some(Ty::KNOWN(TypeExpr::INT(IntType::I32))),
+                        // a negated literal still takes the type it is expected to have
+                        "-" => match args.as_slice() {
+                            [Some(Ty::INT_LITERAL(x))] => Some(Ty::INT_LITERAL(-x)),
+                            [Some(Ty::FLOAT_LITERAL)] => Some(Ty::FLOAT_LITERAL),
+                            _ => None,
+                        },
+                        "new" => match args.first() {
+                            Some(Some(Ty::KNOWN(t))) => Some(Ty::KNOWN(TypeExpr::REF(Box::new(Spanned::new(t.clone(), e.span.clone()))))),
+                            _ => None,
+                        },
+                        "len" => {
+                            if let (Some(Some(Ty::KNOWN(TypeExpr::C_ARRAY(_, ArrayLen::ANY)))), Some(arg)) = (args.first(), fc.arg_list.first()) {
+                                self.diags.emit(Diagnostic::error("cannot take the length of a C-like array")
+                                    .with_label(&arg.span, "this array does not store its length")
+                                    .with_note("use a Rust-like array `[T]`, or bind its length with `T[n]` and use `n`"));
+                            }
+                            Some(Ty::KNOWN(TypeExpr::INT(IntType::U64)))
+                        }
+                        _ => None,
+                    },
+                }
+            }
+            Value::CALL(c) => {
+                let args: Vec<Option<Ty>> = c.arg_list.iter().map(|x| self.expr(x)).collect();
+                let name = c.callee.span.text();
+                match &c.callee.node {
+                    Value::FIELD(x, m) => {
+                        let ty = self.expr(x);
+                        if let Some(decl) = self.interface_of(&ty) {
+                            let fd = self.methods.get(&decl.name.0).and_then(|x| x.get(&m.0)).copied();
+                            match fd {
+                                Some(fd) if takes_self(fd) => {
+                                    self.call(fd, name, &c.arg_list, args, 1, &e.span);
+                                    return Some(Ty::KNOWN(fd.ret.node.clone()));
+                                }
+                                _ => {
+                                    self.diags.emit(Diagnostic::error(&format!("no method named `{}` found for interface `{}`", m.0, decl.name.0))
+                                        .with_label(&c.callee.span, "method not found"));
+                                }
+                            }
+                            return None;
+                        }
+                        let sd = self.struct_of(&ty)?;
+                        match self.method(sd, &m.0) {
+                            Some(fd) if takes_self(fd) => {
+                                self.call(fd, name, &c.arg_list, args, 1, &e.span);
+                                return Some(Ty::KNOWN(fd.ret.node.clone()));
+                            }
+                            Some(_) => {
+                                self.diags.emit(Diagnostic::error(&format!("`{}` is an associated function, not a method", m.0))
+                                    .with_label(&c.callee.span, "this function takes no `self`")
+                                    .with_note(&format!("call it as `{}::{}(...)`", sd.name.0, m.0)));
+                            }
+                            None if sd.fields.iter().any(|x| x.node.id == *m) => {}
+                            None => {
+                                self.diags.emit(Diagnostic::error(&format!("no method named `{}` found for struct `{}`", m.0, sd.name.0))
+                                    .with_label(&c.callee.span, "method not found"));
+                            }
+                        }
+                        None
+                    }
+                    Value::PATH(ty, m) => {
+                        if let Some(Binding::ENUM(decl)) = self.lookup(&ty.0).cloned() {
+                            if self.construct_variant(decl, m, &c.arg_list, args, &e.span) {
+                                return Some(Ty::KNOWN(TypeExpr::NAMED(ty.clone(), vec![])));
+                            }
+                            return None;
+                        }
+                        let fd = self.path(ty, m, &c.callee.span)?;
+                        self.call(fd, name, &c.arg_list, args, 0, &e.span);
+                        Some(Ty::KNOWN(fd.ret.node.clone()))
+                    }
+                    _ => {
+                        self.expr(&c.callee);
+                        None
+                    }
+                }
+            }
+            Value::ASSIGN(x) => {
+                let ty = self.expr(&x.val);
+                match &x.target.node {
+                    Value::VAR(id) => {
+                        if let Some(Binding::VALUE(Some(Ty::KNOWN(t)))) = self.lookup(&id.0) {
+                            let t = Spanned::new(t.clone(), x.target.span.clone());
+                            self.expect(&ty, &t, &x.val.span, &format!("expected due to the type of `{}`", id.0));
+                        }
+                    }
+                    Value::FIELD(obj, name) => {
+                        if let Some(field) = self.field(obj, name, &x.target.span) {
+                            self.expect(&ty, &field.node.ty, &x.val.span, "expected due to the type of this field");
+                        }
+                    }
+                    Value::INDEX(_, _) => {
+                        if let Some(Ty::KNOWN(item)) = self.expr(&x.target) {
+                            let item = Spanned::new(item, x.target.span.clone());
+                            self.expect(&ty, &item, &x.val.span, "expected due to the type of the array");
+                        }
+                    }
+                    _ => {}
+                }
+                ty
+            }
+            Value::COMPOUND_ASSIGN(x) => {
+                self.expr(&x.target);
+                self.expr(&x.val);
+                None
+            }
+            Value::LOGICAL(x) => {
+                self.expr(&x.lhs);
+                self.expr(&x.rhs);
+                Some(Ty::KNOWN(TypeExpr::INT(IntType::I32)))
+            }
+            Value::CAST(x, ty) => {
+                self.expr(x);
+                Some(Ty::KNOWN(ty.node.clone()))
+            }
+            Value::INDEX(x, i) => {
+                let ty = dereferenced(self.expr(x));
+                self.expr(i);
+                item_of(&ty)
+            }
+            Value::SLICE(x, from, to) => {
+                let ty = self.expr(x);
+                for bound in from.iter().chain(to.iter()) {
+                    self.expr(bound);
+                }
+                match ty {
+                    Some(Ty::KNOWN(TypeExpr::ARRAY(t, _) | TypeExpr::C_ARRAY(t, _))) => Some(Ty::KNOWN(TypeExpr::ARRAY(t, ArrayLen::ANY))),
+                    Some(Ty::KNOWN(TypeExpr::REF(t))) if matches!(t.node, TypeExpr::STR | TypeExpr::CSTR) => Some(Ty::KNOWN(TypeExpr::REF(t))),
+                    _ => None,
+                }
+            }
+            Value::TUPLE(elements) => {
+                let types: Vec<Option<Ty>> = elements.iter().map(|x| self.expr(x)).collect();
+                // as with arrays, literals take the type the tuple is used as
+                let mut known = vec![];
+                for (ty, x) in types.into_iter().zip(elements.iter()) {
+                    match ty {
+                        Some(Ty::KNOWN(t)) => known.push(Spanned::new(t, x.span.clone())),
+                        _ => return None,
+                    }
+                }
+                Some(Ty::KNOWN(TypeExpr::TUPLE(known)))
+            }
+            Value::ELEMENT(x, i) => {
+                match dereferenced(self.expr(x)) {
+                    Some(Ty::KNOWN(TypeExpr::TUPLE(types))) => match types.get(*i) {
+                        Some(t) => Some(Ty::KNOWN(t.node.clone())),
+                        None => {
+                            let n = types.len();
+                            self.diags.emit(Diagnostic::error(&format!("no element {} on type {}", i, TypeExpr::TUPLE(types)))
+                                .with_label(&e.span, &format!("the tuple has {}", count(n, "element"))));
+                            None
+                        }
+                    },
+                    _ => None,
+                }
+            }
+            Value::ARRAY(items) => {
+                let types: Vec<Option<Ty>> = items.iter().map(|x| self.expr(x)).collect();
+                let mut first: Option<(&Ty, &Span)> = None;
+                for (ty, item) in types.iter().zip(items.iter()) {
+                    match (first, ty) {
+                        (None, Some(t)) => first = Some((t, &item.span)),
+                        (Some((t, span)), Some(u)) if !same_item_type(t, u) => {
+                            self.diags.emit(Diagnostic::error(&format!("mismatched types: expected {}, found {}", t.describe(), u.describe()))
+                                .with_label(&item.span, &format!("expected {}, found {}", t.describe(), u.describe()))
+                                .with_secondary(span, "the type of the array is set by its first item"));
+                            return None;
+                        }
+                        _ => {}
+                    }
+                }
+                // the items are only known to share a type if none is a literal
+                let (t, span) = match first? {
+                    (Ty::KNOWN(t), span) if types.iter().all(|x| matches!(x, Some(Ty::KNOWN(_)))) => (t, span),
+                    _ => return None,
+                };
+                Some(Ty::KNOWN(TypeExpr::ARRAY(Box::new(Spanned::new(t.clone(), span.clone())), ArrayLen::FIXED(items.len() as u64))))
+            }
+            Value::REPEAT(x, n) => {
+                let ty = self.expr(x);
+                self.expr(n);
+                match (ty, &n.node) {
+                    (Some(Ty::KNOWN(t)), Value::INT(n, _)) => Some(Ty::KNOWN(TypeExpr::ARRAY(Box::new(Spanned::new(t, x.span.clone())), ArrayLen::FIXED(*n)))),
+                    _ => None,
+                }
+            }
+            Value::FIELD(x, name) => self.field(x, name, &e.span).map(|x| Ty::KNOWN(x.node.ty.node.clone())),
+            Value::PATH(ty, name) => {
+                if let Some(Binding::ENUM(decl)) = self.lookup(&ty.0).cloned() {
+                    if self.construct_variant(decl, name, &[], vec![], &e.span) {
+                        return Some(Ty::KNOWN(TypeExpr::NAMED(ty.clone(), vec![])));
+                    }
+                    return None;
+                }
+                self.path(ty, name, &e.span);
+                None
+            }
+            Value::MATCH(x) => self.match_(x, true),
+            Value::REF(x) => match self.expr(x) {
+                Some(Ty::KNOWN(t)) => Some(Ty::KNOWN(TypeExpr::REF(Box::new(Spanned::new(t, x.span.clone()))))),
+                _ => None,
+            },
+            Value::DEREF(x) => match self.expr(x) {
+                Some(Ty::KNOWN(TypeExpr::REF(t) | TypeExpr::POINTER(t))) => Some(Ty::KNOWN(t.node)),
+                _ => None,
+            },
+            Value::LAMBDA(fd) => {
+                self.function(fd);
+                None
+            }
+            Value::LOOP(x) => {
+                self.loop_body(&x.label, "loop", true, &x.then);
+                None
+            }
+            Value::ERROR => None,
+        };
+    }
+}
+
+/// Checks the whole program before it runs, reporting to `diags`
+pub fn check(root: &Block, diags: &mut Diagnostics) {
+    let mut checker = Checker {
+        scopes: vec![HashMap::new()],
+        function: None,
+        loops: vec![],
+        methods: HashMap::new(),
+        implements: HashMap::new(),
+        diags,
+    };
+    checker.block(root);
+}
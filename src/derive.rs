@@ -0,0 +1,128 @@
+use super::diagnostics::*;
+use super::lexer::*;
+use super::parser::*;
+use super::span::*;
+use super::types::*;
+use std::rc::Rc;
+
+/// The interfaces `#[derive(...)]` can implement
+const DERIVABLE: [&str; 3] = ["Copy", "Eq", "Debug"];
+
+/// Generates the `impl Interface for Name` blocks asked for by the struct's
+/// `#[derive(...)]`. The generated nodes are spanned at the derived name.
+pub fn derive(decl: &StructDecl, diags: &mut Diagnostics) -> Vec<Stmt> {
+    let mut impls = vec![];
+    for (i, interface) in decl.derive.iter().enumerate() {
+        if decl.derive[..i].iter().any(|x| x.node == interface.node) {
+            diags.emit(Diagnostic::error(&format!("`{}` is derived twice", interface.node.0))
+                .with_label(&interface.span, "derived again here")
+                .with_secondary(&decl.derive[..i].iter().find(|x| x.node == interface.node).unwrap().span,
+                                "first derived here"));
+            continue;
+        }
+        let span = &interface.span;
+        let method = match interface.node.0.as_str() {
+            "Copy" => clone(decl, span),
+            "Eq" => eq(decl, span),
+            "Debug" => debug(decl, span),
+            name => {
+                diags.emit(Diagnostic::error(&format!("cannot derive `{}`", name))
+                    .with_label(span, "not a derivable interface")
+                    .with_note(&format!("the derivable interfaces are {}", DERIVABLE.join(", "))));
+                continue;
+            }
+        };
+        let x = Impl {
+            interface: Some(interface.clone()),
+            target: Spanned::new(decl.name.clone(), span.clone()),
+            methods: vec![Spanned::new(Rc::new(method), span.clone())],
+        };
+        impls.push(Spanned::new(Statement::IMPL(x), span.clone()));
+    }
+    return impls;
+}
+
+fn node<T>(x: T, span: &Span) -> Spanned<T> {
+    return Spanned::new(x, span.clone());
+}
+
+fn var(name: &str, span: &Span) -> Expr {
+    return node(Value::VAR(Identifier(name.to_string())), span);
+}
+
+fn field(of: &str, field: &Identifier, span: &Span) -> Expr {
+    return node(Value::FIELD(Box::new(var(of, span)), field.clone()), span);
+}
+
+fn call(func: &str, arg_list: Vec<Expr>, span: &Span) -> Expr {
+    let x = FuncCall { func_name: Identifier(func.to_string()), arg_list };
+    return node(Value::FUNC_CALL(x), span);
+}
+
+fn string(s: &str, span: &Span) -> Expr {
+    return node(Value::STRING(s.to_string()), span);
+}
+
+fn arg(name: &str, ty: Option<Type>, span: &Span) -> Spanned<ArgDecl> {
+    let pattern = node(Pattern::BIND(Identifier(name.to_string())), span);
+    return node(ArgDecl { pattern, mutable: false, ty }, span);
+}
+
+fn method(name: &str, arg_list: Vec<Spanned<ArgDecl>>, ret: TypeExpr, value: Expr, span: &Span) -> FuncDecl {
+    return FuncDecl {
+        func_name: Identifier(name.to_string()),
+        arg_list,
+        ret: node(ret, span),
+        body: Block(vec![node(Statement::RETURN(Return(Some(value))), span)]),
+        doc: vec![],
+    };
+}
+
+fn self_type(decl: &StructDecl, span: &Span) -> Type {
+    return node(TypeExpr::NAMED(decl.name.clone(), vec![]), span);
+}
+
+/// `fn clone(self) -> S { return S(self.a, self.b); }`, where constructing
+/// `S` copies the fields that are structs
+fn clone(decl: &StructDecl, span: &Span) -> FuncDecl {
+    let fields = decl.fields.iter().map(|f| field("self", &f.node.id, span)).collect();
+    let value = call(&decl.name.0, fields, span);
+    let ret = self_type(decl, span).node;
+    return method("clone", vec![arg("self", Some(self_type(decl, span)), span)], ret, value, span);
+}
+
+/// `fn eq(self, other: S) -> int { return self.a == other.a && ...; }`
+fn eq(decl: &StructDecl, span: &Span) -> FuncDecl {
+    let mut value: Option<Expr> = None;
+    for f in decl.fields.iter() {
+        let same = call("==", vec![field("self", &f.node.id, span), field("other", &f.node.id, span)], span);
+        value = Some(match value {
+            Some(lhs) => node(Value::LOGICAL(Logical { op: "&&", lhs: Box::new(lhs), rhs: Box::new(same) }), span),
+            None => same,
+        });
+    }
+    let value = value.unwrap_or_else(|| node(Value::INT(1, Some(IntType::I32)), span));
+    let arg_list = vec![
+        arg("self", Some(self_type(decl, span)), span),
+        arg("other", Some(self_type(decl, span)), span),
+    ];
+    return method("eq", arg_list, TypeExpr::INT(IntType::I32), value, span);
+}
+
+/// `fn debug(self) -> ref str { return "S { a: " + to_string(self.a) + " }"; }`,
+/// where `to_string` of a struct is its `debug()`
+fn debug(decl: &StructDecl, span: &Span) -> FuncDecl {
+    let mut value = string(&decl.name.0, span);
+    for (i, f) in decl.fields.iter().enumerate() {
+        let sep = if i == 0 { " { " } else { ", " };
+        let label = string(&format!("{}{}: ", sep, f.node.id.0), span);
+        let x = call("to_string", vec![field("self", &f.node.id, span)], span);
+        value = call("+", vec![value, label], span);
+        value = call("+", vec![value, x], span);
+    }
+    if !decl.fields.is_empty() {
+        value = call("+", vec![value, string(" }", span)], span);
+    }
+    let ret = TypeExpr::REF(Box::new(node(TypeExpr::STR, span)));
+    return method("debug", vec![arg("self", Some(self_type(decl, span)), span)], ret, value, span);
+}
@@ -0,0 +1,209 @@
+use super::span::*;
+use std::fmt::{Display, Error, Formatter};
+use std::rc::Rc;
+use unicode_width::UnicodeWidthStr;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Severity {
+    ERROR,
+    WARNING,
+    NOTE,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            Severity::ERROR => f.write_str("error"),
+            Severity::WARNING => f.write_str("warning"),
+            Severity::NOTE => f.write_str("note"),
+        }
+    }
+}
+
+/// A source range annotated with a message. Primary labels point at the cause
+/// of the diagnostic and are underlined with `^`, secondary ones with `-`.
+#[derive(Clone, Debug)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    pub primary: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: &str) -> Diagnostic {
+        return Diagnostic {
+            severity,
+            message: message.to_string(),
+            labels: vec![],
+            notes: vec![],
+        };
+    }
+
+    pub fn error(message: &str) -> Diagnostic {
+        return Diagnostic::new(Severity::ERROR, message);
+    }
+
+    pub fn warning(message: &str) -> Diagnostic {
+        return Diagnostic::new(Severity::WARNING, message);
+    }
+
+    pub fn with_label(mut self, span: &Span, message: &str) -> Diagnostic {
+        self.labels.push(Label {
+            span: span.clone(),
+            message: message.to_string(),
+            primary: true,
+        });
+        return self;
+    }
+
+    pub fn with_secondary(mut self, span: &Span, message: &str) -> Diagnostic {
+        self.labels.push(Label {
+            span: span.clone(),
+            message: message.to_string(),
+            primary: false,
+        });
+        return self;
+    }
+
+    pub fn with_note(mut self, note: &str) -> Diagnostic {
+        self.notes.push(note.to_string());
+        return self;
+    }
+
+    pub fn primary_span(&self) -> Option<&Span> {
+        return self.labels.iter().find(|x| x.primary).map(|x| &x.span);
+    }
+
+    /// Renders the diagnostic for a terminal: a header, the location of the
+    /// primary label, every labelled source line with an underline, and notes.
+    pub fn render(&self) -> String {
+        let mut out = format!("{}: {}\n", self.severity, self.message);
+        let gutter = self.labels.iter()
+            .map(|x| (x.span.line_col().0).to_string().len())
+            .max()
+            .unwrap_or(0);
+        if let Some(span) = self.primary_span().or_else(|| self.labels.first().map(|x| &x.span)) {
+            out += &format!("{} --> {}\n", " ".repeat(gutter), span);
+            out += &format!("{} |\n", " ".repeat(gutter));
+        }
+        // labels are shown line by line from the top of each file, the files
+        // in the order they are first labelled
+        let mut files: Vec<&Rc<SourceFile>> = vec![];
+        for label in self.labels.iter() {
+            if !files.iter().any(|x| Rc::ptr_eq(x, &label.span.file)) {
+                files.push(&label.span.file);
+            }
+        }
+        let mut labels: Vec<&Label> = self.labels.iter().collect();
+        labels.sort_by_key(|x| {
+            let file = files.iter().position(|f| Rc::ptr_eq(f, &x.span.file)).unwrap();
+            (file, x.span.file.line_index(x.span.start))
+        });
+        let mut printed: Option<(&Span, usize)> = None;
+        for label in labels {
+            let file = &label.span.file;
+            let line = file.line_index(label.span.start);
+            let text = file.line_text(line);
+            let same_line = match printed {
+                Some((span, l)) => Rc::ptr_eq(&span.file, file) && l == line,
+                None => false,
+            };
+            if !same_line {
+                out += &format!("{:>w$} | {}\n", line + 1, text, w = gutter);
+                printed = Some((&label.span, line));
+            }
+            let start = (label.span.start - file.line_start(line)).min(text.len());
+            // a span running over several lines is underlined up to the end of its first line
+            let end = (label.span.end - file.line_start(line)).min(text.len()).max(start);
+            // pad by display width so the underline lines up below wide characters
+            let indent = UnicodeWidthStr::width(&text[..start]);
+            let width = UnicodeWidthStr::width(&text[start..end]).max(1);
+            let mark = if label.primary { "^" } else { "-" };
+            out += &format!("{} | {}{}", " ".repeat(gutter), " ".repeat(indent), mark.repeat(width));
+            if !label.message.is_empty() {
+                out += " ";
+                out += &label.message;
+            }
+            out += "\n";
+        }
+        for note in self.notes.iter() {
+            out += &format!("{} = note: {}\n", " ".repeat(gutter), note);
+        }
+        return out;
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self.primary_span() {
+            Some(span) => write!(f, "{}: {}: {}", span, self.severity, self.message),
+            None => write!(f, "{}: {}", self.severity, self.message),
+        }
+    }
+}
+
+/// `n` and `noun`, made plural unless `n` is 1, as in "2 arguments"
+pub fn count(n: usize, noun: &str) -> String {
+    return format!("{} {}{}", n, noun, if n == 1 { "" } else { "s" });
+}
+
+/// That `n` things were supplied, as in "1 was supplied"
+pub fn supplied(n: usize) -> String {
+    return format!("{} {} supplied", n, if n == 1 { "was" } else { "were" });
+}
+
+/// Collects the diagnostics reported by every compilation and execution stage
+#[derive(Default, Debug)]
+pub struct Diagnostics {
+    pub items: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Diagnostics {
+        return Diagnostics { items: vec![] };
+    }
+
+    pub fn emit(&mut self, diagnostic: Diagnostic) {
+        debug!("emit {}", diagnostic);
+        self.items.push(diagnostic);
+    }
+
+    pub fn error_count(&self) -> usize {
+        return self.items.iter().filter(|x| x.severity == Severity::ERROR).count();
+    }
+
+    pub fn has_errors(&self) -> bool {
+        return self.error_count() > 0;
+    }
+
+    /// Renders every diagnostic in source order. Stages report in batches, e.g. all
+    /// lexer errors come before the parser's, so the order of reporting is not kept;
+    /// only files are kept in the order their first diagnostic was reported.
+    pub fn render(&self) -> String {
+        let mut files: Vec<&Rc<SourceFile>> = vec![];
+        for span in self.items.iter().filter_map(|x| x.primary_span()) {
+            if !files.iter().any(|x| Rc::ptr_eq(x, &span.file)) {
+                files.push(&span.file);
+            }
+        }
+        let mut items: Vec<&Diagnostic> = self.items.iter().collect();
+        items.sort_by_key(|x| x.primary_span().map(|s| {
+            let file = files.iter().position(|x| Rc::ptr_eq(x, &s.file)).unwrap();
+            (file, s.start)
+        }));
+        let mut out = String::new();
+        for x in items {
+            out += &x.render();
+            out += "\n";
+        }
+        return out;
+    }
+}
@@ -1,6 +1,11 @@
-use std::fmt::{Debug, Error, Formatter};
+use std::fmt::{Debug, Display, Error, Formatter};
+use std::rc::Rc;
 
+use super::diagnostics::*;
+use super::span::*;
+use super::types::*;
 use super::util::*;
+use unicode_xid::UnicodeXID;
 
 #[derive(PartialEq, Eq, Clone)]
 pub struct Identifier(pub String);
@@ -13,113 +18,680 @@ impl Debug for Identifier {
     }
 }
 
-#[derive(PartialEq)]
-pub enum Token {
+#[allow(non_camel_case_types)]
+#[derive(PartialEq, Debug, Clone)]
+pub enum TokenKind {
     IDENTIFIER(Identifier),
     OPERATOR(&'static str),
     KEYWORD(&'static str),
-    INTEGER(i32),
-    FLOAT(f64),
+    /// magnitude and optional type suffix, as in `0x6C` or `8888888888ul`
+    INTEGER(u64, Option<IntType>),
+    FLOAT(f64, Option<FloatType>),
+    /// `"..."` or `r"..."`, of type `ref str`
+    STRING(String),
+    /// `c"..."` or `cr"..."`, of type `ref cstr`
+    CSTRING(String),
+    CHAR(char),
+    /// `'name`, naming a loop
+    LABEL(String),
+    /// `/// text`, kept so that documentation can be attached to declarations
+    DOC_COMMENT(String),
     SEMICOLON,
-    ERROR,
+    /// input that is not a valid token, with the reason
+    ERROR(String),
     EOF,
 }
 
+impl Display for TokenKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            TokenKind::IDENTIFIER(x) => write!(f, "identifier `{}`", x.0),
+            TokenKind::OPERATOR(x) => write!(f, "`{}`", x),
+            TokenKind::KEYWORD(x) => write!(f, "keyword `{}`", x),
+            TokenKind::INTEGER(x, t) => write!(f, "integer `{}{}`", x, t.map_or("", |x| x.name())),
+            TokenKind::FLOAT(x, t) => write!(f, "float `{}{}`", x, t.map_or("", |x| x.name())),
+            TokenKind::STRING(x) => write!(f, "string {:?}", x),
+            TokenKind::CSTRING(x) => write!(f, "c string c{:?}", x),
+            TokenKind::CHAR(x) => write!(f, "character {:?}", x),
+            TokenKind::LABEL(x) => write!(f, "label `'{}`", x),
+            TokenKind::DOC_COMMENT(_) => f.write_str("doc comment"),
+            TokenKind::SEMICOLON => f.write_str("`;`"),
+            TokenKind::ERROR(x) => f.write_str(x),
+            TokenKind::EOF => f.write_str("end of file"),
+        }
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum TriviaKind {
+    WHITESPACE,
+    /// `// ...`, `# ...` or `/* ... */`, but not a doc comment
+    COMMENT,
+}
+
+/// Input between two tokens that has no meaning to the parser
+#[derive(PartialEq, Debug, Clone)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub span: Span,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+    /// whitespace and comments before the token; only kept by the lossless mode
+    pub leading: Vec<Trivia>,
+}
+
+/// Iterates over the tokens of a file, ending with a single `EOF` token
+pub struct Lexer {
+    file: Rc<SourceFile>,
+    pos: usize,
+    done: bool,
+    lossless: bool,
+}
+
+impl Lexer {
+    pub fn new(file: &Rc<SourceFile>) -> Lexer {
+        return Lexer {
+            file: file.clone(),
+            pos: 0,
+            done: false,
+            lossless: false,
+        };
+    }
+
+    /// A lexer attaching all whitespace and comments to the tokens following them,
+    /// so that the tokens together reproduce the file exactly. See `reprint`.
+    pub fn lossless(file: &Rc<SourceFile>) -> Lexer {
+        let mut lexer = Lexer::new(file);
+        lexer.lossless = true;
+        return lexer;
+    }
+}
+
+impl Iterator for Lexer {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        if self.done {
+            return None;
+        }
+        let mut leading = vec![];
+        if self.lossless {
+            let input = self.file.text.as_str();
+            while let Some((kind, end)) = trivia(input, self.pos) {
+                leading.push(Trivia {
+                    kind,
+                    span: Span::new(&self.file, self.pos, end),
+                });
+                self.pos = end;
+            }
+        }
+        let mut tk = next_token(&self.file, self.pos);
+        tk.leading = leading;
+        self.pos = tk.span.end;
+        self.done = tk.kind == TokenKind::EOF;
+        return Some(tk);
+    }
+}
+
+/// The tokens of a file, lexed once up front, with a cursor for the parser
+pub struct TokenStream {
+    file: Rc<SourceFile>,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl TokenStream {
+    /// Lexes the whole `file`, reporting every invalid token to `diags`
+    pub fn new(file: &Rc<SourceFile>, diags: &mut Diagnostics) -> TokenStream {
+        let tokens: Vec<Token> = Lexer::new(file).collect();
+        for tk in tokens.iter() {
+            if let TokenKind::ERROR(e) = &tk.kind {
+                diags.emit(Diagnostic::error(e).with_label(&tk.span, "invalid token"));
+            }
+        }
+        return TokenStream {
+            file: file.clone(),
+            tokens,
+            pos: 0,
+        };
+    }
+
+    pub fn file(&self) -> &Rc<SourceFile> {
+        return &self.file;
+    }
+
+    pub fn tokens(&self) -> &[Token] {
+        return &self.tokens;
+    }
+
+    /// The token `n` places after the cursor; `EOF` past the end
+    pub fn peek(&self, n: usize) -> &Token {
+        let i = (self.pos + n).min(self.tokens.len() - 1);
+        return &self.tokens[i];
+    }
+
+    /// Consumes the token under the cursor. The cursor never moves past `EOF`.
+    pub fn bump(&mut self) -> Token {
+        let tk = self.tokens[self.pos].clone();
+        if self.pos + 1 < self.tokens.len() {
+            self.pos += 1;
+        }
+        return tk;
+    }
+
+    pub fn position(&self) -> usize {
+        return self.pos;
+    }
+
+    pub fn reset(&mut self, pos: usize) {
+        self.pos = pos;
+    }
+
+    /// The last consumed token, if any
+    pub fn prev(&self) -> Option<&Token> {
+        if self.pos == 0 {
+            return None;
+        }
+        return Some(&self.tokens[self.pos - 1]);
+    }
+
+    /// Splits the operator under the cursor after its first character, as when
+    /// `>>` closes two template argument lists in `Vec<Vec<int>>`
+    pub fn split_operator(&mut self) {
+        let tk = &self.tokens[self.pos];
+        if let TokenKind::OPERATOR(op) = tk.kind {
+            let find = |s: &str| OPERATORS.iter().find(|x| **x == s).copied();
+            if let (Some(first), Some(rest)) = (find(&op[..1]), find(&op[1..])) {
+                let start = tk.span.start;
+                let first = Token {
+                    kind: TokenKind::OPERATOR(first),
+                    span: Span::new(&self.file, start, start + 1),
+                    leading: tk.leading.clone(),
+                };
+                let rest = Token {
+                    kind: TokenKind::OPERATOR(rest),
+                    span: Span::new(&self.file, start + 1, tk.span.end),
+                    leading: vec![],
+                };
+                self.tokens.splice(self.pos..self.pos + 1, vec![first, rest]);
+            }
+        }
+    }
+
+    /// Span from the token at index `start` to the last consumed one, or an
+    /// empty span at `start` if nothing was consumed since
+    pub fn span_from(&self, start: usize) -> Span {
+        let first = &self.tokens[start].span;
+        if self.pos <= start {
+            return Span::new(&self.file, first.start, first.start);
+        }
+        return first.to(&self.tokens[self.pos - 1].span);
+    }
+}
+
 fn strcmp(input: &str, pos: usize, s: &str) -> bool {
     if pos + s.len() > input.len() {
         return false;
     }
-
-    unsafe {
-        return input.get_unchecked(pos..pos + s.len()) == s;
-    }
+    // compared as bytes, `pos` may fall inside a multi-byte character
+    return &input.as_bytes()[pos..pos + s.len()] == s.as_bytes();
 }
 
-const KEYWORDS: [&str; 13] = [
-    "for", "if", "while", "loop", "until", "return", "continue", "break", "to", "downto", "fn", "else", "elif"
+const KEYWORDS: [&str; 26] = [
+    "for", "if", "while", "loop", "until", "return", "continue", "break", "to", "downto", "fn", "else", "elif",
+    "let", "mut", "in", "step", "do", "struct", "class", "impl", "interface", "enum", "match", "true", "false",
 ];
-const OPERATORS: [&str; 14] = [
-    "+", "-", "*", "/", "=", "**", "[", "]", "(", ")", "{", "}", ",", "->"
+const OPERATORS: [&str; 49] = [
+    // arithmetic
+    "+", "-", "*", "/", "%", "**",
+    // comparison
+    "==", "!=", "<", "<=", ">", ">=",
+    // logical
+    "&&", "||", "!",
+    // bitwise
+    "&", "|", "^", "<<", ">>", "~",
+    // assignment
+    "=", "+=", "-=", "*=", "/=", "%=", "**=", "&=", "|=", "^=", "<<=", ">>=",
+    // punctuation
+    "[", "]", "(", ")", "{", "}", ",", "->", "=>", ".", "..", ":", "::", "#[",
+    // operators spelled as words, only matched as whole identifiers
+    "as", "ref",
 ];
 
-pub fn next_token(input: &str, pos: usize) -> (Token, usize) {
+/// Finds the longest operator spelled at `pos`, so that `**=` wins over `**` and `*`
+fn longest_operator(input: &str, pos: usize) -> Option<&'static str> {
+    let mut found: Option<&'static str> = None;
+    for k in OPERATORS.iter() {
+        if k.starts_with(|c: char| c.is_alphabetic()) {
+            continue;
+        }
+        if strcmp(input, pos, k) && found.is_none_or(|x| x.len() < k.len()) {
+            found = Some(k);
+        }
+    }
+    return found;
+}
+
+fn is_doc_comment(input: &str, pos: usize) -> bool {
+    // `////...` is an ordinary comment again
+    return strcmp(input, pos, "///") && !strcmp(input, pos, "////");
+}
+
+fn line_end(input: &str, pos: usize) -> usize {
+    return match input[pos..].find('\n') {
+        Some(x) => pos + x,
+        None => input.len(),
+    };
+}
 
+/// Finds the end of the (nestable) block comment opened at `pos`
+fn block_comment_end(input: &str, pos: usize) -> Option<usize> {
+    let mut pos = pos + 2;
+    let mut depth = 1;
+    while pos < input.len() {
+        if strcmp(input, pos, "/*") {
+            depth += 1;
+            pos += 2;
+        } else if strcmp(input, pos, "*/") {
+            depth -= 1;
+            pos += 2;
+            if depth == 0 {
+                return Some(pos);
+            }
+        } else {
+            pos += 1;
+        }
+    }
+    return None;
+}
+
+/// Matches a run of whitespace or a single comment at `pos`: `// ...`, `# ...`
+/// or a nested `/* ... */`. Doc comments, which are tokens, and an unterminated
+/// `/*` are not trivia.
+fn trivia(input: &str, pos: usize) -> Option<(TriviaKind, usize)> {
+    let ch = get(input, pos);
+    if ch.is_whitespace() {
+        let mut end = pos;
+        while get(input, end).is_whitespace() {
+            end += get(input, end).len_utf8();
+        }
+        return Some((TriviaKind::WHITESPACE, end));
+    }
+    if strcmp(input, pos, "//") && !is_doc_comment(input, pos) {
+        return Some((TriviaKind::COMMENT, line_end(input, pos)));
+    }
+    if ch == '#' && get(input, pos + 1) != '[' {
+        // `#[` is left for attributes
+        return Some((TriviaKind::COMMENT, line_end(input, pos)));
+    }
+    if strcmp(input, pos, "/*") {
+        return block_comment_end(input, pos).map(|end| (TriviaKind::COMMENT, end));
+    }
+    return None;
+}
+
+/// Skips whitespace and comments
+pub fn skip_whitespace(input: &str, pos: usize) -> usize {
     let mut pos = pos;
-    while get(input, pos) == ' ' || get(input,pos) == '\t' || get(input,pos) == '\n' || get(input,pos) == '\r' {
-        pos += 1;
+    while let Some((_, end)) = trivia(input, pos) {
+        pos = end;
     }
+    return pos;
+}
+
+pub fn next_token(file: &Rc<SourceFile>, pos: usize) -> Token {
+    let input = file.text.as_str();
+    let start = skip_whitespace(input, pos);
+    let (kind, end) = token_kind(input, start);
+    return Token {
+        kind,
+        span: Span::new(file, start, end),
+        leading: vec![],
+    };
+}
+
+/// Concatenates the tokens with their trivia. For the tokens of a lossless
+/// lexer this gives back the input byte for byte.
+pub fn reprint(tokens: &[Token]) -> String {
+    let mut out = String::new();
+    for tk in tokens.iter() {
+        for t in tk.leading.iter() {
+            out += t.span.text();
+        }
+        out += tk.span.text();
+    }
+    return out;
+}
+
+fn token_kind(input: &str, pos: usize) -> (TokenKind, usize) {
+    let mut pos = pos;
     if pos >= input.len() {
         debug!("match EOF");
-        return (Token::EOF, pos);
+        return (TokenKind::EOF, pos);
     }
 
+    if is_doc_comment(input, pos) {
+        debug!("match doc comment");
+        let end = line_end(input, pos);
+        let text = input[pos + 3..end].trim_end_matches('\r');
+        let text = text.strip_prefix(' ').unwrap_or(text);
+        return (TokenKind::DOC_COMMENT(text.to_string()), end);
+    }
+    if strcmp(input, pos, "/*") {
+        return (TokenKind::ERROR("unterminated block comment".to_string()), input.len());
+    }
     if get(input, pos) == ';' {
         debug!("match semicolon");
-        return (Token::SEMICOLON, pos + 1);
+        return (TokenKind::SEMICOLON, pos + 1);
     }
-    for k in KEYWORDS.iter() {
-        if strcmp(input, pos, *k) {
-            debug!("match keyword");
-            return (Token::KEYWORD(k), pos + k.len());
-        }
+    if let Some(k) = longest_operator(input, pos) {
+        debug!("match operator");
+        return (TokenKind::OPERATOR(k), pos + k.len());
     }
-    for k in OPERATORS.iter() {
-        if strcmp(input, pos, *k) {
-            debug!("match operator");
-            return (Token::OPERATOR(k), pos + k.len());
-        }
+    if let Some(x) = string_literal(input, pos) {
+        debug!("match string");
+        return x;
     }
     let ch = get(input, pos);
-    if ch.is_alphabetic() || ch == '_' {
+    if ch == '\'' {
+        if let Some(x) = label(input, pos) {
+            debug!("match label");
+            return x;
+        }
+        debug!("match char");
+        return char_literal(input, pos);
+    }
+    if UnicodeXID::is_xid_start(ch) || ch == '_' {
         let mut buf = String::new();
         buf.push(ch);
-        pos += 1;
+        pos += ch.len_utf8();
 
         loop {
             let ch = get(input, pos);
-            if ch.is_alphanumeric() || ch == '_' {
+            if UnicodeXID::is_xid_continue(ch) {
                 buf.push(ch);
-                pos += 1;
+                pos += ch.len_utf8();
             } else {
                 break;
             }
         }
+        // keywords are only recognized as whole words, so `iffy` stays an identifier
+        if let Some(k) = KEYWORDS.iter().find(|k| **k == buf) {
+            debug!("match keyword");
+            return (TokenKind::KEYWORD(k), pos);
+        }
+        if let Some(k) = OPERATORS.iter().find(|k| **k == buf) {
+            debug!("match operator");
+            return (TokenKind::OPERATOR(k), pos);
+        }
         debug!("match identifier {}", buf);
-        return (Token::IDENTIFIER(Identifier(buf)), pos);
+        return (TokenKind::IDENTIFIER(Identifier(buf)), pos);
+    }
+    if ch.is_ascii_digit() {
+        debug!("match number");
+        return number_literal(input, pos);
     }
-    if ch.is_numeric() {
-        let mut n = (ch as u8 - '0' as u8) as f64;
+
+    return (TokenKind::ERROR(format!("unknown character {:?}", ch)), pos + ch.len_utf8());
+}
+
+/// Type suffixes accepted after a numeric literal
+const SUFFIXES: [&str; 13] = [
+    "i8", "i16", "i32", "i64", "isize", "u8", "u16", "u32", "u64", "usize", "ul", "f32", "f64"
+];
+
+/// Reads the digits of `radix` starting at `pos`, skipping `_` separators
+fn digits(input: &str, pos: usize, radix: u32, buf: &mut String) -> usize {
+    let mut pos = pos;
+    loop {
+        let ch = get(input, pos);
+        if ch == '_' {
+            pos += 1;
+        } else if ch.is_digit(radix) {
+            buf.push(ch);
+            pos += 1;
+        } else {
+            return pos;
+        }
+    }
+}
+
+/// Lexes an integer or float literal: `42`, `1_000`, `0x6C`, `0o744`, `0b1001`,
+/// `3.14`, `1e-9`, each optionally followed by a type suffix like `u8` or `f32`.
+fn number_literal(input: &str, start: usize) -> (TokenKind, usize) {
+    let radix = match input.get(start..start + 2) {
+        Some("0x") => 16,
+        Some("0o") => 8,
+        Some("0b") => 2,
+        _ => 10,
+    };
+    let mut pos = if radix == 10 { start } else { start + 2 };
+    let mut buf = String::new();
+    pos = digits(input, pos, radix, &mut buf);
+    let mut float = false;
+    if radix == 10 {
+        // `1.` followed by a non-digit is left alone, for `1..2`. In `t.0.1` this
+        // lexes the float `0.1`, which the parser splits into two element accesses
+        if get(input, pos) == '.' && get(input, pos + 1).is_ascii_digit() {
+            float = true;
+            buf.push('.');
+            pos = digits(input, pos + 1, 10, &mut buf);
+        }
+        if get(input, pos) == 'e' || get(input, pos) == 'E' {
+            let mut p = pos + 1;
+            let mut exponent = String::from("e");
+            if get(input, p) == '+' || get(input, p) == '-' {
+                exponent.push(get(input, p));
+                p += 1;
+            }
+            let before = exponent.len();
+            p = digits(input, p, 10, &mut exponent);
+            if exponent.len() == before {
+                return (TokenKind::ERROR("expected at least one digit in the exponent".to_string()), p);
+            }
+            float = true;
+            buf += &exponent;
+            pos = p;
+        }
+    }
+
+    let suffix_start = pos;
+    while get(input, pos).is_ascii_alphanumeric() || get(input, pos) == '_' {
         pos += 1;
-        let mut dot = false;
-        let mut ex = 0.1;
-        loop {
-            let ch = get(input, pos);
-            if ch.is_numeric() {
-                if dot {
-                    n += ex * (ch as u8 - '0' as u8) as f64;
-                    ex *= 0.1;
-                } else {
-                    n = n * 10.0 + (ch as u8 - '0' as u8) as f64;
-                }
-                pos += 1;
-            } else if ch == '.' {
-                if dot {
-                    error(&format!("warn: repeated dots in the same number at pos {}", pos));
-                }
-                dot = true;
-                pos += 1;
+    }
+    let suffix = &input[suffix_start..pos];
+    let kind = match radix {
+        16 => "hexadecimal",
+        8 => "octal",
+        2 => "binary",
+        _ => "decimal",
+    };
+    if buf.is_empty() {
+        return (TokenKind::ERROR(format!("no digits in the {} literal", kind)), pos);
+    }
+    if suffix.starts_with(|x: char| x.is_ascii_digit()) {
+        return (TokenKind::ERROR(format!("invalid digit `{}` in the {} literal", &suffix[..1], kind)), pos);
+    }
+    if !suffix.is_empty() && !SUFFIXES.contains(&suffix) {
+        return (TokenKind::ERROR(format!("invalid suffix `{}` for a number literal", suffix)), pos);
+    }
+
+    let float_type = FloatType::from_name(suffix);
+    if float || float_type.is_some() {
+        if radix != 10 {
+            return (TokenKind::ERROR(format!("{} float literals are not supported", kind)), pos);
+        }
+        if !suffix.is_empty() && float_type.is_none() {
+            return (TokenKind::ERROR(format!("invalid suffix `{}` for a float literal", suffix)), pos);
+        }
+        let x: f64 = buf.parse().unwrap();
+        if float_type == Some(FloatType::F32) && (x as f32).is_infinite() || x.is_infinite() {
+            return (TokenKind::ERROR("float literal is out of range".to_string()), pos);
+        }
+        return (TokenKind::FLOAT(x, float_type), pos);
+    }
+
+    let int_type = IntType::from_name(suffix);
+    let x = match u64::from_str_radix(&buf, radix) {
+        Ok(x) => x,
+        Err(_) => return (TokenKind::ERROR("integer literal is too large, it does not fit in u64".to_string()), pos),
+    };
+    if let Some(t) = int_type {
+        // the minimum of a signed type is written negated, so the checker
+        // reports a literal one past its maximum unless it is negated
+        if !t.contains(x as i128) && !t.contains(-(x as i128)) {
+            return (TokenKind::ERROR(format!("literal out of range for {}, whose maximum is {}", t, t.max())), pos);
+        }
+    }
+    return (TokenKind::INTEGER(x, int_type), pos);
+}
+
+/// Reads the escape sequence following the `\` at `pos`, returning the
+/// escaped character and the position after the sequence.
+fn escape(input: &str, pos: usize) -> (Result<char, String>, usize) {
+    let ch = match input[pos + 1..].chars().next() {
+        Some(x) => x,
+        None => return (Err("unterminated escape sequence".to_string()), input.len()),
+    };
+    let pos = pos + 1 + ch.len_utf8();
+    match ch {
+        'n' => (Ok('\n'), pos),
+        't' => (Ok('\t'), pos),
+        'r' => (Ok('\r'), pos),
+        '0' => (Ok('\0'), pos),
+        '\\' => (Ok('\\'), pos),
+        '"' => (Ok('"'), pos),
+        '\'' => (Ok('\''), pos),
+        'x' => {
+            let digits = input.get(pos..pos + 2).unwrap_or("");
+            if digits.len() != 2 || !digits.chars().all(|x| x.is_ascii_hexdigit()) {
+                return (Err("`\\x` must be followed by two hexadecimal digits".to_string()), pos);
+            }
+            let code = u8::from_str_radix(digits, 16).unwrap();
+            if code > 0x7F {
+                return (Err(format!("`\\x{}` is out of range, it must be at most `\\x7F`", digits)), pos + 2);
+            }
+            (Ok(code as char), pos + 2)
+        }
+        'u' => {
+            if get(input, pos) != '{' {
+                return (Err("`\\u` must be followed by `{`".to_string()), pos);
+            }
+            let close = match input[pos..].char_indices().take(8).find(|x| x.1 == '}') {
+                Some((x, _)) => pos + x,
+                None => return (Err("unterminated unicode escape, expected `}`".to_string()), pos + 1),
+            };
+            let digits = &input[pos + 1..close];
+            let code = if digits.is_empty() || !digits.chars().all(|x| x.is_ascii_hexdigit()) {
+                None
             } else {
-                break;
+                u32::from_str_radix(digits, 16).ok()
+            };
+            match code.and_then(std::char::from_u32) {
+                Some(x) => (Ok(x), close + 1),
+                None => (Err(format!("invalid unicode escape `\\u{{{}}}`", digits)), close + 1),
             }
         }
-        debug!("match number");
-        if dot {
-            return (Token::FLOAT(n), pos);
-        } else {
-            return (Token::INTEGER(n as i32), pos);
+        _ => (Err(format!("unknown escape sequence `\\{}`", ch)), pos),
+    }
+}
+
+/// Lexes `"..."`, `c"..."`, and their raw forms `r"..."`, `r#"..."#`, `cr"..."`
+/// starting at `pos`, or returns `None` if there is no string literal there.
+fn string_literal(input: &str, pos: usize) -> Option<(TokenKind, usize)> {
+    let mut p = pos;
+    let c = get(input, p) == 'c';
+    if c {
+        p += 1;
+    }
+    let raw = get(input, p) == 'r';
+    if raw {
+        p += 1;
+    }
+    let mut hashes = 0;
+    while raw && get(input, p) == '#' {
+        hashes += 1;
+        p += 1;
+    }
+    if get(input, p) != '"' {
+        return None;
+    }
+    p += 1;
+
+    let mut buf = String::new();
+    let mut err = None;
+    let end;
+    loop {
+        let ch = match input[p..].chars().next() {
+            Some(x) => x,
+            None => {
+                let what = if raw { "raw string" } else { "string" };
+                return Some((TokenKind::ERROR(format!("unterminated {} literal", what)), input.len()));
+            }
+        };
+        if ch == '"' && strcmp(input, p + 1, &"#".repeat(hashes)) {
+            end = p + 1 + hashes;
+            break;
+        }
+        if ch == '\\' && !raw {
+            let (x, next) = escape(input, p);
+            match x {
+                Ok(x) => buf.push(x),
+                Err(e) => err = err.or(Some(e)),
+            }
+            p = next;
+            continue;
         }
+        buf.push(ch);
+        p += ch.len_utf8();
+    }
+    if let Some(e) = err {
+        return Some((TokenKind::ERROR(e), end));
     }
+    if c {
+        if buf.contains('\0') {
+            return Some((TokenKind::ERROR("c strings cannot contain a nul character".to_string()), end));
+        }
+        return Some((TokenKind::CSTRING(buf), end));
+    }
+    return Some((TokenKind::STRING(buf), end));
+}
 
+/// Lexes `'name`, unless it is a character literal like `'a'`
+fn label(input: &str, pos: usize) -> Option<(TokenKind, usize)> {
+    let first = get(input, pos + 1);
+    if !UnicodeXID::is_xid_start(first) && first != '_' {
+        return None;
+    }
+    let mut p = pos + 1 + first.len_utf8();
+    if get(input, p) == '\'' {
+        return None;
+    }
+    while UnicodeXID::is_xid_continue(get(input, p)) {
+        p += get(input, p).len_utf8();
+    }
+    return Some((TokenKind::LABEL(input[pos + 1..p].to_string()), p));
+}
 
-    return (Token::ERROR, pos);
+/// Lexes a character literal `'x'` or `'\n'` starting at the quote at `pos`
+fn char_literal(input: &str, pos: usize) -> (TokenKind, usize) {
+    let p = pos + 1;
+    let (ch, p) = match input[p..].chars().next() {
+        Some('\\') => escape(input, p),
+        Some('\'') => return (TokenKind::ERROR("empty character literal".to_string()), p + 1),
+        Some('\n') | None => return (TokenKind::ERROR("unterminated character literal".to_string()), p),
+        Some(x) => (Ok(x), p + x.len_utf8()),
+    };
+    if get(input, p) != '\'' {
+        return (TokenKind::ERROR("unterminated character literal".to_string()), p);
+    }
+    return match ch {
+        Ok(x) => (TokenKind::CHAR(x), p + 1),
+        Err(e) => (TokenKind::ERROR(e), p + 1),
+    };
 }
@@ -1,3 +1,4 @@
+#![allow(clippy::needless_return)]
 #[macro_use]
 extern crate log;
 
@@ -7,10 +8,20 @@ use std::io::Read;
 #[macro_use]
 pub mod util;
 
+pub mod span;
+
+pub mod types;
+
+pub mod diagnostics;
+
 pub mod lexer;
 
 pub mod parser;
 
+pub mod derive;
+
+pub mod checker;
+
 pub mod runtime;
 
 extern crate clap;
@@ -23,17 +34,61 @@ fn main() {
         .author("Jack Quinn")
         .about("A simple calculator but supporting complex logic")
         .args_from_usage("-i, --input=[FILE] 'source code file'")
+        .args_from_usage("--tokens 'print the tokens of the input and exit'")
+        .args_from_usage("--reprint 'print the input back from its lossless tokens and exit'")
 //        .args_from_usage("-o, --output=[FILE] 'output file'")
         .get_matches();
 
 //    let buf = String::from("fn foo(a){return 6+a;} a = foo(7); a = a + a; while a { a = a - 1;print(a);}\n");
-    let mut buf = String::new();
-    let mut file = File::open(matches.value_of("input").unwrap_or("/dev/stdin")).expect("Cannot open file");
-    file.read_to_string(&mut buf).expect("Cannot read file");
-    let v = parser::parse(&buf, 0);
+    let path = matches.value_of("input").unwrap_or("/dev/stdin");
+    let mut diags = diagnostics::Diagnostics::new();
+    let mut buf = vec![];
+    if let Err(e) = File::open(path).and_then(|mut file| file.read_to_end(&mut buf)) {
+        diags.emit(diagnostics::Diagnostic::error(&format!("cannot read `{}`: {}", path, e)));
+        eprint!("{}", diags.render());
+        std::process::exit(1);
+    }
+    let source = match String::from_utf8(buf) {
+        Ok(x) => span::SourceFile::new(path, x),
+        Err(e) => {
+            // shown with the invalid bytes replaced, as `\u{FFFD}`
+            let at = e.utf8_error().valid_up_to();
+            let source = span::SourceFile::new(path, String::from_utf8_lossy(e.as_bytes()).into_owned());
+            diags.emit(diagnostics::Diagnostic::error(&format!("`{}` is not valid UTF-8", path))
+                .with_label(&span::Span::new(&source, at, at + '\u{FFFD}'.len_utf8()), "invalid UTF-8 here"));
+            eprint!("{}", diags.render());
+            std::process::exit(1);
+        }
+    };
+    if matches.is_present("reprint") {
+        let tokens: Vec<lexer::Token> = lexer::Lexer::lossless(&source).collect();
+        print!("{}", lexer::reprint(&tokens));
+        return;
+    }
+    let mut tokens = lexer::TokenStream::new(&source, &mut diags);
+    if matches.is_present("tokens") {
+        for tk in tokens.tokens() {
+            println!("{:?} {:?}", tk.span, tk.kind);
+        }
+        eprint!("{}", diags.render());
+        std::process::exit(if diags.has_errors() { 1 } else { 0 });
+    }
+    // the interfaces that can be derived, declared before the program
+    let prelude = span::SourceFile::new("<prelude>", include_str!("prelude.alg").to_string());
+    let mut v = parser::parse(&mut lexer::TokenStream::new(&prelude, &mut diags), &mut diags);
+    v.0.extend(parser::parse(&mut tokens, &mut diags).0);
     // todo log system
     debug!("{:#?}", v);
-    runtime::run_code(&v);
+    if !diags.has_errors() {
+        checker::check(&v, &mut diags);
+    }
+    if !diags.has_errors() {
+        runtime::run_code(&v, &mut diags);
+    }
+    eprint!("{}", diags.render());
+    if diags.has_errors() {
+        std::process::exit(1);
+    }
 
 }
 
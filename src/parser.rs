@@ -1,12 +1,16 @@
+use super::derive;
+use super::diagnostics::*;
 use super::lexer::*;
-use super::util::*;
+use super::span::*;
+use super::types::*;
 use std::fmt::{Debug, Error, Formatter};
+use std::rc::Rc;
 
 
 #[derive(PartialEq, Clone)]
 pub struct FuncCall {
     pub func_name: Identifier,
-    pub arg_list: Vec<Value>,
+    pub arg_list: Vec<Expr>,
 }
 
 impl Debug for FuncCall {
@@ -19,97 +23,572 @@ impl Debug for FuncCall {
     }
 }
 
+/// Length of an array type
+#[allow(non_camel_case_types)]
+#[derive(PartialEq, Clone)]
+pub enum ArrayLen {
+    FIXED(u64),
+    /// `[T]`, `[T; _]`, `T[]` or `T[_]`: any length
+    ANY,
+    /// `[T; n]` or `T[n]`: any length, bound to the immutable variable `n`
+    BIND(Identifier),
+}
+
+#[allow(non_camel_case_types)]
+#[derive(PartialEq, Clone)]
+pub enum TypeExpr {
+    /// `i8` to `u64` and their aliases like `int`, `byte` or `usize`
+    INT(IntType),
+    /// `f32`, `f64`, `float` or `double`
+    FLOAT(FloatType),
+    CHAR,
+    STR,
+    CSTR,
+    /// the return type of functions without `->`
+    VOID,
+    /// `_`, and the return type of lambdas without `->`: whatever the value is
+    INFER,
+    /// `*T`
+    POINTER(Box<Type>),
+    /// `&T` or `ref T`
+    REF(Box<Type>),
+    /// Rust-like `[T; N]`, with the length stored in front of the elements
+    ARRAY(Box<Type>, ArrayLen),
+    /// C-like `T[N]`, without a stored length
+    C_ARRAY(Box<Type>, ArrayLen),
+    /// `()`, `(T,)` or `(T1, T2, ...)`
+    TUPLE(Vec<Type>),
+    /// `fn(A, B) -> R`
+    FUNCTION(Vec<Type>, Option<Box<Type>>),
+    /// any other type by name, with its template arguments as in `Map<str, int>`
+    NAMED(Identifier, Vec<Type>),
+    /// placeholder for a type that failed to parse
+    ERROR,
+}
+
+pub type Type = Spanned<TypeExpr>;
+
+fn fmt_list(f: &mut Formatter<'_>, list: &[Type]) -> Result<(), Error> {
+    for (i, x) in list.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{}", x.node)?;
+    }
+    return Ok(());
+}
+
+impl std::fmt::Display for TypeExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            TypeExpr::INT(t) => write!(f, "{}", t),
+            TypeExpr::FLOAT(t) => write!(f, "{}", t),
+            TypeExpr::CHAR => f.write_str("char"),
+            TypeExpr::STR => f.write_str("str"),
+            TypeExpr::CSTR => f.write_str("cstr"),
+            TypeExpr::VOID => f.write_str("void"),
+            TypeExpr::INFER => f.write_str("_"),
+            TypeExpr::POINTER(x) => write!(f, "*{}", x.node),
+            TypeExpr::REF(x) => write!(f, "ref {}", x.node),
+            TypeExpr::ARRAY(x, ArrayLen::FIXED(n)) => write!(f, "[{}; {}]", x.node, n),
+            TypeExpr::ARRAY(x, ArrayLen::ANY) => write!(f, "[{}]", x.node),
+            TypeExpr::ARRAY(x, ArrayLen::BIND(n)) => write!(f, "[{}; {}]", x.node, n.0),
+            TypeExpr::C_ARRAY(x, ArrayLen::FIXED(n)) => write!(f, "{}[{}]", x.node, n),
+            TypeExpr::C_ARRAY(x, ArrayLen::ANY) => write!(f, "{}[]", x.node),
+            TypeExpr::C_ARRAY(x, ArrayLen::BIND(n)) => write!(f, "{}[{}]", x.node, n.0),
+            TypeExpr::TUPLE(list) => {
+                f.write_str("(")?;
+                fmt_list(f, list)?;
+                f.write_str(if list.len() == 1 { ",)" } else { ")" })
+            }
+            TypeExpr::FUNCTION(args, ret) => {
+                f.write_str("fn(")?;
+                fmt_list(f, args)?;
+                f.write_str(")")?;
+                match ret {
+                    Some(x) => write!(f, " -> {}", x.node),
+                    None => Ok(()),
+                }
+            }
+            TypeExpr::NAMED(id, args) => {
+                f.write_str(&id.0)?;
+                if !args.is_empty() {
+                    f.write_str("<")?;
+                    fmt_list(f, args)?;
+                    f.write_str(">")?;
+                }
+                return Ok(());
+            }
+            TypeExpr::ERROR => f.write_str("<error>"),
+        }
+    }
+}
+
+impl Debug for TypeExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
 #[derive(PartialEq, Debug, Clone)]
-pub struct ArgDecl(pub Identifier);
+pub struct ArgDecl {
+    /// the name of the argument, or a tuple pattern destructuring it
+    pub pattern: Spanned<Pattern>,
+    pub mutable: bool,
+    pub ty: Option<Type>,
+}
+
+impl ArgDecl {
+    pub fn is_self(&self) -> bool {
+        return matches!(&self.pattern.node, Pattern::BIND(id) if id.0 == "self");
+    }
+}
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct FuncDecl {
     pub func_name: Identifier,
-    pub arg_list: Vec<ArgDecl>,
+    pub arg_list: Vec<Spanned<ArgDecl>>,
+    /// the type after `->`, `void` if there is none
+    pub ret: Type,
     pub body: Block,
+    /// lines of the `///` comments written before the declaration
+    pub doc: Vec<String>,
 }
 
+#[derive(PartialEq, Debug, Clone)]
+pub struct FieldDecl {
+    pub id: Identifier,
+    pub ty: Type,
+}
+
+/// `struct Name { field: type, ... }`, also spelled `class`
+#[derive(PartialEq, Debug, Clone)]
+pub struct StructDecl {
+    pub name: Identifier,
+    pub fields: Vec<Spanned<FieldDecl>>,
+    /// the interfaces of `#[derive(...)]`
+    pub derive: Vec<Spanned<Identifier>>,
+    pub doc: Vec<String>,
+}
+
+/// `interface Name { fn m(self) -> int; ... }`
+#[derive(PartialEq, Debug, Clone)]
+pub struct InterfaceDecl {
+    pub name: Identifier,
+    /// methods declared without a body, which every implementation defines
+    pub required: Vec<Spanned<Rc<FuncDecl>>>,
+    /// methods with a default body, which implementations may override
+    pub provided: Vec<Spanned<Rc<FuncDecl>>>,
+    pub doc: Vec<String>,
+}
+
+/// A variant of an enum, with the types of its payload
+#[derive(PartialEq, Debug, Clone)]
+pub struct VariantDecl {
+    pub name: Identifier,
+    pub fields: Vec<Type>,
+}
+
+/// `enum Name { A, B(type, ...), ... }`
+#[derive(PartialEq, Debug, Clone)]
+pub struct EnumDecl {
+    pub name: Identifier,
+    pub variants: Vec<Spanned<VariantDecl>>,
+    pub doc: Vec<String>,
+}
+
+impl EnumDecl {
+    pub fn variant(&self, name: &Identifier) -> Option<usize> {
+        return self.variants.iter().position(|x| x.node.name == *name);
+    }
+}
+
+/// `impl Name { fn ... }` or `impl Interface for Name { fn ... }`. Methods
+/// take `self` as their first argument.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Impl {
+    pub interface: Option<Spanned<Identifier>>,
+    pub target: Spanned<Identifier>,
+    pub methods: Vec<Spanned<Rc<FuncDecl>>>,
+}
+
+/// `target = val`, where `target` is a variable, a field, an index or `*ref`
 #[derive(PartialEq, Clone)]
 pub struct Assign {
-    pub id: Identifier,
-    pub val: Value,
+    pub target: Box<Expr>,
+    pub val: Box<Expr>,
 }
 
 impl Debug for Assign {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
-        self.id.fmt(f)?;
+        self.target.fmt(f)?;
         f.write_str(" = ")?;
         self.val.fmt(f)?;
         return Ok(());
     }
 }
 
+/// `target op= val`, like `a[i] += 1`, which assigns `target op val` to the
+/// place `target` evaluated once
+#[derive(PartialEq, Clone)]
+pub struct CompoundAssign {
+    /// the operator applied, like `+`
+    pub op: Identifier,
+    pub target: Box<Expr>,
+    pub val: Box<Expr>,
+}
+
+impl Debug for CompoundAssign {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{:?} {}= {:?}", self.target, self.op.0, self.val)
+    }
+}
+
+/// `lhs && rhs` or `lhs || rhs`, evaluating `rhs` only when it decides the result
+#[derive(PartialEq, Clone)]
+pub struct Logical {
+    pub op: &'static str,
+    pub lhs: Box<Expr>,
+    pub rhs: Box<Expr>,
+}
+
+impl Debug for Logical {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "({:?} {} {:?})", self.lhs, self.op, self.rhs)
+    }
+}
+
+/// A call of anything but a plain name, like `f(1)(2)`; those are `FuncCall`s
+#[derive(PartialEq, Clone)]
+pub struct Call {
+    pub callee: Box<Expr>,
+    pub arg_list: Vec<Expr>,
+}
+
+impl Debug for Call {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        self.callee.fmt(f)?;
+        self.arg_list.fmt(f)?;
+        return Ok(());
+    }
+}
+
 #[allow(non_camel_case_types)]
 #[derive(PartialEq, Clone)]
 pub enum Value {
     VAR(Identifier),
-    FLOAT(f64),
-    INT(i32),
+    FLOAT(f64, Option<FloatType>),
+    INT(u64, Option<IntType>),
+    /// `true` or `false`, which are the `i32` values 1 and 0
     BOOL(bool),
+    STRING(String),
+    CSTRING(String),
+    CHAR(char),
+    /// a named function or an operator applied to its operands, as in `+[a, b]`
     FUNC_CALL(FuncCall),
+    CALL(Call),
+    ASSIGN(Assign),
+    COMPOUND_ASSIGN(CompoundAssign),
+    LOGICAL(Logical),
+    /// `value as type`
+    CAST(Box<Expr>, Type),
+    /// `value[index]`
+    INDEX(Box<Expr>, Box<Expr>),
+    /// `value[from..to]`, where both bounds are optional
+    SLICE(Box<Expr>, Option<Box<Expr>>, Option<Box<Expr>>),
+    /// `[a, b, c]`
+    ARRAY(Vec<Expr>),
+    /// `[value; count]`
+    REPEAT(Box<Expr>, Box<Expr>),
+    /// `(a, b)` or `(a,)`; `()` is the empty tuple
+    TUPLE(Vec<Expr>),
+    /// `value.0`, an element of a tuple
+    ELEMENT(Box<Expr>, usize),
+    /// `value.field`, or `value.method` when called
+    FIELD(Box<Expr>, Identifier),
+    /// `Type::function`
+    PATH(Identifier, Identifier),
+    /// a `loop` used as a value, as in `let x = loop { break 1; };`
+    LOOP(Loop),
+    /// a `match` used as a value
+    MATCH(Box<Match>),
+    /// `|x| x + 1` or `fn(x) { ... }`, named `lambda`
+    LAMBDA(Rc<FuncDecl>),
+    /// `&value`
+    REF(Box<Expr>),
+    /// `*value`
+    DEREF(Box<Expr>),
+    /// placeholder for an expression that failed to parse
+    ERROR,
 }
 
 impl Debug for Value {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
         match self {
             Value::VAR(x) => x.fmt(f),
-            Value::FLOAT(x) => x.fmt(f),
+            Value::FLOAT(x, t) => write!(f, "{:?}{}", x, t.map_or("", |x| x.name())),
             Value::FUNC_CALL(x) => x.fmt(f),
-            Value::INT(x) => x.fmt(f),
+            Value::CALL(x) => x.fmt(f),
+            Value::ASSIGN(x) => x.fmt(f),
+            Value::COMPOUND_ASSIGN(x) => x.fmt(f),
+            Value::LOGICAL(x) => x.fmt(f),
+            Value::CAST(x, t) => write!(f, "({:?} as {})", x, t.node),
+            Value::INDEX(x, i) => write!(f, "{:?}[{:?}]", x, i),
+            Value::SLICE(x, from, to) => {
+                write!(f, "{:?}[", x)?;
+                if let Some(from) = from {
+                    from.fmt(f)?;
+                }
+                f.write_str("..")?;
+                if let Some(to) = to {
+                    to.fmt(f)?;
+                }
+                f.write_str("]")
+            }
+            Value::ARRAY(x) => x.fmt(f),
+            Value::REPEAT(x, n) => write!(f, "[{:?}; {:?}]", x, n),
+            Value::TUPLE(x) => {
+                f.write_str("(")?;
+                for (i, x) in x.iter().enumerate() {
+                    write!(f, "{}{:?}", if i > 0 { ", " } else { "" }, x)?;
+                }
+                f.write_str(if x.len() == 1 { ",)" } else { ")" })
+            }
+            Value::ELEMENT(x, i) => write!(f, "{:?}.{}", x, i),
+            Value::FIELD(x, id) => write!(f, "{:?}.{}", x, id.0),
+            Value::PATH(ty, id) => write!(f, "{}::{}", ty.0, id.0),
+            Value::LOOP(x) => x.fmt(f),
+            Value::MATCH(x) => x.fmt(f),
+            Value::LAMBDA(x) => x.fmt(f),
+            Value::REF(x) => write!(f, "&{:?}", x),
+            Value::DEREF(x) => write!(f, "*{:?}", x),
+            Value::INT(x, t) => write!(f, "{}{}", x, t.map_or("", |x| x.name())),
             Value::BOOL(x) => x.fmt(f),
+            Value::STRING(x) => x.fmt(f),
+            Value::CSTRING(x) => write!(f, "c{:?}", x),
+            Value::CHAR(x) => x.fmt(f),
+            Value::ERROR => f.write_str("<error>"),
         }
     }
 }
 
+pub type Expr = Spanned<Value>;
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct If {
-    pub cond: Vec<Value>,
+    pub cond: Vec<Expr>,
     pub then: Vec<Block>,
 }
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct While {
-    pub cond: Value,
+    pub label: Option<Spanned<Identifier>>,
+    pub cond: Expr,
+    pub then: Block,
+}
+
+/// `loop { ... }`, which only ends by `break`, possibly with the value of the loop
+#[derive(PartialEq, Debug, Clone)]
+pub struct Loop {
+    pub label: Option<Spanned<Identifier>>,
+    pub then: Block,
+}
+
+/// `do { ... } until cond;`, running the body at least once
+#[derive(PartialEq, Debug, Clone)]
+pub struct DoUntil {
+    pub label: Option<Spanned<Identifier>>,
+    pub then: Block,
+    pub cond: Expr,
+}
+
+/// `break ['label] [value];`
+#[derive(PartialEq, Debug, Clone)]
+pub struct Break {
+    pub label: Option<Spanned<Identifier>>,
+    pub value: Option<Expr>,
+}
+
+/// `from to|downto to [step step]`, counting in steps of 1 by default
+#[derive(PartialEq, Debug, Clone)]
+pub struct CountedLoop {
+    pub from: Expr,
+    pub to: Expr,
+    pub downto: bool,
+    pub step: Option<Expr>,
+}
+
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+#[derive(PartialEq, Debug, Clone)]
+pub enum Iteration {
+    /// `for i = 0 to n`, including both bounds
+    COUNTED(Box<CountedLoop>),
+    /// `for x in iterable`
+    IN(Expr),
+}
+
+/// A `for` loop; `var` is only visible in the body
+#[derive(PartialEq, Debug, Clone)]
+pub struct For {
+    pub label: Option<Spanned<Identifier>>,
+    pub var: Spanned<Pattern>,
+    /// `for mut x`, which lets the body assign to `var`
+    pub mutable: bool,
+    pub iter: Iteration,
     pub then: Block,
 }
 
+/// What an arm of `match` runs
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+#[derive(PartialEq, Debug, Clone)]
+pub enum ArmBody {
+    /// `pattern => value,`, whose value is the value of the `match`
+    EXPR(Expr),
+    /// `pattern => { ... }`, which has no value
+    BLOCK(Block),
+}
+
+/// `pattern [if guard] => body`
+#[derive(PartialEq, Debug, Clone)]
+pub struct Arm {
+    pub pattern: Spanned<Pattern>,
+    pub guard: Option<Expr>,
+    pub body: ArmBody,
+}
+
+/// `match value { arm, ... }`, running the first arm whose pattern matches
+#[derive(PartialEq, Debug, Clone)]
+pub struct Match {
+    pub value: Expr,
+    pub arms: Vec<Spanned<Arm>>,
+}
+
+/// `return [value];`, where a void function returns no value
 #[derive(PartialEq, Debug, Clone)]
-pub struct Return(pub Value);
+pub struct Return(pub Option<Expr>);
+
+/// What a declaration binds its value to, or what an arm of `match` matches
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+#[derive(PartialEq, Clone)]
+pub enum Pattern {
+    BIND(Identifier),
+    /// `_`, which drops the value
+    WILDCARD,
+    /// a number, string, character or boolean, numbers possibly negated; only in `match`
+    LITERAL(Expr),
+    /// `Enum::Variant(pattern, ...)`, with no patterns for a variant without
+    /// payload; only in `match`
+    VARIANT(Identifier, Identifier, Vec<Spanned<Pattern>>),
+    /// `(pattern, ...)`, destructuring a tuple
+    TUPLE(Vec<Spanned<Pattern>>),
+}
+
+impl Debug for Pattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            Pattern::BIND(x) => x.fmt(f),
+            Pattern::WILDCARD => f.write_str("_"),
+            Pattern::LITERAL(x) => x.fmt(f),
+            Pattern::VARIANT(ty, variant, fields) => {
+                write!(f, "{}::{}", ty.0, variant.0)?;
+                if !fields.is_empty() {
+                    f.write_str("(")?;
+                    for (i, x) in fields.iter().enumerate() {
+                        write!(f, "{}{:?}", if i > 0 { ", " } else { "" }, x)?;
+                    }
+                    f.write_str(")")?;
+                }
+                return Ok(());
+            }
+            Pattern::TUPLE(elements) => {
+                f.write_str("(")?;
+                for (i, x) in elements.iter().enumerate() {
+                    write!(f, "{}{:?}", if i > 0 { ", " } else { "" }, x)?;
+                }
+                f.write_str(if elements.len() == 1 { ",)" } else { ")" })
+            }
+        }
+    }
+}
+
+/// `let [mut] pattern [: type] [= init];`
+#[derive(PartialEq, Clone)]
+pub struct Let {
+    pub pattern: Spanned<Pattern>,
+    pub mutable: bool,
+    pub ty: Option<Type>,
+    pub init: Option<Expr>,
+}
+
+impl Debug for Let {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        f.write_str(if self.mutable { "let mut " } else { "let " })?;
+        self.pattern.fmt(f)?;
+        if let Some(ty) = &self.ty {
+            write!(f, ": {}", ty.node)?;
+        }
+        if let Some(init) = &self.init {
+            f.write_str(" = ")?;
+            init.fmt(f)?;
+        }
+        return Ok(());
+    }
+}
 
 #[allow(non_camel_case_types)]
 #[derive(PartialEq, Clone)]
 pub enum Statement {
-    ASSIGNMENT(Assign),
+    LET(Let),
     RETURN(Return),
-    EXPRESSION(Value),
-    FUNC_DECL(FuncDecl),
+    EXPRESSION(Expr),
+    FUNC_DECL(Rc<FuncDecl>),
+    STRUCT(Rc<StructDecl>),
+    INTERFACE(Rc<InterfaceDecl>),
+    ENUM(Rc<EnumDecl>),
+    IMPL(Impl),
     IF(If),
     WHILE(While),
+    FOR(For),
+    LOOP(Loop),
+    DO_UNTIL(DoUntil),
+    MATCH(Match),
+    BREAK(Break),
+    /// `continue ['label];`
+    CONTINUE(Option<Spanned<Identifier>>),
     NOTHING,
+    /// placeholder for the tokens skipped while recovering from a syntax error
+    ERROR,
 }
 
 impl Debug for Statement {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
         match self {
-            Statement::ASSIGNMENT(x) => x.fmt(f),
+            Statement::LET(x) => x.fmt(f),
             Statement::RETURN(x) => x.fmt(f),
             Statement::EXPRESSION(x) => x.fmt(f),
             Statement::FUNC_DECL(x) => x.fmt(f),
+            Statement::STRUCT(x) => x.fmt(f),
+            Statement::INTERFACE(x) => x.fmt(f),
+            Statement::ENUM(x) => x.fmt(f),
+            Statement::IMPL(x) => x.fmt(f),
             Statement::IF(x) => x.fmt(f),
             Statement::WHILE(x) => x.fmt(f),
+            Statement::FOR(x) => x.fmt(f),
+            Statement::LOOP(x) => x.fmt(f),
+            Statement::DO_UNTIL(x) => x.fmt(f),
+            Statement::MATCH(x) => x.fmt(f),
+            Statement::BREAK(x) => x.fmt(f),
+            Statement::CONTINUE(x) => write!(f, "continue {:?}", x),
             Statement::NOTHING => f.write_str(";"),
+            Statement::ERROR => f.write_str("<error>;"),
         }
     }
 }
 
+pub type Stmt = Spanned<Statement>;
+
 #[derive(PartialEq, Clone)]
-pub struct Block(pub Vec<Statement>);
+pub struct Block(pub Vec<Stmt>);
 
 impl Debug for Block {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
@@ -119,374 +598,1691 @@ impl Debug for Block {
     }
 }
 
-fn try_eat_keyword(input: &str, pos: usize, keyword: &str) -> Option<usize> {
-    let (tk, pos) = next_token(input, pos);
-    if let Token::KEYWORD(s) = tk {
-        if s == keyword {
-            return Some(pos);
-        }
-    }
-    return None;
+
+/// Reports that `what` was expected at the token under the cursor
+fn expected(ts: &TokenStream, diags: &mut Diagnostics, what: &str) {
+    unexpected(ts.peek(0), diags, what);
 }
 
+/// Whether the last syntax error was reported at `tk`, which explains what
+/// else is wrong there
+fn reported_at(tk: &Token, diags: &Diagnostics) -> bool {
+    return diags.items.last().and_then(|x| x.primary_span()).is_some_and(|x| *x == tk.span);
+}
 
-fn try_eat_operator(input: &str, pos: usize, operator: &str) -> Option<usize> {
-    let (tk, pos) = next_token(input, pos);
-    if let Token::OPERATOR(s) = tk {
-        if s == operator {
-            return Some(pos);
-        }
+/// Reports finding `tk` where `what` was expected. Invalid tokens have been
+/// reported by the lexer already, and so has any token reported before.
+fn unexpected(tk: &Token, diags: &mut Diagnostics, what: &str) {
+    if let TokenKind::ERROR(_) = tk.kind {
+        return;
     }
-    return None;
+    if reported_at(tk, diags) {
+        return;
+    }
+    diags.emit(Diagnostic::error(&format!("expected {}, found {}", what, tk.kind))
+        .with_label(&tk.span, &format!("expected {}", what)));
 }
 
-fn try_eat_semicolon(input: &str, pos: usize) -> Option<usize> {
-    if let (Token::SEMICOLON, pos) = next_token(input, pos) {
-        return Some(pos);
+/// Reports a missing expression and stands in an error node for it
+fn missing_expression(ts: &mut TokenStream, diags: &mut Diagnostics, what: &str) -> Expr {
+    expected(ts, diags, what);
+    let span = ts.peek(0).span.clone();
+    // an invalid token is taken as the erroneous expression itself
+    if let TokenKind::ERROR(_) = ts.peek(0).kind {
+        ts.bump();
     }
-    return None;
+    return Spanned::new(Value::ERROR, span);
 }
 
-fn assignment_stmt(input: &str, pos: usize) -> Option<(Assign, usize)> {
-    if let (Token::IDENTIFIER(id), pos) = next_token(input, pos) {
-        let pos = try_eat_operator(input, pos, "=");
-        if pos.is_some() {
-            let r = expression(input, pos.unwrap());
-            if r.is_some() {
-                let (val, pos) = r.unwrap();
-                return Some((Assign { id, val }, pos));
+/// Skips tokens after a syntax error until a point where parsing can resume:
+/// just after a `;` or a balanced `{ ... }`, or just before an unmatched `}`.
+fn synchronize(ts: &mut TokenStream) {
+    let mut depth = 0;
+    loop {
+        match ts.peek(0).kind {
+            TokenKind::EOF => return,
+            TokenKind::SEMICOLON if depth == 0 => {
+                ts.bump();
+                return;
             }
-        } else {
-            return None;
+            TokenKind::OPERATOR("{") => depth += 1,
+            TokenKind::OPERATOR("}") => {
+                if depth == 0 {
+                    return;
+                }
+                depth -= 1;
+                if depth == 0 {
+                    ts.bump();
+                    return;
+                }
+            }
+            _ => {}
         }
+        ts.bump();
     }
-    return None;
 }
 
+/// Skips the rest of a bracketed list after a syntax error, past the `close`
+/// bracket that ends it. Stops before a `;`, `{`, `}` or unmatched closing bracket
+/// outside of the list instead.
+fn skip_to_closing(ts: &mut TokenStream, close: &str) {
+    let mut depth = 0;
+    loop {
+        match ts.peek(0).kind {
+            TokenKind::EOF => return,
+            TokenKind::SEMICOLON if depth == 0 => return,
+            TokenKind::OPERATOR("{") | TokenKind::OPERATOR("}") if depth == 0 => return,
+            TokenKind::OPERATOR(x) if x == close && depth == 0 => {
+                ts.bump();
+                return;
+            }
+            TokenKind::OPERATOR(")") | TokenKind::OPERATOR("]") if depth == 0 => return,
+            TokenKind::OPERATOR("(") | TokenKind::OPERATOR("[") | TokenKind::OPERATOR("{") => depth += 1,
+            TokenKind::OPERATOR(")") | TokenKind::OPERATOR("]") | TokenKind::OPERATOR("}") => depth -= 1,
+            _ => {}
+        }
+        ts.bump();
+    }
+}
 
-fn identifier(input: &str, pos: usize) -> Option<(Identifier, usize)> {
-    if let (Token::IDENTIFIER(id), p) = next_token(input, pos) {
-        return Some((id, p));
+fn is_keyword(ts: &TokenStream, n: usize, keyword: &str) -> bool {
+    if let TokenKind::KEYWORD(s) = ts.peek(n).kind {
+        return s == keyword;
     }
-    return None;
+    return false;
 }
 
-fn func_decl(input: &str, pos: usize) -> Option<(FuncDecl, usize)> {
-    if let Some(mut pos) = try_eat_keyword(input, pos, "fn") {
-        if let Some((func_name, p)) = identifier(input, pos) {
-            pos = p;
-            let mut arg_list = vec![];
-            if let Some(p) = try_eat_operator(input, pos, "(") {
-                pos = p;
-                let mut expect_comma = false;
-                loop {
-                    if !expect_comma {
-                        if let (Token::IDENTIFIER(v), p) = next_token(input, pos) {
-                            pos = p;
-                            arg_list.push(ArgDecl(v));
-                            expect_comma = true;
-                            continue;
-                        }
-                    } else {
-                        if let Some(p) = try_eat_operator(input, pos, ",") {
-                            pos = p;
-                            expect_comma = false;
-                            continue;
-                        }
-                    }
-                    if let Some(p) = try_eat_operator(input, pos, ")") {
-                        pos = p;
-                        break;
-                    }
-                    fatal(&format!("Error: expect ',' or ')' while trying to parse a function call at {}", pos));
-                    break;
-                }
-            } else {
-                fatal(&format!("Error: expect '(' after fn [id] at {}", pos));
-            }
-            if let Some(pos) = try_eat_operator(input, pos, "{") {
-                let (body, pos) = block(input, pos);
+fn is_operator(ts: &TokenStream, n: usize, operator: &str) -> bool {
+    if let TokenKind::OPERATOR(s) = ts.peek(n).kind {
+        return s == operator;
+    }
+    return false;
+}
 
-                if let Some(pos) = try_eat_operator(input, pos, "}") {
-                    return Some((FuncDecl {
-                        func_name,
-                        arg_list,
-                        body,
-                    }, pos));
-                } else {
-                    fatal(&format!("Error: not closing bracket at pos {}", pos));
-                }
-            }
-        } else {
-            fatal(&format!("Error: expect identifier after fn at {}", pos));
-        }
+fn try_eat_keyword(ts: &mut TokenStream, keyword: &str) -> bool {
+    if is_keyword(ts, 0, keyword) {
+        ts.bump();
+        return true;
     }
-    return None;
+    return false;
 }
 
-fn return_stmt(input: &str, pos: usize) -> Option<(Return, usize)> {
-    if let Some(pos) = try_eat_keyword(input, pos, "return") {
-        if let Some((v, pos)) = expression(input, pos) {
-            return Some((Return(v), pos));
-        }
+
+fn try_eat_operator(ts: &mut TokenStream, operator: &str) -> bool {
+    if is_operator(ts, 0, operator) {
+        ts.bump();
+        return true;
     }
-    return None;
+    return false;
 }
 
-fn func_call(input: &str, pos: usize) -> Option<(FuncCall, usize)> {
-    if let Some((func_name, pos)) = identifier(input, pos) {
-        if let Some(mut pos) = try_eat_operator(input, pos, "(") {
-            let mut func = FuncCall {
-                func_name,
-                arg_list: vec![],
-            };
-            let mut expect_comma = false;
-            loop {
-                if !expect_comma {
-                    if let Some((v, p)) = expression(input, pos) {
-                        pos = p;
-                        func.arg_list.push(v);
-                        expect_comma = true;
-                        continue;
-                    }
-                } else {
-                    if let Some(p) = try_eat_operator(input, pos, ",") {
-                        pos = p;
-                        expect_comma = false;
-                        continue;
-                    }
-                }
-                if let Some(p) = try_eat_operator(input, pos, ")") {
-                    pos = p;
-                    break;
-                }
-                error(&format!("Error: expect ',' or ')' while trying to parse a function call at {}", pos));
-                break;
-            }
-            return Some((func, pos));
-        }
+fn try_eat_semicolon(ts: &mut TokenStream) -> bool {
+    if let TokenKind::SEMICOLON = ts.peek(0).kind {
+        ts.bump();
+        return true;
     }
-    return None;
+    return false;
 }
 
-fn if_stmt(input: &str, pos: usize) -> Option<(If, usize)> {
-    if let Some(mut pos) = try_eat_keyword(input, pos, "if") {
-        let mut if_ = If { cond: vec![], then: vec![] };
-        let mut flag = true;
-        let mut true_cond = false;
-        while flag {
-            flag = false;
-            if !true_cond {
-                if let Some((expr, p)) = expression(input, pos) {
-                    if_.cond.push(expr);
-                    pos = p;
-                } else {
-                    fatal(&format!("Error: expect expression after if at pos {}", pos));
-                }
-            } else {
-                if_.cond.push(Value::BOOL(true));
-            }
-            if let Some(p) = try_eat_operator(input, pos, "{") {
-                pos = p;
-            } else {
-                fatal(&format!("Error: expect bracket after \"if cond \" at pos {}", pos))
-            }
+fn can_start_statement(kind: &TokenKind) -> bool {
+    return match kind {
+        TokenKind::IDENTIFIER(_) | TokenKind::KEYWORD(_) | TokenKind::INTEGER(..) | TokenKind::FLOAT(..) => true,
+        TokenKind::STRING(_) | TokenKind::CSTRING(_) | TokenKind::CHAR(_) | TokenKind::LABEL(_) => true,
+        TokenKind::SEMICOLON | TokenKind::DOC_COMMENT(_) | TokenKind::EOF => true,
+        TokenKind::OPERATOR(x) => ["(", "{", "}"].contains(x) || PREFIX_OPERATORS.contains(x),
+        TokenKind::ERROR(_) => false,
+    };
+}
 
-            let (then, p) = block(input, pos);
-            if_.then.push(then);
-            pos = p;
+/// Eats the `;` ending a statement, reporting it as missing if it is not there
+fn expect_semicolon(ts: &mut TokenStream, diags: &mut Diagnostics) {
+    if try_eat_semicolon(ts) {
+        return;
+    }
+    // an invalid token, like an unterminated string, already explains the missing `;`
+    let after_error = match ts.prev() {
+        Some(tk) => matches!(tk.kind, TokenKind::ERROR(_)),
+        None => false,
+    };
+    if !after_error && !reported_at(ts.peek(0), diags) {
+        let tk = ts.peek(0);
+        if let TokenKind::ERROR(_) = tk.kind {} else {
+            let end = ts.prev().map_or(tk.span.start, |x| x.span.end);
+            diags.emit(Diagnostic::error(&format!("expected ';' after the statement, found {}", tk.kind))
+                .with_label(&Span::new(ts.file(), end, end), "expected ';' here"));
+        }
+    }
+    // carry on with the next statement if it follows right away, skip the garbage otherwise
+    if !can_start_statement(&ts.peek(0).kind) {
+        synchronize(ts);
+    }
+}
 
-            if let Some(p) = try_eat_operator(input, pos, "}") {
-                pos = p;
-            } else {
-                fatal(&format!("Error: expect bracket after \"if cond {} stmts\" at pos {}", "{", pos))
+fn identifier(ts: &mut TokenStream) -> Option<Identifier> {
+    if let TokenKind::IDENTIFIER(id) = &ts.peek(0).kind {
+        let id = id.clone();
+        ts.bump();
+        return Some(id);
+    }
+    return None;
+}
+
+/// Parses the declarations of arguments up to the `close` bracket, after the opening one
+fn arg_decls(ts: &mut TokenStream, diags: &mut Diagnostics, close: &str) -> Vec<Spanned<ArgDecl>> {
+    let mut arg_list = vec![];
+    let mut expect_comma = false;
+    loop {
+        if !expect_comma {
+            let start = ts.position();
+            let mutable = try_eat_keyword(ts, "mut");
+            if let Some(pattern) = pattern(ts, diags) {
+                let ty = type_annotation(ts, diags);
+                arg_list.push(Spanned::new(ArgDecl { pattern, mutable, ty }, ts.span_from(start)));
+                expect_comma = true;
+                continue;
             }
-            if let Some(p) = try_eat_keyword(input, pos, "elif") {
-                flag = true;
-                pos = p;
-            } else if let Some(p) = try_eat_keyword(input, pos, "else") {
-                flag = true;
-                pos = p;
-                true_cond = true;
+            if mutable {
+                expected(ts, diags, "an argument name after 'mut'");
+                skip_to_closing(ts, close);
+                break;
             }
+        } else if try_eat_operator(ts, ",") {
+            expect_comma = false;
+            continue;
         }
-        return Some((if_, pos));
+        if try_eat_operator(ts, close) {
+            break;
+        }
+        expected(ts, diags, &format!("',' or '{}' in the argument list of a function", close));
+        skip_to_closing(ts, close);
+        break;
     }
-    return None;
+    return arg_list;
 }
 
-fn while_stmt(input: &str, pos: usize) -> Option<(While, usize)> {
-    if let Some(mut pos) = try_eat_keyword(input, pos, "while") {
-        let cond;
+/// Parses the optional `-> type`, which is `default` if absent
+fn return_type(ts: &mut TokenStream, diags: &mut Diagnostics, default: TypeExpr) -> Type {
+    if try_eat_operator(ts, "->") {
+        return match type_expr(ts, diags) {
+            Some(x) => x,
+            None => missing_type(ts, diags, "a return type after '->'"),
+        };
+    }
+    return Spanned::new(default, ts.span_from(ts.position()));
+}
 
-        if let Some((expr, p)) = expression(input, pos) {
-            cond = expr;
-            pos = p;
-        } else {
-            fatal(&format!("Error: expect expression after if at pos {}", pos));
-            panic!();
-        }
+fn func_body(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<Block> {
+    if try_eat_operator(ts, "{") {
+        let body = block(ts, diags);
 
-        if let Some(p) = try_eat_operator(input, pos, "{") {
-            pos = p;
-        } else {
-            fatal(&format!("Error: expect bracket after \"while cond \" at pos {}", pos))
+        if !try_eat_operator(ts, "}") {
+            expected(ts, diags, "'}' to close the function body");
         }
+        return Some(body);
+    }
+    expected(ts, diags, "'{' before the function body");
+    return None;
+}
 
-        let (then, p) = block(input, pos);
-        pos = p;
-
-        if let Some(p) = try_eat_operator(input, pos, "}") {
-            pos = p;
+/// Parses `fn name(args) -> ret`, leaving the body empty
+fn func_signature(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<FuncDecl> {
+    // `fn(` starts a lambda
+    if is_keyword(ts, 0, "fn") && !is_operator(ts, 1, "(") {
+        ts.bump();
+        if let Some(func_name) = identifier(ts) {
+            let arg_list = if try_eat_operator(ts, "(") {
+                arg_decls(ts, diags, ")")
+            } else {
+                expected(ts, diags, "'(' after the function name");
+                vec![]
+            };
+            let ret = return_type(ts, diags, TypeExpr::VOID);
+            return Some(FuncDecl {
+                func_name,
+                arg_list,
+                ret,
+                body: Block(vec![]),
+                doc: vec![],
+            });
         } else {
-            fatal(&format!("Error: expect bracket after \"while cond {} stmts\" at pos {}", "{", pos))
+            expected(ts, diags, "a function name after 'fn'");
         }
-
-        return Some((While { cond, then }, pos));
     }
     return None;
 }
 
-fn expression(input: &str, pos: usize) -> Option<(Value, usize)> {
-    return addition_expr(input, pos);
+fn func_decl(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<FuncDecl> {
+    let mut decl = func_signature(ts, diags)?;
+    decl.body = func_body(ts, diags)?;
+    return Some(decl);
 }
 
-fn expr_implementation(
-    input: &str,
-    pos: usize,
-    oper_list: &[&str],
-    fun: fn(&str, usize) -> Option<(Value, usize)>,
-) -> Option<(Value, usize)> {
-    let mut flag = true;
-    if let Some((mut o1, mut pos)) = fun(input, pos) {
-        while flag {
-            flag = false;
-            for oper in oper_list {
-                let r = try_eat_operator(input, pos, oper);
-                if r.is_none() {
-                    continue;
-                }
-                flag = true;
-                pos = r.unwrap();
-                if let Some((o2, p)) = fun(input, pos) {
-                    pos = p;
-                    let x = Value::FUNC_CALL(FuncCall {
-                        func_name: Identifier(oper.to_string()),
-                        arg_list: vec![o1, o2],
-                    });
-                    o1 = x;
-                } else {
-                    fatal_::<()>(&format!("Error: expect a expression at pos {}", pos));
-                }
-            }
+/// Gives an untyped `self` the type the methods are declared for
+fn type_self(decl: &mut FuncDecl, ty: &Identifier) {
+    if let Some(arg) = decl.arg_list.first_mut() {
+        if arg.node.is_self() && arg.node.ty.is_none() {
+            let ty = TypeExpr::NAMED(ty.clone(), vec![]);
+            arg.node.ty = Some(Spanned::new(ty, arg.span.clone()));
         }
-
-        return Some((o1, pos));
     }
-    return None;
 }
 
-fn addition_expr(input: &str, pos: usize) -> Option<(Value, usize)> {
-    const LIST: [&str; 2] = ["+", "-"];
-    return expr_implementation(input, pos, &LIST, multiplication_expr);
+/// Parses `fn(args) -> ret { ... }`, `|args| value` or `|args| -> ret { ... }`
+fn lambda(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<Expr> {
+    let start = ts.position();
+    let (arg_list, ret, body) = if is_keyword(ts, 0, "fn") && is_operator(ts, 1, "(") {
+        ts.bump();
+        ts.bump();
+        let arg_list = arg_decls(ts, diags, ")");
+        let ret = return_type(ts, diags, TypeExpr::VOID);
+        (arg_list, ret, func_body(ts, diags))
+    } else if is_operator(ts, 0, "|") || is_operator(ts, 0, "||") {
+        let arg_list = if try_eat_operator(ts, "|") {
+            arg_decls(ts, diags, "|")
+        } else {
+            ts.bump();
+            vec![]
+        };
+        let ret = return_type(ts, diags, TypeExpr::INFER);
+        let body = if ret.node != TypeExpr::INFER || is_operator(ts, 0, "{") {
+            func_body(ts, diags)
+        } else {
+            let v = match expression(ts, diags) {
+                Some(x) => x,
+                None => missing_expression(ts, diags, "the body of the lambda"),
+            };
+            let span = v.span.clone();
+            Some(Block(vec![Spanned::new(Statement::RETURN(Return(Some(v))), span)]))
+        };
+        (arg_list, ret, body)
+    } else {
+        return None;
+    };
+    let value = match body {
+        Some(body) => Value::LAMBDA(Rc::new(FuncDecl {
+            func_name: Identifier("lambda".to_string()),
+            arg_list,
+            ret,
+            body,
+            doc: vec![],
+        })),
+        None => Value::ERROR,
+    };
+    return Some(Spanned::new(value, ts.span_from(start)));
 }
 
-fn multiplication_expr(input: &str, pos: usize) -> Option<(Value, usize)> {
-    const LIST: [&str; 2] = ["*", "/"];
-    return expr_implementation(input, pos, &LIST, value);
-}
+fn struct_decl(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<StructDecl> {
+    if try_eat_keyword(ts, "struct") || try_eat_keyword(ts, "class") {
+        let name = match identifier(ts) {
+            Some(x) => x,
+            None => {
+                expected(ts, diags, "a struct name");
+                synchronize(ts);
+                return None;
+            }
+        };
+        if !try_eat_operator(ts, "{") {
+            expected(ts, diags, "'{' after the struct name");
+            synchronize(ts);
+            return None;
+        }
+        let mut fields = vec![];
+        let mut expect_comma = false;
+        loop {
+            if !expect_comma {
+                let start = ts.position();
+                if let Some(id) = identifier(ts) {
+                    let ty = if try_eat_operator(ts, ":") {
+                        match type_expr(ts, diags) {
+                            Some(x) => x,
+                            None => missing_type(ts, diags, "a field type after ':'"),
+                        }
+                    } else {
+                        missing_type(ts, diags, "':' and the type of the field")
+                    };
+                    fields.push(Spanned::new(FieldDecl { id, ty }, ts.span_from(start)));
+                    expect_comma = true;
+                    continue;
+                }
+            } else if try_eat_operator(ts, ",") {
+                expect_comma = false;
+                continue;
+            }
+            if try_eat_operator(ts, "}") {
+                break;
+            }
+            expected(ts, diags, "',' or '}' in the fields of a struct");
+            skip_to_closing(ts, "}");
+            try_eat_operator(ts, "}");
+            break;
+        }
+        return Some(StructDecl { name, fields, derive: vec![], doc: vec![] });
+    }
+    return None;
+}
+
+fn enum_decl(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<EnumDecl> {
+    if try_eat_keyword(ts, "enum") {
+        let name = match identifier(ts) {
+            Some(x) => x,
+            None => {
+                expected(ts, diags, "an enum name");
+                synchronize(ts);
+                return None;
+            }
+        };
+        if !try_eat_operator(ts, "{") {
+            expected(ts, diags, "'{' after the enum name");
+            synchronize(ts);
+            return None;
+        }
+        let mut variants = vec![];
+        let mut expect_comma = false;
+        loop {
+            if !expect_comma {
+                let start = ts.position();
+                if let Some(name) = identifier(ts) {
+                    let fields = if try_eat_operator(ts, "(") {
+                        type_list(ts, diags, ")", "',' or ')' in the fields of a variant").0
+                    } else {
+                        vec![]
+                    };
+                    variants.push(Spanned::new(VariantDecl { name, fields }, ts.span_from(start)));
+                    expect_comma = true;
+                    continue;
+                }
+            } else if try_eat_operator(ts, ",") {
+                expect_comma = false;
+                continue;
+            }
+            if try_eat_operator(ts, "}") {
+                break;
+            }
+            expected(ts, diags, "',' or '}' in the variants of an enum");
+            skip_to_closing(ts, "}");
+            try_eat_operator(ts, "}");
+            break;
+        }
+        return Some(EnumDecl { name, variants, doc: vec![] });
+    }
+    return None;
+}
+
+fn impl_block(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<Impl> {
+    if try_eat_keyword(ts, "impl") {
+        let start = ts.position();
+        let mut interface = None;
+        let mut target = match identifier(ts) {
+            Some(x) => Spanned::new(x, ts.span_from(start)),
+            None => {
+                expected(ts, diags, "a struct name after 'impl'");
+                synchronize(ts);
+                return None;
+            }
+        };
+        if try_eat_keyword(ts, "for") {
+            let start = ts.position();
+            interface = Some(target);
+            target = match identifier(ts) {
+                Some(x) => Spanned::new(x, ts.span_from(start)),
+                None => {
+                    expected(ts, diags, "a struct name after 'for'");
+                    synchronize(ts);
+                    return None;
+                }
+            };
+        }
+        if !try_eat_operator(ts, "{") {
+            expected(ts, diags, "'{' after the struct name");
+            synchronize(ts);
+            return None;
+        }
+        let mut methods = vec![];
+        loop {
+            let start = ts.position();
+            let doc = doc_comments(ts);
+            if let Some(mut decl) = func_decl(ts, diags) {
+                decl.doc = doc;
+                type_self(&mut decl, &target.node);
+                methods.push(Spanned::new(Rc::new(decl), ts.span_from(start)));
+                continue;
+            }
+            if try_eat_operator(ts, "}") {
+                break;
+            }
+            expected(ts, diags, "a method or '}' in the impl block");
+            if let TokenKind::EOF = ts.peek(0).kind {
+                break;
+            }
+            synchronize(ts);
+        }
+        return Some(Impl { interface, target, methods });
+    }
+    return None;
+}
 
-fn value(input: &str, pos: usize) -> Option<(Value, usize)> {
-    if let Some((x, p)) = func_call(input, pos) {
-        return Some((Value::FUNC_CALL(x), p));
+fn interface_decl(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<InterfaceDecl> {
+    if try_eat_keyword(ts, "interface") {
+        let name = match identifier(ts) {
+            Some(x) => x,
+            None => {
+                expected(ts, diags, "an interface name");
+                synchronize(ts);
+                return None;
+            }
+        };
+        if !try_eat_operator(ts, "{") {
+            expected(ts, diags, "'{' after the interface name");
+            synchronize(ts);
+            return None;
+        }
+        let mut decl = InterfaceDecl { name, required: vec![], provided: vec![], doc: vec![] };
+        loop {
+            let start = ts.position();
+            let doc = doc_comments(ts);
+            if let Some(mut method) = func_signature(ts, diags) {
+                method.doc = doc;
+                type_self(&mut method, &decl.name);
+                if try_eat_semicolon(ts) {
+                    decl.required.push(Spanned::new(Rc::new(method), ts.span_from(start)));
+                    continue;
+                }
+                if let Some(body) = func_body(ts, diags) {
+                    method.body = body;
+                    decl.provided.push(Spanned::new(Rc::new(method), ts.span_from(start)));
+                }
+                continue;
+            }
+            if try_eat_operator(ts, "}") {
+                break;
+            }
+            expected(ts, diags, "a method or '}' in the interface");
+            if let TokenKind::EOF = ts.peek(0).kind {
+                break;
+            }
+            synchronize(ts);
+        }
+        return Some(decl);
     }
-    if let Some(pos) = try_eat_operator(input, pos, "(") {
-        if let Some((v, pos)) = expression(input, pos) {
-            if let Some(pos) = try_eat_operator(input, pos, ")") {
-                return Some((v, pos));
+    return None;
+}
+
+/// Parses the `#[derive(A, B)]` attributes before a struct, returning the derived interfaces
+fn attributes(ts: &mut TokenStream, diags: &mut Diagnostics) -> Vec<Spanned<Identifier>> {
+    let mut derive = vec![];
+    while try_eat_operator(ts, "#[") {
+        let start = ts.position();
+        match identifier(ts) {
+            Some(Identifier(name)) if name == "derive" => {
+                if try_eat_operator(ts, "(") {
+                    let mut expect_comma = false;
+                    loop {
+                        if !expect_comma {
+                            let start = ts.position();
+                            if let Some(id) = identifier(ts) {
+                                derive.push(Spanned::new(id, ts.span_from(start)));
+                                expect_comma = true;
+                                continue;
+                            }
+                        } else if try_eat_operator(ts, ",") {
+                            expect_comma = false;
+                            continue;
+                        }
+                        if try_eat_operator(ts, ")") {
+                            break;
+                        }
+                        expected(ts, diags, "',' or ')' in the derived interfaces");
+                        skip_to_closing(ts, ")");
+                        break;
+                    }
+                } else {
+                    expected(ts, diags, "'(' after 'derive'");
+                }
+            }
+            Some(Identifier(name)) => {
+                diags.emit(Diagnostic::error(&format!("unknown attribute `{}`", name))
+                    .with_label(&ts.span_from(start), "only `derive` is supported"));
+                skip_to_closing(ts, "]");
+                continue;
+            }
+            None => {
+                expected(ts, diags, "an attribute name after '#['");
+                skip_to_closing(ts, "]");
+                continue;
+            }
+        }
+        if !try_eat_operator(ts, "]") {
+            expected(ts, diags, "']' to close the attribute");
+            skip_to_closing(ts, "]");
+        }
+    }
+    return derive;
+}
+
+/// Reports a missing type and stands in an error node for it
+fn missing_type(ts: &mut TokenStream, diags: &mut Diagnostics, what: &str) -> Type {
+    expected(ts, diags, what);
+    return Spanned::new(TypeExpr::ERROR, ts.span_from(ts.position()));
+}
+
+/// Parses the optional `: type` after a declared name
+fn type_annotation(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<Type> {
+    if try_eat_operator(ts, ":") {
+        return Some(match type_expr(ts, diags) {
+            Some(x) => x,
+            None => missing_type(ts, diags, "a type after ':'"),
+        });
+    }
+    return None;
+}
+
+/// Parses a type, followed by any number of C-like array suffixes as in `int[3][]`
+fn type_expr(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<Type> {
+    let start = ts.position();
+    let mut ty = base_type(ts, diags)?;
+    while try_eat_operator(ts, "[") {
+        let len = array_len(ts, diags);
+        if !try_eat_operator(ts, "]") {
+            expected(ts, diags, "']' to close the array type");
+        }
+        ty = Spanned::new(TypeExpr::C_ARRAY(Box::new(ty), len), ts.span_from(start));
+    }
+    return Some(ty);
+}
+
+/// Parses an array length, which is empty for arrays of any length
+fn array_len(ts: &mut TokenStream, diags: &mut Diagnostics) -> ArrayLen {
+    let len = match &ts.peek(0).kind {
+        TokenKind::INTEGER(n, _) => ArrayLen::FIXED(*n),
+        TokenKind::IDENTIFIER(id) if id.0 == "_" => ArrayLen::ANY,
+        TokenKind::IDENTIFIER(id) => ArrayLen::BIND(id.clone()),
+        TokenKind::OPERATOR("]") => return ArrayLen::ANY,
+        _ => {
+            expected(ts, diags, "an array length");
+            return ArrayLen::ANY;
+        }
+    };
+    ts.bump();
+    return len;
+}
+
+/// Parses the type after a prefix like `*` or `ref`
+fn inner_type(ts: &mut TokenStream, diags: &mut Diagnostics, prefix: &str) -> Box<Type> {
+    return Box::new(match type_expr(ts, diags) {
+        Some(x) => x,
+        None => missing_type(ts, diags, &format!("a type after '{}'", prefix)),
+    });
+}
+
+fn base_type(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<Type> {
+    let start = ts.position();
+    let tk = ts.peek(0).clone();
+    let ty = match &tk.kind {
+        TokenKind::OPERATOR("*") => {
+            ts.bump();
+            TypeExpr::POINTER(inner_type(ts, diags, "*"))
+        }
+        TokenKind::OPERATOR(op @ "&") | TokenKind::OPERATOR(op @ "ref") => {
+            ts.bump();
+            TypeExpr::REF(inner_type(ts, diags, op))
+        }
+        // `&&T` is lexed as one operator but means `&(&T)`
+        TokenKind::OPERATOR("&&") => {
+            ts.bump();
+            let inner = inner_type(ts, diags, "&&");
+            let span = Span::new(ts.file(), tk.span.start + 1, ts.span_from(start).end);
+            TypeExpr::REF(Box::new(Spanned::new(TypeExpr::REF(inner), span)))
+        }
+        TokenKind::OPERATOR("[") => {
+            ts.bump();
+            let elem = inner_type(ts, diags, "[");
+            let len = if !try_eat_semicolon(ts) {
+                ArrayLen::ANY
+            } else if is_operator(ts, 0, "]") {
+                expected(ts, diags, "an array length after ';'");
+                ArrayLen::ANY
             } else {
-                error(&format!("Error: not closing parenthesis at pos {}", pos));
-                return Some((v, pos));
+                array_len(ts, diags)
+            };
+            if !try_eat_operator(ts, "]") {
+                expected(ts, diags, "']' to close the array type");
             }
+            TypeExpr::ARRAY(elem, len)
+        }
+        TokenKind::OPERATOR("(") => {
+            ts.bump();
+            let (mut list, trailing_comma) = type_list(ts, diags, ")", "',' or ')' in a tuple type");
+            // `(T)` is just `T` in parentheses
+            if list.len() == 1 && !trailing_comma {
+                return list.pop();
+            }
+            TypeExpr::TUPLE(list)
+        }
+        TokenKind::KEYWORD("fn") => {
+            ts.bump();
+            let mut args = vec![];
+            if try_eat_operator(ts, "(") {
+                args = type_list(ts, diags, ")", "',' or ')' in the argument list of a function type").0;
+            } else {
+                expected(ts, diags, "'(' after 'fn' in a function type");
+            }
+            let mut ret = None;
+            if try_eat_operator(ts, "->") {
+                ret = Some(inner_type(ts, diags, "->"));
+            }
+            TypeExpr::FUNCTION(args, ret)
+        }
+        TokenKind::IDENTIFIER(id) => {
+            ts.bump();
+            match id.0.as_str() {
+                "char" => TypeExpr::CHAR,
+                "str" => TypeExpr::STR,
+                "cstr" => TypeExpr::CSTR,
+                "void" => TypeExpr::VOID,
+                "_" => TypeExpr::INFER,
+                name => match (IntType::from_name(name), FloatType::from_name(name)) {
+                    (Some(t), _) => TypeExpr::INT(t),
+                    (_, Some(t)) => TypeExpr::FLOAT(t),
+                    _ => {
+                        let mut args = vec![];
+                        if try_eat_operator(ts, "<") {
+                            args = type_list(ts, diags, ">", "',' or '>' in the template arguments").0;
+                        }
+                        TypeExpr::NAMED(id.clone(), args)
+                    }
+                },
+            }
+        }
+        _ => return None,
+    };
+    return Some(Spanned::new(ty, ts.span_from(start)));
+}
+
+/// Eats the `close` bracket. A `>` may be the start of a longer operator, as
+/// in `Vec<Vec<int>>`, which is split up for it.
+fn try_eat_closing(ts: &mut TokenStream, close: &str) -> bool {
+    if close == ">" {
+        if let TokenKind::OPERATOR(">>") | TokenKind::OPERATOR(">=") | TokenKind::OPERATOR(">>=") = ts.peek(0).kind {
+            ts.split_operator();
         }
     }
+    return try_eat_operator(ts, close);
+}
+
+/// Parses comma separated types up to and including the `close` bracket,
+/// returning them and whether the last one was followed by a comma
+fn type_list(ts: &mut TokenStream, diags: &mut Diagnostics, close: &str, what: &str) -> (Vec<Type>, bool) {
+    let mut list = vec![];
+    let mut trailing_comma = false;
+    loop {
+        if try_eat_closing(ts, close) {
+            break;
+        }
+        if let Some(ty) = type_expr(ts, diags) {
+            list.push(ty);
+            trailing_comma = try_eat_operator(ts, ",");
+            if trailing_comma {
+                continue;
+            }
+            if try_eat_closing(ts, close) {
+                break;
+            }
+        }
+        expected(ts, diags, what);
+        skip_to_closing(ts, close);
+        break;
+    }
+    return (list, trailing_comma);
+}
+
+/// Parses the patterns of a tuple or a variant after the `(`, up to and
+/// including the `)`, and whether the last is followed by a comma
+fn pattern_list(ts: &mut TokenStream, diags: &mut Diagnostics, item: fn(&mut TokenStream, &mut Diagnostics) -> Option<Spanned<Pattern>>) -> (Vec<Spanned<Pattern>>, bool) {
+    let mut list = vec![];
+    let mut expect_comma = false;
+    loop {
+        if !expect_comma {
+            if let Some(x) = item(ts, diags) {
+                list.push(x);
+                expect_comma = true;
+                continue;
+            }
+        } else if try_eat_operator(ts, ",") {
+            expect_comma = false;
+            continue;
+        }
+        if try_eat_operator(ts, ")") {
+            break;
+        }
+        expected(ts, diags, "',' or ')' in the pattern");
+        skip_to_closing(ts, ")");
+        break;
+    }
+    let trailing_comma = !expect_comma && !list.is_empty();
+    return (list, trailing_comma);
+}
 
-    let (tk, pos) = next_token(input, pos);
-    if let Token::FLOAT(f) = tk {
-        return Some((Value::FLOAT(f), pos));
+/// Parses a name, `_`, or a tuple pattern of these
+fn pattern(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<Spanned<Pattern>> {
+    let start = ts.position();
+    if try_eat_operator(ts, "(") {
+        return Some(tuple_pattern(ts, diags, pattern, start));
     }
-    if let Token::INTEGER(i) = tk {
-        return Some((Value::INT(i), pos));
+    let span = ts.peek(0).span.clone();
+    let id = identifier(ts)?;
+    if id.0 == "_" {
+        return Some(Spanned::new(Pattern::WILDCARD, span));
     }
-    if let Token::IDENTIFIER(id) = tk {
-        return Some((Value::VAR(id), pos));
+    return Some(Spanned::new(Pattern::BIND(id), span));
+}
+
+/// Parses a tuple pattern after its `(`, where `(p)` is just `p` in parentheses
+fn tuple_pattern(ts: &mut TokenStream, diags: &mut Diagnostics, item: fn(&mut TokenStream, &mut Diagnostics) -> Option<Spanned<Pattern>>, start: usize) -> Spanned<Pattern> {
+    let (mut list, trailing_comma) = pattern_list(ts, diags, item);
+    if list.len() == 1 && !trailing_comma {
+        return list.pop().unwrap();
     }
+    return Spanned::new(Pattern::TUPLE(list), ts.span_from(start));
+}
 
+/// Parses the pattern of a `match` arm
+fn match_pattern(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<Spanned<Pattern>> {
+    let start = ts.position();
+    let number = |kind: &TokenKind| matches!(kind, TokenKind::INTEGER(..) | TokenKind::FLOAT(..));
+    if is_operator(ts, 0, "-") && number(&ts.peek(1).kind) {
+        ts.bump();
+        let x = primary_expr(ts, diags)?;
+        let neg = FuncCall { func_name: Identifier("-".to_string()), arg_list: vec![x] };
+        let x = Spanned::new(Value::FUNC_CALL(neg), ts.span_from(start));
+        return Some(Spanned::new(Pattern::LITERAL(x), ts.span_from(start)));
+    }
+    if try_eat_operator(ts, "(") {
+        return Some(tuple_pattern(ts, diags, match_pattern, start));
+    }
+    match ts.peek(0).kind {
+        TokenKind::STRING(_) | TokenKind::CSTRING(_) | TokenKind::CHAR(_) => {}
+        TokenKind::KEYWORD("true") | TokenKind::KEYWORD("false") => {}
+        ref kind if number(kind) => {}
+        _ => {
+            if !is_operator(ts, 1, "::") {
+                return pattern(ts, diags);
+            }
+            let ty = identifier(ts)?;
+            ts.bump();
+            let variant = match identifier(ts) {
+                Some(x) => x,
+                None => {
+                    expected(ts, diags, "a variant name after '::'");
+                    return Some(Spanned::new(Pattern::WILDCARD, ts.span_from(start)));
+                }
+            };
+            let mut fields = vec![];
+            if try_eat_operator(ts, "(") {
+                fields = pattern_list(ts, diags, match_pattern).0;
+            }
+            return Some(Spanned::new(Pattern::VARIANT(ty, variant, fields), ts.span_from(start)));
+        }
+    }
+    let x = primary_expr(ts, diags)?;
+    return Some(Spanned::new(Pattern::LITERAL(x), ts.span_from(start)));
+}
+
+/// Parses a `match`, which is an error node if it has no arms to check
+fn match_expr(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<Value> {
+    if try_eat_keyword(ts, "match") {
+        let value = match expression(ts, diags) {
+            Some(x) => x,
+            None => missing_expression(ts, diags, "a value after 'match'"),
+        };
+        let mut arms = vec![];
+        if !try_eat_operator(ts, "{") {
+            expected(ts, diags, "'{' after the value of 'match'");
+            return Some(Value::ERROR);
+        }
+        while !try_eat_operator(ts, "}") {
+            let start = ts.position();
+            let pattern = match match_pattern(ts, diags) {
+                Some(x) => x,
+                None => {
+                    expected(ts, diags, "a pattern or '}' in the arms of 'match'");
+                    skip_to_closing(ts, "}");
+                    try_eat_operator(ts, "}");
+                    break;
+                }
+            };
+            let mut guard = None;
+            if try_eat_keyword(ts, "if") {
+                guard = Some(match expression(ts, diags) {
+                    Some(x) => x,
+                    None => missing_expression(ts, diags, "a condition after 'if'"),
+                });
+            }
+            if !try_eat_operator(ts, "=>") {
+                expected(ts, diags, "'=>' after the pattern");
+            }
+            let body = if try_eat_operator(ts, "{") {
+                let then = block(ts, diags);
+                if !try_eat_operator(ts, "}") {
+                    expected(ts, diags, "'}' to close the arm");
+                }
+                try_eat_operator(ts, ",");
+                ArmBody::BLOCK(then)
+            } else {
+                let x = match expression(ts, diags) {
+                    Some(x) => x,
+                    None => missing_expression(ts, diags, "an expression or '{' after '=>'"),
+                };
+                if !try_eat_operator(ts, ",") && !is_operator(ts, 0, "}") {
+                    expected(ts, diags, "',' or '}' after the arm");
+                }
+                ArmBody::EXPR(x)
+            };
+            arms.push(Spanned::new(Arm { pattern, guard, body }, ts.span_from(start)));
+        }
+        return Some(Value::MATCH(Box::new(Match { value, arms })));
+    }
     return None;
 }
 
-fn statement(input: &str, pos: usize) -> Option<(Statement, usize)> {
-    if let Some((ass, pos)) = assignment_stmt(input, pos) {
-        if let Some(pos) = try_eat_semicolon(input, pos) {
-            return Some((Statement::ASSIGNMENT(ass), pos));
+fn let_stmt(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<Let> {
+    if try_eat_keyword(ts, "let") {
+        let mutable = try_eat_keyword(ts, "mut");
+        let pattern = match pattern(ts, diags) {
+            Some(x) => x,
+            None => {
+                expected(ts, diags, if mutable { "a name after 'let mut'" } else { "a name after 'let'" });
+                let span = ts.span_from(ts.position());
+                Spanned::new(Pattern::WILDCARD, span)
+            }
+        };
+        let ty = type_annotation(ts, diags);
+        let mut init = None;
+        if try_eat_operator(ts, "=") {
+            init = Some(match expression(ts, diags) {
+                Some(x) => x,
+                None => missing_expression(ts, diags, "an expression after '='"),
+            });
         }
+        return Some(Let { pattern, mutable, ty, init });
     }
-    if let Some((rtn, pos)) = return_stmt(input, pos) {
-        if let Some(pos) = try_eat_semicolon(input, pos) {
-            return Some((Statement::RETURN(rtn), pos));
+    return None;
+}
+
+fn return_stmt(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<Return> {
+    if try_eat_keyword(ts, "return") {
+        let start = ts.position();
+        let value = expression(ts, diags);
+        // `return a, b;` returns the tuple `(a, b)`
+        if let Some(first) = value.as_ref().filter(|_| is_operator(ts, 0, ",")) {
+            let mut elements = vec![first.clone()];
+            while try_eat_operator(ts, ",") {
+                elements.push(match expression(ts, diags) {
+                    Some(x) => x,
+                    None => missing_expression(ts, diags, "a value after ','"),
+                });
+            }
+            return Some(Return(Some(Spanned::new(Value::TUPLE(elements), ts.span_from(start)))));
         }
+        return Some(Return(value));
     }
+    return None;
+}
 
-    if let Some((val, pos)) = value(input, pos) {
-        if let Some(pos) = try_eat_semicolon(input, pos) {
-            return Some((Statement::EXPRESSION(val), pos));
+fn if_stmt(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<If> {
+    if try_eat_keyword(ts, "if") {
+        let mut if_ = If { cond: vec![], then: vec![] };
+        let mut flag = true;
+        let mut true_cond = false;
+        while flag {
+            flag = false;
+            if !true_cond {
+                if let Some(expr) = expression(ts, diags) {
+                    if_.cond.push(expr);
+                } else {
+                    let expr = missing_expression(ts, diags, "a condition after 'if'");
+                    if_.cond.push(expr);
+                }
+            } else {
+                if_.cond.push(Spanned::new(Value::BOOL(true), ts.span_from(ts.position())));
+            }
+            let then = body(ts, diags, "'{' after the condition of 'if'", "'if'");
+            if_.then.push(then);
+            if try_eat_keyword(ts, "elif") {
+                flag = true;
+            } else if try_eat_keyword(ts, "else") {
+                flag = true;
+                true_cond = true;
+            }
         }
+        return Some(if_);
     }
-    if let Some((decl, pos)) = func_decl(input, pos) {
-        return Some((Statement::FUNC_DECL(decl), pos));
+    return None;
+}
+
+fn while_stmt(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<While> {
+    if try_eat_keyword(ts, "while") {
+        let cond = match expression(ts, diags) {
+            Some(expr) => expr,
+            None => missing_expression(ts, diags, "a condition after 'while'"),
+        };
+
+        let then = body(ts, diags, "'{' after the condition of 'while'", "'while'");
+
+        return Some(While { label: None, cond, then });
     }
-    if let Some((if_, pos)) = if_stmt(input, pos) {
-        return Some((Statement::IF(if_), pos));
+    return None;
+}
+
+/// Skips the rest of a malformed header of a statement, up to the `{` of its body
+fn skip_header(ts: &mut TokenStream) {
+    loop {
+        match ts.peek(0).kind {
+            TokenKind::EOF | TokenKind::SEMICOLON => return,
+            TokenKind::OPERATOR("{") | TokenKind::OPERATOR("}") => return,
+            _ => {}
+        }
+        ts.bump();
     }
+}
 
-    if let Some((while_, pos)) = while_stmt(input, pos) {
-        return Some((Statement::WHILE(while_), pos));
+/// Parses the braced body of the statement `what` after its header, which is
+/// skipped up to the `{` if it is malformed
+fn body(ts: &mut TokenStream, diags: &mut Diagnostics, open: &str, what: &str) -> Block {
+    if !try_eat_operator(ts, "{") {
+        expected(ts, diags, open);
+        skip_header(ts);
+        if !try_eat_operator(ts, "{") {
+            // without a body, the statement ends at the `;`
+            try_eat_semicolon(ts);
+            return Block(vec![]);
+        }
+    }
+    let then = block(ts, diags);
+    if !try_eat_operator(ts, "}") {
+        expected(ts, diags, &format!("'}}' to close the body of {}", what));
     }
+    return then;
+}
 
-    if let Some(pos) = try_eat_semicolon(input, pos) {
-        return Some((Statement::NOTHING, pos));
+/// Parses the `'name:` before a loop
+fn loop_label(ts: &mut TokenStream) -> Option<Spanned<Identifier>> {
+    if let TokenKind::LABEL(name) = &ts.peek(0).kind {
+        if is_operator(ts, 1, ":") {
+            let label = Spanned::new(Identifier(name.clone()), ts.peek(0).span.clone());
+            ts.bump();
+            ts.bump();
+            return Some(label);
+        }
     }
     return None;
 }
 
-pub fn block(input: &str, pos: usize) -> (Block, usize) {
-    let mut b = Block(vec![]);
-    let mut pos = pos;
+/// Parses the `'name` after `break` or `continue`
+fn jump_label(ts: &mut TokenStream) -> Option<Spanned<Identifier>> {
+    if let TokenKind::LABEL(name) = &ts.peek(0).kind {
+        let label = Spanned::new(Identifier(name.clone()), ts.peek(0).span.clone());
+        ts.bump();
+        return Some(label);
+    }
+    return None;
+}
+
+fn loop_expr(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<Loop> {
+    if try_eat_keyword(ts, "loop") {
+        let then = body(ts, diags, "'{' after 'loop'", "'loop'");
+        return Some(Loop { label: None, then });
+    }
+    return None;
+}
+
+fn do_until_stmt(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<DoUntil> {
+    if try_eat_keyword(ts, "do") {
+        let then = body(ts, diags, "'{' after 'do'", "'do'");
+        let cond = if try_eat_keyword(ts, "until") {
+            match expression(ts, diags) {
+                Some(x) => x,
+                None => missing_expression(ts, diags, "a condition after 'until'"),
+            }
+        } else {
+            missing_expression(ts, diags, "'until' after the body of 'do'")
+        };
+        return Some(DoUntil { label: None, then, cond });
+    }
+    return None;
+}
+
+/// Parses any loop statement, with its optional label
+fn loop_stmt(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<Statement> {
+    let label = loop_label(ts);
+    if let Some(mut x) = while_stmt(ts, diags) {
+        x.label = label;
+        return Some(Statement::WHILE(x));
+    }
+    if let Some(mut x) = for_stmt(ts, diags) {
+        x.label = label;
+        return Some(Statement::FOR(x));
+    }
+    if let Some(mut x) = loop_expr(ts, diags) {
+        x.label = label;
+        return Some(Statement::LOOP(x));
+    }
+    if let Some(mut x) = do_until_stmt(ts, diags) {
+        x.label = label;
+        expect_semicolon(ts, diags);
+        return Some(Statement::DO_UNTIL(x));
+    }
+    if label.is_some() {
+        expected(ts, diags, "a loop after the label");
+        return Some(Statement::ERROR);
+    }
+    return None;
+}
+
+/// Parses `break` or `continue`, without the `;`
+fn jump_stmt(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<Statement> {
+    if try_eat_keyword(ts, "break") {
+        let label = jump_label(ts);
+        let value = expression(ts, diags);
+        return Some(Statement::BREAK(Break { label, value }));
+    }
+    if try_eat_keyword(ts, "continue") {
+        return Some(Statement::CONTINUE(jump_label(ts)));
+    }
+    return None;
+}
+
+fn for_stmt(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<For> {
+    if try_eat_keyword(ts, "for") {
+        let mutable = try_eat_keyword(ts, "mut");
+        let var = match pattern(ts, diags) {
+            Some(x) => x,
+            None => {
+                expected(ts, diags, "a loop variable after 'for'");
+                Spanned::new(Pattern::WILDCARD, ts.span_from(ts.position()))
+            }
+        };
+        let iter = if try_eat_operator(ts, "=") {
+            let from = match expression(ts, diags) {
+                Some(x) => x,
+                None => missing_expression(ts, diags, "the start of the loop after '='"),
+            };
+            let downto = try_eat_keyword(ts, "downto");
+            let to = if !downto && !try_eat_keyword(ts, "to") {
+                expected(ts, diags, "'to' or 'downto' after the start of the loop");
+                skip_header(ts);
+                Spanned::new(Value::ERROR, ts.span_from(ts.position()))
+            } else {
+                match expression(ts, diags) {
+                    Some(x) => x,
+                    None => missing_expression(ts, diags, "the end of the loop"),
+                }
+            };
+            let mut step = None;
+            if try_eat_keyword(ts, "step") {
+                step = Some(match expression(ts, diags) {
+                    Some(x) => x,
+                    None => missing_expression(ts, diags, "an expression after 'step'"),
+                });
+            }
+            Iteration::COUNTED(Box::new(CountedLoop { from, to, downto, step }))
+        } else if try_eat_keyword(ts, "in") {
+            Iteration::IN(match expression(ts, diags) {
+                Some(x) => x,
+                None => missing_expression(ts, diags, "an expression after 'in'"),
+            })
+        } else {
+            expected(ts, diags, "'=' or 'in' after the loop variable");
+            skip_header(ts);
+            Iteration::IN(Spanned::new(Value::ERROR, ts.span_from(ts.position())))
+        };
+        let then = body(ts, diags, "'{' before the body of 'for'", "'for'");
+
+        return Some(For { label: None, var, mutable, iter, then });
+    }
+    return None;
+}
+
+fn expression(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<Expr> {
+    return binary_expr(ts, diags, 0);
+}
+
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+#[derive(PartialEq, Clone, Copy)]
+enum Assoc {
+    LEFT,
+    RIGHT,
+    /// chaining is an error, as in `a < b < c`
+    NONE,
+}
+
+const ASSIGN_POWER: u8 = 1;
+/// Binding power of the prefix operators: tighter than every infix operator
+/// but `**`, so that `-a * b` is `(-a) * b` and `-a ** b` is `-(a ** b)`
+const PREFIX_POWER: u8 = 12;
+const PREFIX_OPERATORS: [&str; 7] = ["-", "!", "~", "&", "&&", "*", "ref"];
+
+/// Binding power and associativity of an infix operator; a higher power binds tighter
+fn infix_power(op: &str) -> Option<(u8, Assoc)> {
+    return match op {
+        "=" | "+=" | "-=" | "*=" | "/=" | "%=" | "**=" | "&=" | "|=" | "^=" | "<<=" | ">>=" => Some((ASSIGN_POWER, Assoc::RIGHT)),
+        "||" => Some((2, Assoc::LEFT)),
+        "&&" => Some((3, Assoc::LEFT)),
+        "==" | "!=" | "<" | "<=" | ">" | ">=" => Some((4, Assoc::NONE)),
+        "|" => Some((5, Assoc::LEFT)),
+        "^" => Some((6, Assoc::LEFT)),
+        "&" => Some((7, Assoc::LEFT)),
+        "<<" | ">>" => Some((8, Assoc::LEFT)),
+        "+" | "-" => Some((9, Assoc::LEFT)),
+        "*" | "/" | "%" => Some((10, Assoc::LEFT)),
+        "as" => Some((11, Assoc::LEFT)),
+        "**" => Some((13, Assoc::RIGHT)),
+        _ => None,
+    };
+}
+
+/// Parses an expression made of operators binding tighter than `min_power`
+fn binary_expr(ts: &mut TokenStream, diags: &mut Diagnostics, min_power: u8) -> Option<Expr> {
+    let mut lhs = prefix_expr(ts, diags)?;
+    // power of the non-associative operator just parsed at this level
+    let mut chained = None;
+    while let TokenKind::OPERATOR(op) = ts.peek(0).kind {
+        let (power, assoc) = match infix_power(op) {
+            Some(x) => x,
+            None => break,
+        };
+        if power <= min_power {
+            break;
+        }
+        let op_span = ts.bump().span;
+        if chained == Some(power) {
+            diags.emit(Diagnostic::error("comparison operators cannot be chained")
+                .with_label(&op_span, "second comparison")
+                .with_secondary(&lhs.span, "first comparison")
+                .with_note("split the comparison with `&&`, as in `a < b && b < c`"));
+        }
+        chained = if assoc == Assoc::NONE { Some(power) } else { None };
+
+        if op == "as" {
+            let ty = match type_expr(ts, diags) {
+                Some(ty) => ty,
+                None => missing_type(ts, diags, "a type after 'as'"),
+            };
+            let span = lhs.span.to(&ts.prev().unwrap().span);
+            lhs = Spanned::new(Value::CAST(Box::new(lhs), ty), span);
+            continue;
+        }
+
+        let rhs_power = if assoc == Assoc::RIGHT { power - 1 } else { power };
+        let rhs = match binary_expr(ts, diags, rhs_power) {
+            Some(x) => x,
+            None => missing_expression(ts, diags, &format!("an expression after '{}'", op)),
+        };
+        let span = lhs.span.to(&rhs.span);
+        let value = if power == ASSIGN_POWER {
+            assignment(lhs, op, rhs, diags)
+        } else if op == "&&" || op == "||" {
+            Value::LOGICAL(Logical {
+                op,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            })
+        } else {
+            Value::FUNC_CALL(FuncCall {
+                func_name: Identifier(op.to_string()),
+                arg_list: vec![lhs, rhs],
+            })
+        };
+        lhs = Spanned::new(value, span);
+    }
+    return Some(lhs);
+}
+
+/// Builds `target = val`, where `op` is `=` or a compound assignment like `+=`
+fn assignment(target: Expr, op: &str, val: Expr, diags: &mut Diagnostics) -> Value {
+    match &target.node {
+        Value::VAR(_) | Value::FIELD(_, _) | Value::INDEX(_, _) | Value::DEREF(_) => {}
+        _ => {
+            diags.emit(Diagnostic::error("invalid left-hand side of assignment")
+                .with_label(&target.span, "cannot assign to this expression"));
+            return Value::ERROR;
+        }
+    }
+    if op == "=" {
+        return Value::ASSIGN(Assign { target: Box::new(target), val: Box::new(val) });
+    }
+    return Value::COMPOUND_ASSIGN(CompoundAssign {
+        op: Identifier(op[..op.len() - 1].to_string()),
+        target: Box::new(target),
+        val: Box::new(val),
+    });
+}
+
+fn prefix_expr(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<Expr> {
+    let start = ts.position();
+    let op = match ts.peek(0).kind {
+        TokenKind::OPERATOR(op) if PREFIX_OPERATORS.contains(&op) => op,
+        _ => return postfix_expr(ts, diags),
+    };
+    ts.bump();
+    let operand = match binary_expr(ts, diags, PREFIX_POWER) {
+        Some(x) => x,
+        None => missing_expression(ts, diags, &format!("an expression after '{}'", op)),
+    };
+    let span = ts.span_from(start);
+    let value = match op {
+        "&" | "ref" => Value::REF(Box::new(operand)),
+        // `&&x` is lexed as one operator but means `&(&x)`
+        "&&" => {
+            let inner = Span::new(ts.file(), span.start + 1, span.end);
+            Value::REF(Box::new(Spanned::new(Value::REF(Box::new(operand)), inner)))
+        }
+        "*" => Value::DEREF(Box::new(operand)),
+        _ => Value::FUNC_CALL(FuncCall {
+            func_name: Identifier(op.to_string()),
+            arg_list: vec![operand],
+        }),
+    };
+    return Some(Spanned::new(value, span));
+}
+
+/// Parses a primary expression followed by any calls `(...)`, indexes `[...]`,
+/// slices `[from..to]` and field accesses `.field`
+fn postfix_expr(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<Expr> {
+    let start = ts.position();
+    let mut expr = primary_expr(ts, diags)?;
     loop {
-        match statement(input, pos) {
-            Some((node, p)) => {
-                match node {
-                    Statement::NOTHING => {}
-                    _ => {
-                        b.0.push(node);
+        let value = if try_eat_operator(ts, "(") {
+            let arg_list = call_arguments(ts, diags);
+            match expr.node {
+                Value::VAR(func_name) => Value::FUNC_CALL(FuncCall { func_name, arg_list }),
+                callee => Value::CALL(Call {
+                    callee: Box::new(Spanned::new(callee, expr.span)),
+                    arg_list,
+                }),
+            }
+        } else if try_eat_operator(ts, "[") {
+            let mut from = None;
+            if !is_operator(ts, 0, "..") {
+                from = Some(match expression(ts, diags) {
+                    Some(x) => x,
+                    None => missing_expression(ts, diags, "an index after '['"),
+                });
+            }
+            if try_eat_operator(ts, "..") {
+                let mut to = None;
+                if !is_operator(ts, 0, "]") {
+                    to = Some(match expression(ts, diags) {
+                        Some(x) => x,
+                        None => missing_expression(ts, diags, "the end of the slice after '..'"),
+                    });
+                }
+                if !try_eat_operator(ts, "]") {
+                    expected(ts, diags, "']' to close the slice");
+                }
+                Value::SLICE(Box::new(expr), from.map(Box::new), to.map(Box::new))
+            } else {
+                if !try_eat_operator(ts, "]") {
+                    expected(ts, diags, "']' to close the index");
+                }
+                Value::INDEX(Box::new(expr), Box::new(from.unwrap()))
+            }
+        } else if try_eat_operator(ts, ".") {
+            match element(ts, &expr) {
+                Some(x) => x,
+                None => match identifier(ts) {
+                    Some(field) => Value::FIELD(Box::new(expr), field),
+                    None => {
+                        expected(ts, diags, "a field name after '.'");
+                        return Some(Spanned::new(Value::ERROR, ts.span_from(start)));
                     }
+                },
+            }
+        } else if is_operator(ts, 0, "::") {
+            let ty = match expr.node {
+                Value::VAR(ty) => ty,
+                _ => break,
+            };
+            ts.bump();
+            match identifier(ts) {
+                Some(x) => Value::PATH(ty, x),
+                None => {
+                    expected(ts, diags, "a name after '::'");
+                    return Some(Spanned::new(Value::ERROR, ts.span_from(start)));
                 }
-                pos = p;
             }
-            None => {
+        } else {
+            break;
+        };
+        expr = Spanned::new(value, ts.span_from(start));
+    }
+    return Some(expr);
+}
+
+/// Parses the index of `tuple.0` after the `.`. The lexer reads `t.0.1` as
+/// `t.` and the float `0.1`, which is split here into two accesses, the first
+/// applies to `expr`.
+fn element(ts: &mut TokenStream, expr: &Expr) -> Option<Value> {
+    let span = ts.peek(0).span.clone();
+    let text = span.text();
+    let (first, second) = match &ts.peek(0).kind {
+        TokenKind::INTEGER(_, None) => (text, None),
+        TokenKind::FLOAT(_, None) => match text.split_once('.') {
+            Some((a, b)) => (a, Some(b)),
+            None => return None,
+        },
+        _ => return None,
+    };
+    let index = |x: &str| -> Option<usize> {
+        if x.is_empty() || !x.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        return x.parse().ok();
+    };
+    let i = index(first)?;
+    let j = match second {
+        Some(x) => Some(index(x)?),
+        None => None,
+    };
+    ts.bump();
+    let x = Value::ELEMENT(Box::new(expr.clone()), i);
+    return Some(match j {
+        Some(j) => {
+            let inner = Span::new(&span.file, expr.span.start, span.start + first.len());
+            Value::ELEMENT(Box::new(Spanned::new(x, inner)), j)
+        }
+        None => x,
+    });
+}
+
+/// Parses `[a, b, c]` or `[value; count]` after the `[`
+fn array_literal(ts: &mut TokenStream, diags: &mut Diagnostics, start: usize) -> Expr {
+    if try_eat_operator(ts, "]") {
+        return Spanned::new(Value::ARRAY(vec![]), ts.span_from(start));
+    }
+    let first = match expression(ts, diags) {
+        Some(x) => x,
+        None => missing_expression(ts, diags, "an item or ']' after '['"),
+    };
+    if try_eat_semicolon(ts) {
+        let count = match expression(ts, diags) {
+            Some(x) => x,
+            None => missing_expression(ts, diags, "the number of items after ';'"),
+        };
+        if !try_eat_operator(ts, "]") {
+            expected(ts, diags, "']' to close the array");
+        }
+        return Spanned::new(Value::REPEAT(Box::new(first), Box::new(count)), ts.span_from(start));
+    }
+    let mut items = vec![first];
+    loop {
+        if try_eat_operator(ts, "]") {
+            break;
+        }
+        if try_eat_operator(ts, ",") {
+            if try_eat_operator(ts, "]") {
                 break;
             }
+            items.push(match expression(ts, diags) {
+                Some(x) => x,
+                None => missing_expression(ts, diags, "an item after ','"),
+            });
+            continue;
         }
+        expected(ts, diags, "',' or ']' in the items of an array");
+        skip_to_closing(ts, "]");
+        break;
     }
-    return (b, pos);
+    return Spanned::new(Value::ARRAY(items), ts.span_from(start));
 }
 
+/// Parses `(value)`, or the tuple `(a, b)`, `(a,)` or `()`, after the `(`
+fn parenthesized(ts: &mut TokenStream, diags: &mut Diagnostics, start: usize) -> Expr {
+    if try_eat_operator(ts, ")") {
+        return Spanned::new(Value::TUPLE(vec![]), ts.span_from(start));
+    }
+    let v = match expression(ts, diags) {
+        Some(v) => v,
+        None => missing_expression(ts, diags, "an expression after '('"),
+    };
+    if !is_operator(ts, 0, ",") {
+        if !try_eat_operator(ts, ")") {
+            expected(ts, diags, "')' to close the parenthesis");
+        }
+        return v;
+    }
+    let mut elements = vec![v];
+    loop {
+        if try_eat_operator(ts, ")") {
+            break;
+        }
+        if try_eat_operator(ts, ",") {
+            if try_eat_operator(ts, ")") {
+                break;
+            }
+            elements.push(match expression(ts, diags) {
+                Some(x) => x,
+                None => missing_expression(ts, diags, "an element after ','"),
+            });
+            continue;
+        }
+        expected(ts, diags, "',' or ')' in the elements of a tuple");
+        skip_to_closing(ts, ")");
+        break;
+    }
+    return Spanned::new(Value::TUPLE(elements), ts.span_from(start));
+}
+
+/// Parses the arguments of a call after its `(`, up to and including the `)`
+fn call_arguments(ts: &mut TokenStream, diags: &mut Diagnostics) -> Vec<Expr> {
+    let mut arg_list = vec![];
+    let mut expect_comma = false;
+    loop {
+        if !expect_comma {
+            if let Some(v) = expression(ts, diags) {
+                arg_list.push(v);
+                expect_comma = true;
+                continue;
+            }
+        } else if try_eat_operator(ts, ",") {
+            expect_comma = false;
+            continue;
+        }
+        if try_eat_operator(ts, ")") {
+            break;
+        }
+        expected(ts, diags, "',' or ')' in the argument list of a function call");
+        skip_to_closing(ts, ")");
+        break;
+    }
+    return arg_list;
+}
 
-pub fn parse(input: &str, pos: usize) -> Block {
-    let (b, pos) = block(input, pos);
+fn primary_expr(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<Expr> {
+    let start = ts.position();
+    let labeled = matches!(ts.peek(0).kind, TokenKind::LABEL(_)) && is_operator(ts, 1, ":") && is_keyword(ts, 2, "loop");
+    if labeled || is_keyword(ts, 0, "loop") {
+        let label = loop_label(ts);
+        let mut x = loop_expr(ts, diags).unwrap();
+        x.label = label;
+        return Some(Spanned::new(Value::LOOP(x), ts.span_from(start)));
+    }
+    if let Some(x) = match_expr(ts, diags) {
+        return Some(Spanned::new(x, ts.span_from(start)));
+    }
+    if let Some(x) = lambda(ts, diags) {
+        return Some(x);
+    }
+    if try_eat_operator(ts, "[") {
+        return Some(array_literal(ts, diags, start));
+    }
+    if try_eat_operator(ts, "(") {
+        return Some(parenthesized(ts, diags, start));
+    }
+
+    let span = ts.peek(0).span.clone();
+    let value = match &ts.peek(0).kind {
+        TokenKind::FLOAT(f, t) => Value::FLOAT(*f, *t),
+        TokenKind::INTEGER(i, t) => Value::INT(*i, *t),
+        TokenKind::STRING(x) => Value::STRING(x.clone()),
+        TokenKind::CSTRING(x) => Value::CSTRING(x.clone()),
+        TokenKind::CHAR(x) => Value::CHAR(*x),
+        TokenKind::KEYWORD("true") => Value::BOOL(true),
+        TokenKind::KEYWORD("false") => Value::BOOL(false),
+        TokenKind::IDENTIFIER(id) => Value::VAR(id.clone()),
+        _ => return None,
+    };
+    ts.bump();
+    return Some(Spanned::new(value, span));
+}
 
-    if let (Token::EOF, _) = next_token(input, pos) {} else {
-        error(&format!("Unknown error at: {}", pos));
+/// Collects the lines of consecutive doc comments under the cursor
+fn doc_comments(ts: &mut TokenStream) -> Vec<String> {
+    let mut doc = vec![];
+    while let TokenKind::DOC_COMMENT(x) = &ts.peek(0).kind {
+        doc.push(x.clone());
+        ts.bump();
+    }
+    return doc;
+}
+
+fn statement(ts: &mut TokenStream, diags: &mut Diagnostics) -> Option<Stmt> {
+    let start = ts.position();
+    let spanned = |stmt, ts: &TokenStream| Some(Spanned::new(stmt, ts.span_from(start)));
+    let doc = doc_comments(ts);
+    let attributes_start = ts.position();
+    let derive = attributes(ts, diags);
+    if let Some(mut decl) = struct_decl(ts, diags) {
+        decl.doc = doc;
+        decl.derive = derive;
+        return spanned(Statement::STRUCT(Rc::new(decl)), ts);
+    }
+    if ts.position() != attributes_start {
+        diags.emit(Diagnostic::error("attributes are only allowed on structs")
+            .with_label(&ts.span_from(attributes_start), "not followed by a struct"));
+    }
+    if !doc.is_empty() {
+        if let Some(mut decl) = func_decl(ts, diags) {
+            decl.doc = doc;
+            return spanned(Statement::FUNC_DECL(Rc::new(decl)), ts);
+        }
+        if let Some(mut decl) = interface_decl(ts, diags) {
+            decl.doc = doc;
+            return spanned(Statement::INTERFACE(Rc::new(decl)), ts);
+        }
+        if let Some(mut decl) = enum_decl(ts, diags) {
+            decl.doc = doc;
+            return spanned(Statement::ENUM(Rc::new(decl)), ts);
+        }
+        diags.emit(Diagnostic::warning("unused doc comment")
+            .with_label(&ts.span_from(start), "doc comments are only attached to functions and types"));
+        return match statement(ts, diags) {
+            Some(x) => Some(x),
+            None => spanned(Statement::NOTHING, ts),
+        };
+    }
+    if let Some(x) = let_stmt(ts, diags) {
+        expect_semicolon(ts, diags);
+        return spanned(Statement::LET(x), ts);
+    }
+    if let Some(rtn) = return_stmt(ts, diags) {
+        expect_semicolon(ts, diags);
+        return spanned(Statement::RETURN(rtn), ts);
+    }
+    if let Some(decl) = func_decl(ts, diags) {
+        return spanned(Statement::FUNC_DECL(Rc::new(decl)), ts);
+    }
+    if let Some(decl) = interface_decl(ts, diags) {
+        return spanned(Statement::INTERFACE(Rc::new(decl)), ts);
+    }
+    if let Some(decl) = enum_decl(ts, diags) {
+        return spanned(Statement::ENUM(Rc::new(decl)), ts);
+    }
+    if let Some(x) = impl_block(ts, diags) {
+        return spanned(Statement::IMPL(x), ts);
+    }
+    if let Some(if_) = if_stmt(ts, diags) {
+        return spanned(Statement::IF(if_), ts);
+    }
+
+    if let Some(x) = loop_stmt(ts, diags) {
+        return spanned(x, ts);
+    }
+
+    if let Some(x) = match_expr(ts, diags) {
+        try_eat_semicolon(ts);
+        return match x {
+            Value::MATCH(x) => spanned(Statement::MATCH(*x), ts),
+            x => spanned(Statement::EXPRESSION(Spanned::new(x, ts.span_from(start))), ts),
+        };
+    }
+
+    if let Some(x) = jump_stmt(ts, diags) {
+        expect_semicolon(ts, diags);
+        return spanned(x, ts);
+    }
+
+    if try_eat_semicolon(ts) {
+        return spanned(Statement::NOTHING, ts);
+    }
+
+    if let Some(val) = expression(ts, diags) {
+        expect_semicolon(ts, diags);
+        return spanned(Statement::EXPRESSION(val), ts);
+    }
+    return None;
+}
+
+pub fn block(ts: &mut TokenStream, diags: &mut Diagnostics) -> Block {
+    let mut b = Block(vec![]);
+    loop {
+        let start = ts.position();
+        let reported = diags.items.len();
+        if let Some(node) = statement(ts, diags) {
+            match &node.node {
+                Statement::NOTHING => {}
+                Statement::STRUCT(x) => {
+                    let derived = derive::derive(x, diags);
+                    b.0.push(node);
+                    b.0.extend(derived);
+                }
+                _ => {
+                    b.0.push(node);
+                }
+            }
+            continue;
+        }
+        match ts.peek(0).kind {
+            TokenKind::EOF | TokenKind::OPERATOR("}") if ts.position() == start => break,
+            _ => {}
+        }
+        // the statement may have been reported already by the rule that gave up on it
+        if diags.items.len() == reported {
+            unexpected(ts.peek(0), diags, "a statement");
+        }
+        // `synchronize` skips a `{ ... }` as a whole
+        if ts.position() == start && !is_operator(ts, 0, "{") {
+            ts.bump();
+        }
+        synchronize(ts);
+        b.0.push(Spanned::new(Statement::ERROR, ts.span_from(start)));
+    }
+    return b;
+}
+
+
+/// Parses a whole file. Syntax errors are all reported to `diags`; the returned
+/// block then contains `ERROR` nodes where the parser had to skip input.
+pub fn parse(ts: &mut TokenStream, diags: &mut Diagnostics) -> Block {
+    let mut b = Block(vec![]);
+    loop {
+        let blk = block(ts, diags);
+        b.0.extend(blk.0);
+        let tk = ts.bump();
+        if tk.kind == TokenKind::EOF {
+            break;
+        }
+        diags.emit(Diagnostic::error(&format!("unexpected {}", tk.kind))
+            .with_label(&tk.span, "unmatched closing bracket"));
     }
 
     return b;
@@ -500,4 +2296,4 @@ pub fn parse(input: &str, pos: usize) -> Block {
 // func_decl: fn (arg1, arg2, arg3) -> {blblbl}
 // if cond { } else {}
 // if cond { }
-// if cond { } else if cond {}
\ No newline at end of file
+// if cond { } else if cond {}
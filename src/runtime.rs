@@ -1,16 +1,36 @@
+use super::diagnostics::*;
+use super::lexer::Identifier;
 use super::parser::*;
+use super::span::*;
+use super::types::*;
 
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use crate::runtime::PrimitiveType::VOID;
-use crate::util::{fatal_, fatal};
 use std::fmt::{Display, Formatter, Error};
 use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::rc::Rc;
 
+/// Result of evaluating anything at runtime; the error aborts the execution
+pub type RunResult<T> = Result<T, Diagnostic>;
+
+/// How a block finished running
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+pub enum Completion {
+    /// ran to its end; a `loop` also has the value it was broken out with
+    NORMAL(PrimitiveType),
+    /// `return`, with the value to return from the function
+    RETURN(PrimitiveType),
+    /// `break`, with the label it targets, its value and where it is
+    BREAK(Option<Identifier>, PrimitiveType, Span),
+    /// `continue`, with the label it targets and where it is
+    CONTINUE(Option<Identifier>, Span),
+}
 
 #[derive(Clone)]
 pub struct BuiltInFunc {
-    execute: fn(Rc<RefCell<Scope>>, &FuncCall) -> PrimitiveType
+    execute: fn(Rc<RefCell<Scope>>, &FuncCall, &Span) -> RunResult<PrimitiveType>
 }
 
 impl PartialEq for BuiltInFunc {
@@ -19,224 +39,2121 @@ impl PartialEq for BuiltInFunc {
     }
 }
 
+/// A function with the scope it was declared in, where its body looks up names
+#[derive(Clone)]
+pub struct Closure {
+    pub decl: Rc<FuncDecl>,
+    pub env: Rc<RefCell<Scope>>,
+}
+
+impl PartialEq for Closure {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.decl, &other.decl) && Rc::ptr_eq(&self.env, &other.env)
+    }
+}
+
+/// A struct type, with the methods of its `impl` blocks
+pub struct StructType {
+    pub decl: Rc<StructDecl>,
+    pub methods: HashMap<String, Closure>,
+    /// the interfaces it implements
+    pub interfaces: Vec<String>,
+}
+
+impl StructType {
+    fn implements(&self, interface: &str) -> bool {
+        return self.interfaces.iter().any(|x| x == interface);
+    }
+}
+
+impl PartialEq for StructType {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.decl, &other.decl)
+    }
+}
+
+/// An instance of a struct, shared by all the values referring to it
+#[derive(PartialEq)]
+pub struct Object {
+    pub ty: Rc<RefCell<StructType>>,
+    /// in the order of their declaration
+    pub fields: Vec<PrimitiveType>,
+}
+
+impl Object {
+    fn field_index(&self, field: &Identifier) -> Option<usize> {
+        return self.ty.as_ref().borrow().decl.fields.iter().position(|x| x.node.id == *field);
+    }
+}
+
+/// The items of an array, shared by all the values referring to it
+#[derive(PartialEq, Clone)]
+pub struct Array {
+    pub items: Rc<RefCell<Vec<PrimitiveType>>>,
+    /// whether the length is stored with the items, as in the Rust-like
+    /// `[T; N]`; a C-like `T[]` does not know its length
+    pub sized: bool,
+}
+
+/// A value of an enum: one of its variants, with its payload
+pub struct Variant {
+    pub ty: Rc<EnumDecl>,
+    pub index: usize,
+    pub fields: Vec<PrimitiveType>,
+}
+
+impl Variant {
+    fn name(&self) -> &Identifier {
+        return &self.ty.variants[self.index].node.name;
+    }
+}
+
+impl PartialEq for Variant {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.ty, &other.ty) && self.index == other.index && self.fields == other.fields
+    }
+}
+
+/// Memory allocated by `new`, shared by all the references to it
+pub struct Allocation {
+    /// `None` once deleted
+    value: Option<PrimitiveType>,
+    /// where it was deleted
+    deleted: Option<Span>,
+}
+
+/// What a reference refers to
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+#[derive(Clone)]
+pub enum Target {
+    HEAP(Rc<RefCell<Allocation>>),
+    /// `&x`: the variable named `x`, borrowed at a span. Declaring another `x`
+    /// later does not change what it refers to.
+    STACK(Rc<RefCell<Variable>>, String, Span),
+    /// a reference reset by the `delete` at a span, or never set
+    NULL(Option<Span>),
+}
+
+/// A reference `ref T`, or a pointer `*T` after `as *T`
+#[derive(Clone)]
+pub struct Reference {
+    pub target: Target,
+    pub pointer: bool,
+}
+
+impl Reference {
+    fn null() -> Reference {
+        return Reference { target: Target::NULL(None), pointer: false };
+    }
+
+    /// The value referred to, unless it is deleted or null
+    fn referent(&self) -> Option<PrimitiveType> {
+        return match &self.target {
+            Target::HEAP(x) => x.as_ref().borrow().value.clone(),
+            Target::STACK(x, _, _) if x.as_ref().borrow().live => x.as_ref().borrow().value.clone(),
+            Target::STACK(_, _, _) => None,
+            Target::NULL(_) => None,
+        };
+    }
+}
+
+/// References are equal if they refer to the same place
+impl PartialEq for Reference {
+    fn eq(&self, other: &Self) -> bool {
+        return match (&self.target, &other.target) {
+            (Target::HEAP(x), Target::HEAP(y)) => Rc::ptr_eq(x, y),
+            (Target::STACK(x, _, _), Target::STACK(y, _, _)) => Rc::ptr_eq(x, y),
+            (Target::NULL(_), Target::NULL(_)) => true,
+            _ => false,
+        };
+    }
+}
+
 #[derive(PartialEq, Clone)]
 pub enum PrimitiveType {
     F64(f64),
-    I32(i32),
-    FUNCTION(FuncDecl),
+    F32(f32),
+    /// an integer of any width, always kept within the range of its type
+    INT(i128, IntType),
+    STR(String),
+    CSTR(String),
+    CHAR(char),
+    FUNCTION(Closure),
     BUILTIN(BuiltInFunc),
+    /// a struct type, which constructs an object when called
+    STRUCT(Rc<RefCell<StructType>>),
+    /// an interface, usable as the type of the objects implementing it
+    INTERFACE(Rc<InterfaceDecl>),
+    OBJECT(Rc<RefCell<Object>>),
+    /// an enum type, whose variants are constructed by `Enum::Variant(...)`
+    ENUM(Rc<EnumDecl>),
+    VARIANT(Rc<Variant>),
+    ARRAY(Array),
+    TUPLE(Vec<PrimitiveType>),
+    REF(Reference),
     VOID,
 }
 
+impl PrimitiveType {
+    pub fn type_name(&self) -> String {
+        let name = match self {
+            PrimitiveType::F64(_) => "f64",
+            PrimitiveType::F32(_) => "f32",
+            PrimitiveType::INT(_, t) => t.name(),
+            PrimitiveType::STR(_) => "ref str",
+            PrimitiveType::CSTR(_) => "ref cstr",
+            PrimitiveType::CHAR(_) => "char",
+            PrimitiveType::FUNCTION(_) => "function",
+            PrimitiveType::BUILTIN(_) => "built-in function",
+            PrimitiveType::STRUCT(_) => "struct",
+            PrimitiveType::INTERFACE(_) => "interface",
+            PrimitiveType::OBJECT(x) => return x.as_ref().borrow().ty.as_ref().borrow().decl.name.0.clone(),
+            PrimitiveType::ENUM(_) => "enum",
+            PrimitiveType::VARIANT(x) => return x.ty.name.0.clone(),
+            PrimitiveType::ARRAY(x) => {
+                let items = x.items.as_ref().borrow();
+                let item = items.first().map_or("_".to_string(), |x| x.type_name());
+                return match x.sized {
+                    true => format!("[{}; {}]", item, items.len()),
+                    false => format!("{}[]", item),
+                };
+            }
+            PrimitiveType::TUPLE(x) => {
+                let names: Vec<String> = x.iter().map(|x| x.type_name()).collect();
+                return match names.len() {
+                    1 => format!("({},)", names[0]),
+                    _ => format!("({})", names.join(", ")),
+                };
+            }
+            PrimitiveType::REF(x) => {
+                return match (x.referent(), &x.target) {
+                    (Some(v), _) => format!("{}{}", if x.pointer { "*" } else { "ref " }, v.type_name()),
+                    (None, Target::NULL(_)) => "null".to_string(),
+                    (None, _) => "deleted reference".to_string(),
+                };
+            }
+            VOID => "void",
+        };
+        return name.to_string();
+    }
+}
+
 impl Display for PrimitiveType {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
         match self {
             PrimitiveType::F64(x) => { std::fmt::Display::fmt(x, f) }
-            PrimitiveType::I32(x) => { std::fmt::Display::fmt(x, f) }
-            PrimitiveType::FUNCTION(_) => { unimplemented!() }
-            PrimitiveType::BUILTIN(_) => { unimplemented!() }
+            PrimitiveType::F32(x) => { std::fmt::Display::fmt(x, f) }
+            PrimitiveType::INT(x, _) => { std::fmt::Display::fmt(x, f) }
+            PrimitiveType::STR(x) | PrimitiveType::CSTR(x) => { f.write_str(x) }
+            PrimitiveType::CHAR(x) => { std::fmt::Display::fmt(x, f) }
+            PrimitiveType::FUNCTION(x) => { write!(f, "fn {}", x.decl.func_name.0) }
+            PrimitiveType::BUILTIN(_) => { f.write_str("built-in function") }
+            PrimitiveType::STRUCT(x) => { write!(f, "struct {}", x.as_ref().borrow().decl.name.0) }
+            PrimitiveType::INTERFACE(x) => { write!(f, "interface {}", x.name.0) }
+            PrimitiveType::OBJECT(x) => {
+                let x = x.as_ref().borrow();
+                let ty = x.ty.as_ref().borrow();
+                write!(f, "{} {{", ty.decl.name.0)?;
+                for (i, field) in ty.decl.fields.iter().enumerate() {
+                    write!(f, "{} {}: {}", if i > 0 { "," } else { "" }, field.node.id.0, x.fields[i])?;
+                }
+                f.write_str(" }")
+            }
+            PrimitiveType::ENUM(x) => { write!(f, "enum {}", x.name.0) }
+            PrimitiveType::VARIANT(x) => {
+                write!(f, "{}::{}", x.ty.name.0, x.name().0)?;
+                if !x.fields.is_empty() {
+                    f.write_str("(")?;
+                    for (i, field) in x.fields.iter().enumerate() {
+                        write!(f, "{}{}", if i > 0 { ", " } else { "" }, field)?;
+                    }
+                    f.write_str(")")?;
+                }
+                Ok(())
+            }
+            PrimitiveType::ARRAY(x) => {
+                f.write_str("[")?;
+                for (i, item) in x.items.as_ref().borrow().iter().enumerate() {
+                    write!(f, "{}{}", if i > 0 { ", " } else { "" }, item)?;
+                }
+                f.write_str("]")
+            }
+            PrimitiveType::TUPLE(x) => {
+                f.write_str("(")?;
+                for (i, element) in x.iter().enumerate() {
+                    write!(f, "{}{}", if i > 0 { ", " } else { "" }, element)?;
+                }
+                f.write_str(if x.len() == 1 { ",)" } else { ")" })
+            }
+            PrimitiveType::REF(x) => {
+                match (x.referent(), &x.target) {
+                    (Some(v), _) => write!(f, "{}{}", if x.pointer { "*" } else { "&" }, v),
+                    (None, Target::NULL(_)) => f.write_str("null"),
+                    (None, Target::STACK(_, _, _)) => f.write_str("<dangling>"),
+                    (None, _) => f.write_str("<deleted>"),
+                }
+            }
             VOID => { f.write_str("void") }
         }
     }
 }
 
+/// A declared name; `value` is `None` until a `let` without initializer is assigned.
+/// Assignments are checked against the declared type `ty`, if there is one.
+#[derive(Clone)]
+pub struct Variable {
+    pub value: Option<PrimitiveType>,
+    pub mutable: bool,
+    pub ty: Option<Type>,
+    /// `false` once the scope declaring it has ended, leaving references to it dangling
+    pub live: bool,
+}
+
+impl Variable {
+    /// Assigns `value` to the variable `name`. Immutable variables may only be
+    /// assigned once, to initialize them.
+    fn assign(&mut self, name: &str, value: PrimitiveType, span: &Span) -> RunResult<()> {
+        if !self.mutable && self.value.is_some() {
+            return Err(Diagnostic::error(&format!("cannot assign twice to immutable variable `{}`", name))
+                .with_label(span, "cannot assign twice to immutable variable")
+                .with_note(&format!("consider making this binding mutable: `mut {}`", name)));
+        }
+        self.value = Some(value);
+        return Ok(());
+    }
+}
+
 pub struct Scope {
     pub parent: Option<Rc<RefCell<Scope>>>,
-    pub local: HashMap<String, PrimitiveType>,
+    pub local: HashMap<String, Rc<RefCell<Variable>>>,
 }
 
 impl Scope {
-    fn lookup(&self, name: &str) -> PrimitiveType {
+    fn lookup(&self, name: &str, span: &Span) -> RunResult<PrimitiveType> {
         debug!("Looking for {}", name);
-        self.try_lookup(name).expect(&format!("Error because no value called {} at this scope", name))
+        match self.try_lookup(name) {
+            Some(Variable { value: Some(x), .. }) => Ok(x),
+            Some(Variable { value: None, .. }) => Err(Diagnostic::error(&format!("used binding `{}` isn't initialized", name))
+                .with_label(span, &format!("`{}` used here but it isn't initialized", name))),
+            None => Err(Diagnostic::error(&format!("cannot find value `{}` in this scope", name))
+                .with_label(span, "not found in this scope")),
+        }
     }
-    fn try_lookup(&self, name: &str) -> Option<PrimitiveType> {
+    fn try_lookup(&self, name: &str) -> Option<Variable> {
+        return self.slot(name).map(|x| x.as_ref().borrow().clone());
+    }
+
+    /// The nearest declaration of `name`, which `&name` refers to
+    fn slot(&self, name: &str) -> Option<Rc<RefCell<Variable>>> {
         match self.local.get(name) {
             None => {
                 match &self.parent {
                     None => None,
                     Some(x) => {
-                        x.as_ref().borrow().try_lookup(name)
+                        x.as_ref().borrow().slot(name)
                     }
                 }
             }
             Some(x) => { Some(x.clone()) }
         }
     }
+
+    /// Declares `name` in this scope, shadowing any earlier declaration
+    pub fn declare(&mut self, name: &str, value: Option<PrimitiveType>, mutable: bool) {
+        self.declare_typed(name, value, mutable, None);
+    }
+
+    /// Declares `name` like `declare`, with the declared type `ty`
+    pub fn declare_typed(&mut self, name: &str, value: Option<PrimitiveType>, mutable: bool, ty: Option<Type>) {
+        self.local.insert(name.to_string(), Rc::new(RefCell::new(Variable { value, mutable, ty, live: true })));
+    }
+
+    /// Ends this scope, after which references to its variables dangle
+    fn end(&self) {
+        for x in self.local.values() {
+            x.as_ref().borrow_mut().live = false;
+        }
+    }
 }
 
-fn get_value(scope: Rc<RefCell<Scope>>, v: &Value) -> PrimitiveType {
-    match v {
-        Value::VAR(id) => { scope.as_ref().borrow().lookup(&id.0.to_string()) }
-        Value::FLOAT(f) => { PrimitiveType::F64(*f) }
+fn get_value(scope: Rc<RefCell<Scope>>, v: &Expr) -> RunResult<PrimitiveType> {
+    let span = &v.span;
+    match &v.node {
+        Value::VAR(id) => { scope.as_ref().borrow().lookup(&id.0, span) }
+        Value::FLOAT(f, t) => {
+            match t {
+                Some(FloatType::F32) => Ok(PrimitiveType::F32(*f as f32)),
+                _ => Ok(PrimitiveType::F64(*f)),
+            }
+        }
         Value::FUNC_CALL(fc) => {
             let func;
             {
-                func = scope.as_ref().borrow().lookup(&fc.func_name.0.to_string());
+                func = scope.as_ref().borrow().lookup(&fc.func_name.0, span)?;
+            }
+            return call(scope, func, None, fc, span);
+        }
+        Value::CALL(c) => {
+            // named after the callee's source text in error messages
+            let fc = FuncCall {
+                func_name: Identifier(c.callee.span.text().to_string()),
+                arg_list: c.arg_list.clone(),
+            };
+            if let Value::PATH(ty, name) = &c.callee.node {
+                let found = scope.as_ref().borrow().try_lookup(&ty.0);
+                if let Some(Variable { value: Some(PrimitiveType::ENUM(decl)), .. }) = found {
+                    return construct_variant(scope, decl, name, &c.arg_list, span);
+                }
             }
-            match func {
-                PrimitiveType::FUNCTION(fd) => {
-                    if fc.arg_list.len() != fd.arg_list.len() {
-                        fatal("Error: function arguments number not accepted");
+            if let Value::FIELD(x, name) = &c.callee.node {
+                let receiver = dereferenced(get_value(scope.clone(), x)?, &x.span)?;
+                if let Some(method) = method(&receiver, name) {
+                    if method.decl.arg_list.first().is_some_and(|arg| arg.node.mutable) {
+                        check_borrow(&scope, &receiver, x)?;
                     }
-                    let mut args = HashMap::new();
-                    for i in 0..fc.arg_list.len() {
-                        args.insert((fd.arg_list[i].0).0.clone(), get_value(scope.clone(), &fc.arg_list[i]));
+                    return call(scope, PrimitiveType::FUNCTION(method), Some(receiver), &fc, span);
+                }
+                let func = field_value(&receiver, name, &c.callee.span)?;
+                return call(scope, func, None, &fc, span);
+            }
+            let func = get_value(scope.clone(), &c.callee)?;
+            return call(scope, func, None, &fc, span);
+        }
+        Value::ASSIGN(ass) => {
+            let val = get_value(scope.clone(), &ass.val)?;
+            let val = copied(scope.clone(), val, &ass.val)?;
+            assign(scope, &ass.target, val.clone(), &ass.val, span)?;
+            Ok(val)
+        }
+        Value::COMPOUND_ASSIGN(x) => {
+            // the parts of the place that are not variables are evaluated
+            // once, into variables of a scope of their own
+            let pinned = Rc::new(RefCell::new(Scope {
+                parent: Some(scope),
+                local: HashMap::new(),
+            }));
+            let target = pin(&pinned, &x.target)?;
+            let fc = FuncCall { func_name: x.op.clone(), arg_list: vec![target.clone(), (*x.val).clone()] };
+            let expr = Spanned::new(Value::FUNC_CALL(fc), x.target.span.to(&x.val.span));
+            let val = get_value(pinned.clone(), &expr)?;
+            assign(pinned.clone(), &target, val.clone(), &expr, span)?;
+            pinned.as_ref().borrow().end();
+            Ok(val)
+        }
+        Value::LOGICAL(x) => {
+            let lhs = to_boolean(&get_value(scope.clone(), &x.lhs)?);
+            let result = match x.op {
+                "&&" => lhs && to_boolean(&get_value(scope, &x.rhs)?),
+                _ => lhs || to_boolean(&get_value(scope, &x.rhs)?),
+            };
+            Ok(PrimitiveType::INT(result as i128, IntType::I32))
+        }
+        Value::CAST(x, ty) => cast(get_value(scope, x)?, ty, span),
+        Value::INDEX(x, i) => {
+            let v = dereferenced(get_value(scope.clone(), x)?, &x.span)?;
+            let index = get_value(scope, i)?;
+            return index_value(&v, &index, span, &i.span);
+        }
+        Value::SLICE(x, from, to) => {
+            let v = get_value(scope.clone(), x)?;
+            return slice_value(scope, &v, from, to, span);
+        }
+        Value::ARRAY(x) => {
+            let mut items = vec![];
+            for item in x.iter() {
+                items.push(get_value(scope.clone(), item)?);
+            }
+            Ok(PrimitiveType::ARRAY(Array { items: Rc::new(RefCell::new(items)), sized: true }))
+        }
+        Value::REPEAT(x, n) => {
+            let v = get_value(scope.clone(), x)?;
+            let count = match get_value(scope.clone(), n)? {
+                PrimitiveType::INT(n, _) if n >= 0 => n,
+                count => return Err(Diagnostic::error(&format!("the number of items must be a non-negative integer, found {}", count))
+                    .with_label(&n.span, "invalid number of items")),
+            };
+            if let PrimitiveType::OBJECT(o) = &v {
+                let t = o.as_ref().borrow().ty.clone();
+                let t = t.as_ref().borrow();
+                if count > 1 && !t.implements("Copy") {
+                    return Err(Diagnostic::error(&format!("cannot repeat `{}`, as `{}` does not implement `Copy`", x.span.text(), t.decl.name.0))
+                        .with_label(&x.span, "repeated here")
+                        .with_note("derive it with `#[derive(Copy)]`"));
+                }
+            }
+            let mut items = vec![];
+            let reserved = usize::try_from(count).ok().filter(|&count| items.try_reserve_exact(count).is_ok());
+            let count = match reserved {
+                Some(count) => count,
+                None => return Err(Diagnostic::error(&format!("cannot allocate an array of {} items", count))
+                    .with_label(&n.span, "too many items")),
+            };
+            // each item is a copy of its own, not the same array or object
+            for _ in 0..count {
+                items.push(duplicate(scope.clone(), v.clone(), &x.span)?);
+            }
+            Ok(PrimitiveType::ARRAY(Array { items: Rc::new(RefCell::new(items)), sized: true }))
+        }
+        Value::TUPLE(x) => {
+            let mut elements = vec![];
+            for element in x.iter() {
+                elements.push(get_value(scope.clone(), element)?);
+            }
+            Ok(PrimitiveType::TUPLE(elements))
+        }
+        Value::ELEMENT(x, i) => {
+            return match dereferenced(get_value(scope, x)?, &x.span)? {
+                PrimitiveType::TUPLE(mut elements) if *i < elements.len() => Ok(elements.swap_remove(*i)),
+                v => Err(Diagnostic::error(&format!("no element {} on type {}", i, v.type_name()))
+                    .with_label(span, "unknown element")),
+            };
+        }
+        Value::FIELD(x, field) => field_value(&dereferenced(get_value(scope, x)?, &x.span)?, field, span),
+        Value::PATH(ty, name) => {
+            let found = scope.as_ref().borrow().lookup(&ty.0, span)?;
+            let t = match found {
+                PrimitiveType::STRUCT(t) => t,
+                PrimitiveType::ENUM(decl) => return construct_variant(scope, decl, name, &[], span),
+                v => return Err(Diagnostic::error(&format!("`{}` is not a struct or enum", ty.0))
+                    .with_label(span, &format!("`{}` is {}", ty.0, v.type_name()))),
+            };
+            let t = t.as_ref().borrow();
+            match t.methods.get(&name.0) {
+                Some(x) => Ok(PrimitiveType::FUNCTION(x.clone())),
+                None => Err(Diagnostic::error(&format!("no function named `{}` in struct `{}`", name.0, ty.0))
+                    .with_label(span, "function not found in the impl blocks")),
+            }
+        }
+        Value::LAMBDA(x) => Ok(PrimitiveType::FUNCTION(Closure { decl: x.clone(), env: scope })),
+        Value::MATCH(x) => match run_match(scope, x)? {
+            Completion::NORMAL(v) => Ok(v),
+            Completion::RETURN(_) => Err(Diagnostic::error("`return` cannot leave a `match` used as a value")
+                .with_label(span, "this `match` returned from the function")),
+            Completion::BREAK(_, _, span) => Err(Diagnostic::error("`break` cannot leave a `match` used as a value")
+                .with_label(&span, "this would skip the value of the `match`")),
+            Completion::CONTINUE(_, span) => Err(Diagnostic::error("`continue` cannot leave a `match` used as a value")
+                .with_label(&span, "this would skip the value of the `match`")),
+        },
+        Value::LOOP(x) => match run_loop(scope, x)? {
+            Completion::RETURN(_) => Err(Diagnostic::error("`return` cannot leave a `loop` used as a value")
+                .with_label(span, "this `loop` returned from the function")),
+            c => finished(c),
+        },
+        Value::REF(x) => {
+            let id = match &x.node {
+                Value::VAR(id) => id,
+                _ => return Err(Diagnostic::error("cannot borrow a temporary value")
+                    .with_label(&x.span, "not a variable")
+                    .with_note("bind the value to a variable first, or allocate it with `new`")),
+            };
+            let slot = match scope.as_ref().borrow().slot(&id.0) {
+                Some(x) => x,
+                None => return scope.as_ref().borrow().lookup(&id.0, &x.span),
+            };
+            let target = Target::STACK(slot, id.0.clone(), span.clone());
+            Ok(PrimitiveType::REF(Reference { target, pointer: false }))
+        }
+        Value::DEREF(x) => {
+            return match get_value(scope, x)? {
+                PrimitiveType::REF(r) => deref(&r, span),
+                v => Err(Diagnostic::error(&format!("cannot dereference a value of type {}", v.type_name()))
+                    .with_label(&x.span, "not a reference")),
+            };
+        }
+        Value::INT(i, t) => Ok(PrimitiveType::INT(*i as i128, t.unwrap_or_else(|| IntType::of_literal(*i as i128)))),
+        Value::BOOL(b) => Ok(PrimitiveType::INT(*b as i128, IntType::I32)),
+        Value::STRING(x) => Ok(PrimitiveType::STR(x.clone())),
+        Value::CSTRING(x) => Ok(PrimitiveType::CSTR(x.clone())),
+        Value::CHAR(x) => Ok(PrimitiveType::CHAR(*x)),
+        Value::ERROR => Err(Diagnostic::error("cannot evaluate an expression that failed to parse")
+            .with_label(span, "syntax error here")),
+    }
+}
 
-                    }
+/// Reports using a reference whose value was deleted, or which is null
+fn dangling(r: &Reference, span: &Span) -> Diagnostic {
+    return match &r.target {
+        Target::HEAP(x) => {
+            let e = Diagnostic::error("use after delete")
+                .with_label(span, "this reference was deleted");
+            match &x.as_ref().borrow().deleted {
+                Some(at) => e.with_secondary(at, "deleted here"),
+                None => e,
+            }
+        }
+        Target::STACK(_, name, borrowed) => Diagnostic::error(&format!("use of a reference to `{}` after it went out of scope", name))
+            .with_label(span, "this reference dangles")
+            .with_secondary(borrowed, "borrowed here"),
+        Target::NULL(Some(at)) => Diagnostic::error("use of a null reference")
+            .with_label(span, "this reference is null")
+            .with_secondary(at, "set to null by this `delete`"),
+        _ => Diagnostic::error("use of a null reference")
+            .with_label(span, "this reference is null"),
+    };
+}
 
-                    let x = Rc::new(RefCell::new(Scope {
-                        parent: Some(scope),
-                        local: args,
-                    }));
-                    return run_block(false, Some(x), &fd.body);
+/// `*r`: the value `r` refers to
+fn deref(r: &Reference, span: &Span) -> RunResult<PrimitiveType> {
+    if let Target::STACK(x, name, _) = &r.target {
+        let x = x.as_ref().borrow();
+        return match &x.value {
+            Some(v) if x.live => Ok(v.clone()),
+            None if x.live => Err(Diagnostic::error(&format!("used binding `{}` isn't initialized", name))
+                .with_label(span, &format!("`{}` used here but it isn't initialized", name))),
+            _ => Err(dangling(r, span)),
+        };
+    }
+    return r.referent().ok_or_else(|| dangling(r, span));
+}
+
+/// `v`, or the value it refers to if it is a reference, so that the fields
+/// and methods of `new(Foo())` are those of a `Foo`
+fn dereferenced(v: PrimitiveType, span: &Span) -> RunResult<PrimitiveType> {
+    return match v {
+        PrimitiveType::REF(r) => deref(&r, span),
+        v => Ok(v),
+    };
+}
+
+/// Calls the function or built-in `func` with the arguments of `fc`, after the
+/// `receiver` of a method call
+fn call(scope: Rc<RefCell<Scope>>, func: PrimitiveType, receiver: Option<PrimitiveType>, fc: &FuncCall, span: &Span) -> RunResult<PrimitiveType> {
+    match func {
+        PrimitiveType::FUNCTION(closure) => {
+            let fd = closure.decl;
+            let offset = receiver.is_some() as usize;
+            if fc.arg_list.len() + offset != fd.arg_list.len() {
+                let expected = fd.arg_list.len() - offset;
+                return Err(Diagnostic::error(&format!("function `{}` takes {} but {}", fc.func_name.0, count(expected, "argument"), supplied(fc.arg_list.len())))
+                    .with_label(span, &format!("expected {}", count(expected, "argument"))));
+            }
+            let x = Rc::new(RefCell::new(Scope {
+                parent: Some(closure.env),
+                local: HashMap::new(),
+            }));
+            if let Some(v) = receiver {
+                let arg = &fd.arg_list[0].node;
+                bind(&x, &arg.pattern, Some(v), arg.mutable, arg.ty.as_ref())?;
+            }
+            for i in 0..fc.arg_list.len() {
+                let arg = &fd.arg_list[i + offset].node;
+                let mut val = get_value(scope.clone(), &fc.arg_list[i])?;
+                if arg.mutable {
+                    check_borrow(&scope, &val, &fc.arg_list[i])?;
                 }
-                PrimitiveType::BUILTIN(builtin) => {
-                    return (builtin.execute)(scope, fc);
+                val = passed(scope.clone(), val, &fc.arg_list[i])?;
+                if let Some(ty) = &arg.ty {
+                    val = check_type(&x, val, ty, &fc.arg_list[i])?;
                 }
-                _ => {
-                    fatal_(&format!("Error: {} is not a function or built-in function", fc.func_name.0))
+                bind(&x, &arg.pattern, Some(val), arg.mutable, arg.ty.as_ref())?;
+            }
+            let val = finished(run_block(false, Some(x.clone()), &fd.body)?)?;
+            x.as_ref().borrow().end();
+            return returned(val, &fd, fc, span);
+        }
+        PrimitiveType::BUILTIN(builtin) => {
+            return (builtin.execute)(scope, fc, span);
+        }
+        PrimitiveType::STRUCT(t) => {
+            let decl = t.as_ref().borrow().decl.clone();
+            let mut fields = vec![];
+            if fc.arg_list.is_empty() {
+                for field in decl.fields.iter() {
+                    fields.push(zero_value(scope.clone(), field, &mut vec![decl.name.0.clone()], span)?);
+                }
+            } else if fc.arg_list.len() == decl.fields.len() {
+                for (i, field) in decl.fields.iter().enumerate() {
+                    let val = get_value(scope.clone(), &fc.arg_list[i])?;
+                    let val = copied(scope.clone(), val, &fc.arg_list[i])?;
+                    fields.push(check_type(&scope, val, &field.node.ty, &fc.arg_list[i])?);
                 }
+            } else {
+                return Err(Diagnostic::error(&format!("struct `{}` has {} but {}", decl.name.0, count(decl.fields.len(), "field"), supplied(fc.arg_list.len())))
+                    .with_label(span, &format!("expected {}", count(decl.fields.len(), "argument")))
+                    .with_note(&format!("call `{}()` without arguments to zero all the fields", decl.name.0)));
             }
+            return Ok(PrimitiveType::OBJECT(Rc::new(RefCell::new(Object { ty: t, fields }))));
+        }
+        _ => {
+            Err(Diagnostic::error(&format!("`{}` is not a function or built-in function", fc.func_name.0))
+                .with_label(span, &format!("`{}` is {}", fc.func_name.0, func.type_name())))
         }
-        Value::INT(i) => PrimitiveType::I32(*i),
-        Value::BOOL(b) => PrimitiveType::I32(*b as i32),
     }
 }
 
-pub fn to_boolean(v: &PrimitiveType) -> bool {
-    *v != PrimitiveType::I32(0) && *v != PrimitiveType::F64(0.0) && *v != PrimitiveType::VOID
+/// `Enum::Variant(fields...)`, where a variant without payload takes no fields
+fn construct_variant(scope: Rc<RefCell<Scope>>, decl: Rc<EnumDecl>, name: &Identifier, arg_list: &[Expr], span: &Span) -> RunResult<PrimitiveType> {
+    let index = match decl.variant(name) {
+        Some(i) => i,
+        None => return Err(Diagnostic::error(&format!("no variant named `{}` in enum `{}`", name.0, decl.name.0))
+            .with_label(span, "variant not found")),
+    };
+    let variant = &decl.variants[index].node;
+    if arg_list.len() != variant.fields.len() {
+        return Err(Diagnostic::error(&format!("variant `{}::{}` has {} but {}", decl.name.0, name.0, count(variant.fields.len(), "field"), supplied(arg_list.len())))
+            .with_label(span, &format!("expected {}", count(variant.fields.len(), "argument"))));
+    }
+    let mut fields = vec![];
+    for (i, ty) in variant.fields.iter().enumerate() {
+        let val = get_value(scope.clone(), &arg_list[i])?;
+        fields.push(check_type(&scope, val, ty, &arg_list[i])?);
+    }
+    return Ok(PrimitiveType::VARIANT(Rc::new(Variant { ty: decl.clone(), index, fields })));
 }
 
-pub fn run_block(new_scope: bool, parent_scope: Option<Rc<RefCell<Scope>>>, blk: &Block) -> PrimitiveType {
-    let scope = if new_scope {
-        Rc::new(RefCell::new(Scope {
-            parent: parent_scope,
-            local: HashMap::new(),
-        }))
-    } else {
-        parent_scope.unwrap()
+/// Checks the value returned by a call of `fd` against its return type. The
+/// checker has made sure that a function declared to return, say, `u8` only
+/// returns `u8`s or unsuffixed literals, so other integers are converted.
+fn returned(v: PrimitiveType, fd: &FuncDecl, fc: &FuncCall, span: &Span) -> RunResult<PrimitiveType> {
+    if let PrimitiveType::REF(Reference { target: Target::STACK(x, name, borrowed), .. }) = &v {
+        if !x.as_ref().borrow().live {
+            return Err(Diagnostic::error(&format!("cannot return a reference to the local variable `{}`", name))
+                .with_label(borrowed, &format!("`{}` is local to `{}`", name, fc.func_name.0))
+                .with_secondary(span, "the reference would dangle after this call")
+                .with_note("allocate the value with `new` to return a reference to it"));
+        }
+    }
+    let ty = &fd.ret;
+    let v = match (&v, &ty.node) {
+        (PrimitiveType::INT(x, _), TypeExpr::INT(t)) if t.contains(*x) => PrimitiveType::INT(*x, *t),
+        (PrimitiveType::F64(x), TypeExpr::FLOAT(FloatType::F32)) => PrimitiveType::F32(*x as f32),
+        _ => v,
+    };
+    if !has_type(&v, &ty.node) {
+        return Err(Diagnostic::error(&format!("mismatched types: `{}` returned {} but is declared to return {}", fc.func_name.0, v.type_name(), ty.node))
+            .with_label(span, &format!("returned {}", v.type_name()))
+            .with_secondary(&ty.span, "expected due to this return type"));
+    }
+    return Ok(v);
+}
+
+/// The value of the place `expr` to bind or assign elsewhere: a copy made by
+/// `clone` for objects implementing `Copy`, which other objects cannot be,
+/// and a copy of the items of arrays
+fn copied(scope: Rc<RefCell<Scope>>, v: PrimitiveType, expr: &Expr) -> RunResult<PrimitiveType> {
+    let place = matches!(expr.node, Value::VAR(_) | Value::FIELD(_, _) | Value::INDEX(_, _) | Value::ELEMENT(_, _) | Value::DEREF(_));
+    if let (true, PrimitiveType::OBJECT(x)) = (place, &v) {
+        let t = x.as_ref().borrow().ty.clone();
+        let t = t.as_ref().borrow();
+        if !t.implements("Copy") {
+            // only variables can be borrowed
+            let note = match &expr.node {
+                Value::VAR(id) => format!("derive it with `#[derive(Copy)]`, or take a reference with `&{}`", id.0),
+                _ => "derive it with `#[derive(Copy)]`".to_string(),
+            };
+            return Err(Diagnostic::error(&format!("cannot copy `{}`, as `{}` does not implement `Copy`", expr.span.text(), t.decl.name.0))
+                .with_label(&expr.span, "copied here")
+                .with_note(&note));
+        }
+    }
+    if place {
+        return duplicate(scope, v, &expr.span);
+    }
+    return Ok(v);
+}
+
+/// The value of the argument `expr` of a call: a copy like `copied` for objects
+/// implementing `Copy`, and otherwise the same object or array, passed by
+/// reference
+fn passed(scope: Rc<RefCell<Scope>>, v: PrimitiveType, expr: &Expr) -> RunResult<PrimitiveType> {
+    if let Value::VAR(_) | Value::FIELD(_, _) = &expr.node {
+        return cloned(scope, v, &expr.span);
+    }
+    return Ok(v);
+}
+
+/// `v`, or a copy made by `clone` if it is an object implementing `Copy`
+fn cloned(scope: Rc<RefCell<Scope>>, v: PrimitiveType, span: &Span) -> RunResult<PrimitiveType> {
+    if let PrimitiveType::OBJECT(x) = &v {
+        let is_copy = x.as_ref().borrow().ty.as_ref().borrow().implements("Copy");
+        if is_copy {
+            if let Some(clone) = method(&v, &Identifier("clone".to_string())) {
+                let fc = FuncCall { func_name: Identifier("clone".to_string()), arg_list: vec![] };
+                return call(scope, PrimitiveType::FUNCTION(clone), Some(v), &fc, span);
+            }
+        }
+    }
+    return Ok(v);
+}
+
+/// `v` with the items of its arrays copied, so that assigning to them does
+/// not change `v`. The objects in them are copied by `cloned`.
+fn duplicate(scope: Rc<RefCell<Scope>>, v: PrimitiveType, span: &Span) -> RunResult<PrimitiveType> {
+    return match v {
+        PrimitiveType::ARRAY(x) => {
+            let items = x.items.as_ref().borrow().clone();
+            let mut copies = vec![];
+            for item in items {
+                copies.push(duplicate(scope.clone(), item, span)?);
+            }
+            Ok(PrimitiveType::ARRAY(Array { items: Rc::new(RefCell::new(copies)), sized: x.sized }))
+        }
+        PrimitiveType::TUPLE(elements) => {
+            let mut copies = vec![];
+            for x in elements {
+                copies.push(duplicate(scope.clone(), x, span)?);
+            }
+            Ok(PrimitiveType::TUPLE(copies))
+        }
+        v => cloned(scope, v, span),
+    };
+}
+
+/// The value of the field `field` of a struct constructed without arguments.
+/// `outer` are the structs being constructed, which cannot contain themselves.
+fn zero_value(scope: Rc<RefCell<Scope>>, field: &Spanned<FieldDecl>, outer: &mut Vec<String>, span: &Span) -> RunResult<PrimitiveType> {
+    let ty = &field.node.ty;
+    let v = match &ty.node {
+        TypeExpr::INT(t) => PrimitiveType::INT(0, *t),
+        TypeExpr::FLOAT(FloatType::F64) => PrimitiveType::F64(0.0),
+        TypeExpr::FLOAT(FloatType::F32) => PrimitiveType::F32(0.0),
+        TypeExpr::CHAR => PrimitiveType::CHAR('\0'),
+        TypeExpr::REF(x) if x.node == TypeExpr::STR => PrimitiveType::STR(String::new()),
+        TypeExpr::REF(x) if x.node == TypeExpr::CSTR => PrimitiveType::CSTR(String::new()),
+        TypeExpr::REF(_) | TypeExpr::POINTER(_) => PrimitiveType::REF(Reference::null()),
+        TypeExpr::NAMED(id, _) if !outer.contains(&id.0) => {
+            let t = match scope.as_ref().borrow().try_lookup(&id.0) {
+                Some(Variable { value: Some(PrimitiveType::STRUCT(t)), .. }) => t,
+                _ => return Err(unknown_type(id, &ty.span)),
+            };
+            let decl = t.as_ref().borrow().decl.clone();
+            outer.push(id.0.clone());
+            let mut fields = vec![];
+            for x in decl.fields.iter() {
+                fields.push(zero_value(scope.clone(), x, outer, span)?);
+            }
+            outer.pop();
+            PrimitiveType::OBJECT(Rc::new(RefCell::new(Object { ty: t, fields })))
+        }
+        _ => {
+            return Err(Diagnostic::error(&format!("field `{}` of type {} has no zero value", field.node.id.0, ty.node))
+                .with_label(span, "all the fields are zeroed here")
+                .with_secondary(&field.span, "field declared here")
+                .with_note("pass a value for every field to the constructor"));
+        }
+    };
+    return Ok(v);
+}
+
+/// The method `name` of an object, if it has one taking `self`
+fn method(v: &PrimitiveType, name: &Identifier) -> Option<Closure> {
+    if let PrimitiveType::OBJECT(x) = v {
+        let x = x.as_ref().borrow();
+        let ty = x.ty.as_ref().borrow();
+        if let Some(m) = ty.methods.get(&name.0) {
+            if m.decl.arg_list.first().is_some_and(|x| x.node.is_self()) {
+                return Some(m.clone());
+            }
+        }
+    }
+    return None;
+}
+
+fn field_value(v: &PrimitiveType, field: &Identifier, span: &Span) -> RunResult<PrimitiveType> {
+    if let PrimitiveType::OBJECT(x) = v {
+        let x = x.as_ref().borrow();
+        if let Some(i) = x.field_index(field) {
+            return Ok(x.fields[i].clone());
+        }
+        let ty = x.ty.as_ref().borrow();
+        if ty.methods.contains_key(&field.0) {
+            let e = Diagnostic::error(&format!("attempted to take value of method `{}` on type {}", field.0, ty.decl.name.0));
+            return Err(match method(v, field) {
+                Some(_) => e.with_label(span, "method, not a field")
+                    .with_note("use parentheses to call the method"),
+                None => e.with_label(span, "this function takes no `self`")
+                    .with_note(&format!("call it as `{}::{}(...)`", ty.decl.name.0, field.0)),
+            });
+        }
+    }
+    return Err(Diagnostic::error(&format!("no field `{}` on type {}", field.0, v.type_name()))
+        .with_label(span, "unknown field"));
+}
+
+/// The variable a place like `a.b[i].c` is in
+fn root_variable(place: &Expr) -> Option<&Identifier> {
+    return match &place.node {
+        Value::VAR(id) => Some(id),
+        Value::FIELD(x, _) | Value::INDEX(x, _) => root_variable(x),
+        _ => None,
+    };
+}
+
+/// The name of the immutable variable holding the place `target`, if any.
+/// Through a reference, it is the variable it borrows, while values on the
+/// heap are always mutable.
+fn immutable_root(scope: &Rc<RefCell<Scope>>, target: &Expr) -> Option<String> {
+    let id = root_variable(target)?;
+    let (name, var) = match scope.as_ref().borrow().try_lookup(&id.0) {
+        Some(Variable { value: Some(PrimitiveType::REF(r)), .. }) => match &r.target {
+            Target::STACK(x, name, _) => (name.clone(), Some(x.as_ref().borrow().clone())),
+            _ => return None,
+        },
+        var => (id.0.clone(), var),
+    };
+    return match var {
+        Some(Variable { mutable: false, .. }) => Some(name),
+        _ => None,
+    };
+}
+
+/// Reports assigning to a part of the value of an immutable variable, like
+/// the fields of an object or the items of an array
+fn check_mutable(scope: &Rc<RefCell<Scope>>, target: &Expr, span: &Span) -> RunResult<()> {
+    if let Some(name) = immutable_root(scope, target) {
+        return Err(Diagnostic::error(&format!("cannot assign to `{}`, as `{}` is not declared as mutable", target.span.text(), name))
+            .with_label(span, "cannot assign")
+            .with_note(&format!("consider making this binding mutable: `mut {}`", name)));
+    }
+    return Ok(());
+}
+
+/// Reports passing an object or array of an immutable variable by reference
+/// to the `mut` parameter or receiver of a function, which could change it
+fn check_borrow(scope: &Rc<RefCell<Scope>>, v: &PrimitiveType, expr: &Expr) -> RunResult<()> {
+    let shared = match v {
+        PrimitiveType::OBJECT(x) => !x.as_ref().borrow().ty.as_ref().borrow().implements("Copy"),
+        PrimitiveType::ARRAY(_) => true,
+        _ => false,
+    };
+    if shared {
+        if let Some(name) = immutable_root(scope, expr) {
+            return Err(Diagnostic::error(&format!("cannot borrow `{}` as mutable, as `{}` is not declared as mutable", expr.span.text(), name))
+                .with_label(&expr.span, "cannot borrow as mutable")
+                .with_note(&format!("consider making this binding mutable: `mut {}`", name)));
+        }
+    }
+    return Ok(());
+}
+
+/// Assigns `val`, the value of `expr`, to the variable `name` seen from `scope`
+fn assign_variable(scope: &Rc<RefCell<Scope>>, name: &str, val: PrimitiveType, expr: &Expr, span: &Span) -> RunResult<()> {
+    let slot = scope.as_ref().borrow().slot(name);
+    return match slot {
+        Some(x) => assign_slot(scope, &x, name, val, expr, span),
+        None => Err(Diagnostic::error(&format!("cannot assign to undeclared variable `{}`", name))
+            .with_label(span, "not declared in this scope")
+            .with_note(&format!("declare it first with `let {} = ...`", name))),
+    };
+}
+
+/// Assigns `val`, the value of `expr`, to the variable `x` named `name`, which
+/// must have the declared type of the variable
+fn assign_slot(scope: &Rc<RefCell<Scope>>, x: &Rc<RefCell<Variable>>, name: &str, val: PrimitiveType, expr: &Expr, span: &Span) -> RunResult<()> {
+    let ty = x.as_ref().borrow().ty.clone();
+    let val = match ty {
+        Some(ty) => check_type(scope, val, &ty, expr)?,
+        None => val,
+    };
+    return x.as_ref().borrow_mut().assign(name, val, span);
+}
+
+/// The place `target` with the values of its parts that are not variables,
+/// like the index of `a[g()]`, declared in `scope`, so that reading and then
+/// assigning it evaluates them once
+fn pin(scope: &Rc<RefCell<Scope>>, target: &Expr) -> RunResult<Expr> {
+    let value = match &target.node {
+        Value::VAR(_) => return Ok(target.clone()),
+        Value::INDEX(x, i) => Value::INDEX(Box::new(pin(scope, x)?), Box::new(pinned_value(scope, i)?)),
+        Value::FIELD(x, field) => Value::FIELD(Box::new(pin(scope, x)?), field.clone()),
+        Value::DEREF(x) => Value::DEREF(Box::new(pin(scope, x)?)),
+        _ => return pinned_value(scope, target),
+    };
+    return Ok(Spanned::new(value, target.span.clone()));
+}
+
+/// A variable of `scope` holding the value of `expr`, named so that no other
+/// variable can have its name
+fn pinned_value(scope: &Rc<RefCell<Scope>>, expr: &Expr) -> RunResult<Expr> {
+    let v = get_value(scope.clone(), expr)?;
+    let name = format!("#{}", scope.as_ref().borrow().local.len());
+    scope.as_ref().borrow_mut().declare(&name, Some(v), true);
+    return Ok(Spanned::new(Value::VAR(Identifier(name)), expr.span.clone()));
+}
+
+/// Assigns `val`, the value of `expr`, to the variable, field or item `target`
+fn assign(scope: Rc<RefCell<Scope>>, target: &Expr, val: PrimitiveType, expr: &Expr, span: &Span) -> RunResult<()> {
+    match &target.node {
+        Value::VAR(id) => assign_variable(&scope, &id.0, val, expr, span),
+        Value::INDEX(x, i) => {
+            check_mutable(&scope, target, span)?;
+            let v = dereferenced(get_value(scope.clone(), x)?, &x.span)?;
+            let index = get_value(scope, i)?;
+            let items = match v {
+                PrimitiveType::ARRAY(x) => x.items,
+                v => return Err(Diagnostic::error(&format!("cannot assign to an index of {}", v.type_name()))
+                    .with_label(&target.span, "only the items of arrays can be assigned")),
+            };
+            let len = items.as_ref().borrow().len();
+            let at = position(&index, len, &i.span)?;
+            let val = adapted(val, &items.as_ref().borrow()[at], expr)?;
+            items.as_ref().borrow_mut()[at] = val;
+            return Ok(());
+        }
+        Value::DEREF(x) => {
+            let r = match get_value(scope.clone(), x)? {
+                PrimitiveType::REF(r) => r,
+                v => return Err(Diagnostic::error(&format!("cannot dereference a value of type {}", v.type_name()))
+                    .with_label(&x.span, "not a reference")),
+            };
+            match &r.target {
+                Target::STACK(x, name, _) if x.as_ref().borrow().live => return assign_slot(&scope, x, name, val, expr, span),
+                Target::HEAP(a) if a.as_ref().borrow().value.is_some() => {
+                    let val = adapted(val, a.as_ref().borrow().value.as_ref().unwrap(), expr)?;
+                    a.as_ref().borrow_mut().value = Some(val);
+                    return Ok(());
+                }
+                _ => return Err(dangling(&r, &target.span)),
+            }
+        }
+        Value::FIELD(x, field) => {
+            check_mutable(&scope, target, span)?;
+            let obj = match dereferenced(get_value(scope.clone(), x)?, &x.span)? {
+                PrimitiveType::OBJECT(obj) => obj,
+                v => return field_value(&v, field, &target.span).map(|_| ()),
+            };
+            let i = match obj.as_ref().borrow().field_index(field) {
+                Some(i) => i,
+                None => return field_value(&PrimitiveType::OBJECT(obj.clone()), field, &target.span).map(|_| ()),
+            };
+            let ty = obj.as_ref().borrow().ty.as_ref().borrow().decl.fields[i].node.ty.clone();
+            let val = check_type(&scope, val, &ty, expr)?;
+            obj.as_ref().borrow_mut().fields[i] = val;
+            return Ok(());
+        }
+        _ => Err(Diagnostic::error("invalid left-hand side of assignment")
+            .with_label(&target.span, "cannot assign to this expression")),
+    }
+}
+
+/// Converts `v` to the primitive type `ty`. Integers are truncated and floats
+/// saturated to the range of the target type, like in Rust.
+fn cast(v: PrimitiveType, ty: &Type, span: &Span) -> RunResult<PrimitiveType> {
+    match &ty.node {
+        TypeExpr::INT(t) => {
+            let t = *t;
+            return match v {
+                PrimitiveType::INT(x, _) => Ok(PrimitiveType::INT(t.wrap(x), t)),
+                PrimitiveType::CHAR(x) => Ok(PrimitiveType::INT(t.wrap(x as i128), t)),
+                PrimitiveType::F64(x) => Ok(PrimitiveType::INT((x as i128).clamp(t.min(), t.max()), t)),
+                PrimitiveType::F32(x) => Ok(PrimitiveType::INT((x as i128).clamp(t.min(), t.max()), t)),
+                _ => Err(invalid_cast(&v, ty, span)),
+            };
+        }
+        TypeExpr::FLOAT(t) => {
+            let x = match v {
+                PrimitiveType::INT(x, _) => x as f64,
+                PrimitiveType::F64(x) => x,
+                PrimitiveType::F32(x) => x as f64,
+                _ => return Err(invalid_cast(&v, ty, span)),
+            };
+            return match t {
+                FloatType::F32 => Ok(PrimitiveType::F32(x as f32)),
+                FloatType::F64 => Ok(PrimitiveType::F64(x)),
+            };
+        }
+        TypeExpr::CHAR => {
+            return match v {
+                PrimitiveType::CHAR(_) => Ok(v),
+                PrimitiveType::INT(x, _) => match u32::try_from(x).ok().and_then(char::from_u32) {
+                    Some(c) => Ok(PrimitiveType::CHAR(c)),
+                    None => Err(Diagnostic::error(&format!("{} is not a valid character", x))
+                        .with_label(span, "invalid cast")),
+                },
+                _ => Err(invalid_cast(&v, ty, span)),
+            };
+        }
+        TypeExpr::NAMED(id, _) => Err(unknown_type(id, &ty.span)),
+        // `&x as *T` and `p as ref T` change only the kind of reference
+        TypeExpr::REF(_) | TypeExpr::POINTER(_) if matches!(v, PrimitiveType::REF(_)) => {
+            let target = match &v {
+                PrimitiveType::REF(r) => r.target.clone(),
+                _ => unreachable!(),
+            };
+            let pointer = matches!(ty.node, TypeExpr::POINTER(_));
+            let cast = PrimitiveType::REF(Reference { target, pointer });
+            if has_type(&cast, &ty.node) {
+                return Ok(cast);
+            }
+            return Err(invalid_cast(&v, ty, span));
+        }
+        _ => {
+            if has_type(&v, &ty.node) {
+                return Ok(v);
+            }
+            return Err(invalid_cast(&v, ty, span));
+        }
+    }
+}
+
+fn invalid_cast(v: &PrimitiveType, ty: &Type, span: &Span) -> Diagnostic {
+    return Diagnostic::error(&format!("cannot cast {} as {}", v.type_name(), ty.node))
+        .with_label(span, "invalid cast");
+}
+
+fn unknown_type(id: &Identifier, span: &Span) -> Diagnostic {
+    return Diagnostic::error(&format!("cannot find type `{}` in this scope", id.0))
+        .with_label(span, "not found in this scope");
+}
+
+/// Whether `v` is a value of type `ty`
+fn has_type(v: &PrimitiveType, ty: &TypeExpr) -> bool {
+    return match (v, ty) {
+        (PrimitiveType::INT(_, t), TypeExpr::INT(t2)) => t == t2,
+        (PrimitiveType::F64(_), TypeExpr::FLOAT(FloatType::F64)) => true,
+        (PrimitiveType::F32(_), TypeExpr::FLOAT(FloatType::F32)) => true,
+        (PrimitiveType::CHAR(_), TypeExpr::CHAR) => true,
+        (PrimitiveType::STR(_), TypeExpr::REF(x)) => x.node == TypeExpr::STR,
+        (PrimitiveType::CSTR(_), TypeExpr::REF(x)) => x.node == TypeExpr::CSTR,
+        (PrimitiveType::FUNCTION(x), TypeExpr::FUNCTION(args, _)) => x.decl.arg_list.len() == args.len(),
+        (PrimitiveType::BUILTIN(_), TypeExpr::FUNCTION(_, _)) => true,
+        (PrimitiveType::OBJECT(x), TypeExpr::NAMED(id, _)) => {
+            let ty = x.as_ref().borrow().ty.clone();
+            let ty = ty.as_ref().borrow();
+            ty.decl.name == *id || ty.implements(&id.0)
+        }
+        // objects are always passed by reference
+        (PrimitiveType::OBJECT(_), TypeExpr::REF(x)) => has_type(v, &x.node),
+        (PrimitiveType::VARIANT(x), TypeExpr::NAMED(id, _)) => x.ty.name == *id,
+        // a null or deleted reference has any type
+        (PrimitiveType::REF(r), TypeExpr::REF(x)) if !r.pointer => r.referent().is_none_or(|v| has_type(&v, &x.node)),
+        (PrimitiveType::REF(r), TypeExpr::POINTER(x)) if r.pointer => r.referent().is_none_or(|v| has_type(&v, &x.node)),
+        (PrimitiveType::TUPLE(x), TypeExpr::TUPLE(types)) => {
+            x.len() == types.len() && x.iter().zip(types.iter()).all(|(x, t)| has_type(x, &t.node))
+        }
+        (PrimitiveType::ARRAY(x), TypeExpr::ARRAY(item, len)) => x.sized && has_items(x, item, len),
+        (PrimitiveType::ARRAY(x), TypeExpr::C_ARRAY(item, len)) => has_items(x, item, len),
+        (VOID, TypeExpr::VOID) => true,
+        (_, TypeExpr::INFER) => true,
+        _ => false,
+    };
+}
+
+/// Whether the items of the array `x` are of type `item`, and there are `len` of them
+fn has_items(x: &Array, item: &Type, len: &ArrayLen) -> bool {
+    let items = x.items.as_ref().borrow();
+    if let ArrayLen::FIXED(n) = len {
+        if items.len() as u64 != *n {
+            return false;
+        }
+    }
+    return items.iter().all(|x| has_type(x, &item.node));
+}
+
+/// Checks that `v`, the value of `expr`, has the declared type `ty`. Unsuffixed
+/// literals take the declared type if they fit into it, so that
+/// `let b: byte = 0x6C;` works.
+fn check_type(scope: &Rc<RefCell<Scope>>, v: PrimitiveType, ty: &Type, expr: &Expr) -> RunResult<PrimitiveType> {
+    let v = match (&v, literal(expr), &ty.node) {
+        (PrimitiveType::INT(x, _), Value::INT(_, None), TypeExpr::INT(t)) => {
+            if !t.contains(*x) {
+                return Err(Diagnostic::error(&format!("literal out of range for {}", t))
+                    .with_label(&expr.span, &format!("the range of {} is {}..={}", t, t.min(), t.max())));
+            }
+            PrimitiveType::INT(*x, *t)
+        }
+        (PrimitiveType::F64(x), Value::FLOAT(_, None), TypeExpr::FLOAT(FloatType::F32)) => PrimitiveType::F32(*x as f32),
+        // the items of an array literal take the declared type like other literals
+        (PrimitiveType::ARRAY(x), Value::ARRAY(_) | Value::REPEAT(_, _), TypeExpr::ARRAY(item, _) | TypeExpr::C_ARRAY(item, _)) => {
+            let mut items = x.items.as_ref().borrow_mut();
+            for i in 0..items.len() {
+                let expr = match &expr.node {
+                    Value::ARRAY(exprs) => &exprs[i],
+                    Value::REPEAT(x, _) => x,
+                    _ => unreachable!(),
+                };
+                items[i] = check_type(scope, items[i].clone(), item, expr)?;
+            }
+            v.clone()
+        }
+        (PrimitiveType::TUPLE(x), Value::TUPLE(exprs), TypeExpr::TUPLE(types)) if x.len() == types.len() => {
+            let mut elements = vec![];
+            for i in 0..x.len() {
+                elements.push(check_type(scope, x[i].clone(), &types[i], &exprs[i])?);
+            }
+            PrimitiveType::TUPLE(elements)
+        }
+        (_, _, TypeExpr::NAMED(id, _)) => match scope.as_ref().borrow().try_lookup(&id.0) {
+            Some(Variable { value: Some(PrimitiveType::STRUCT(_)), .. }) => v,
+            Some(Variable { value: Some(PrimitiveType::INTERFACE(_)), .. }) => v,
+            Some(Variable { value: Some(PrimitiveType::ENUM(_)), .. }) => v,
+            _ => return Err(unknown_type(id, &ty.span)),
+        },
+        _ => v,
+    };
+    if !has_type(&v, &ty.node) {
+        return Err(Diagnostic::error(&format!("mismatched types: expected {}, found {}", ty.node, v.type_name()))
+            .with_label(&expr.span, &format!("expected {}", ty.node))
+            .with_secondary(&ty.span, "expected due to this type"));
+    }
+    if let PrimitiveType::ARRAY(x) = &v {
+        return array_of_type(scope, x, &ty.node, expr);
+    }
+    return Ok(v);
+}
+
+/// The number literal negated by `expr` if it is one like `-1`, which takes
+/// the declared type like the literal itself, or else `expr`
+fn literal(expr: &Expr) -> &Value {
+    if let Value::FUNC_CALL(fc) = &expr.node {
+        if let ("-", [x]) = (fc.func_name.0.as_str(), fc.arg_list.as_slice()) {
+            if let Value::INT(_, None) | Value::FLOAT(_, None) = x.node {
+                return &x.node;
+            }
+        }
+    }
+    return &expr.node;
+}
+
+/// `v`, the value of `expr`, to store in place of `old`, which has the type
+/// of the array item or allocated value it replaces. Number literals take
+/// that type.
+fn adapted(v: PrimitiveType, old: &PrimitiveType, expr: &Expr) -> RunResult<PrimitiveType> {
+    return match (&v, literal(expr), old) {
+        (PrimitiveType::INT(x, _), Value::INT(_, None), PrimitiveType::INT(_, t)) => {
+            if !t.contains(*x) {
+                return Err(Diagnostic::error(&format!("literal out of range for {}", t))
+                    .with_label(&expr.span, &format!("the range of {} is {}..={}", t, t.min(), t.max())));
+            }
+            Ok(PrimitiveType::INT(*x, *t))
+        }
+        (PrimitiveType::F64(x), Value::FLOAT(_, None), PrimitiveType::F32(_)) => Ok(PrimitiveType::F32(*x as f32)),
+        _ => Ok(v),
+    };
+}
+
+/// Views the array `x` as one of the array type `ty`, which has been checked.
+/// `[T; n]` and `T[n]` bind `n` to the length; a C-like array loses its
+/// length, unless its type fixes it.
+fn array_of_type(scope: &Rc<RefCell<Scope>>, x: &Array, ty: &TypeExpr, expr: &Expr) -> RunResult<PrimitiveType> {
+    let (len, sized) = match ty {
+        TypeExpr::ARRAY(_, len) => (len, true),
+        TypeExpr::C_ARRAY(_, len) => (len, matches!(len, ArrayLen::FIXED(_)) && x.sized),
+        _ => return Ok(PrimitiveType::ARRAY(x.clone())),
     };
+    if let ArrayLen::BIND(n) = len {
+        if !x.sized {
+            return Err(Diagnostic::error(&format!("cannot bind the length of a C-like array to `{}`", n.0))
+                .with_label(&expr.span, "the length of this array is unknown"));
+        }
+        let len = x.items.as_ref().borrow().len();
+        scope.as_ref().borrow_mut().declare(&n.0, Some(PrimitiveType::INT(len as i128, IntType::U64)), false);
+    }
+    return Ok(PrimitiveType::ARRAY(Array { items: x.items.clone(), sized }));
+}
+
+/// Converts `index` to a position among `len` items, checking that it is in bounds
+fn position(index: &PrimitiveType, len: usize, index_span: &Span) -> RunResult<usize> {
+    let i = match index {
+        PrimitiveType::INT(i, _) => *i,
+        _ => return Err(Diagnostic::error(&format!("cannot index with {}", index.type_name()))
+            .with_label(index_span, "expected an integer")),
+    };
+    if i < 0 || i >= len as i128 {
+        return Err(Diagnostic::error(&format!("index out of bounds: the length is {} but the index is {}", len, i))
+            .with_label(index_span, "out of bounds"));
+    }
+    return Ok(i as usize);
+}
+
+/// `v[index]`: an item of an array, or the character at a position of a string
+fn index_value(v: &PrimitiveType, index: &PrimitiveType, span: &Span, index_span: &Span) -> RunResult<PrimitiveType> {
+    return match v {
+        PrimitiveType::ARRAY(x) => {
+            let items = x.items.as_ref().borrow();
+            Ok(items[position(index, items.len(), index_span)?].clone())
+        }
+        PrimitiveType::STR(s) | PrimitiveType::CSTR(s) => {
+            let i = position(index, s.chars().count(), index_span)?;
+            Ok(PrimitiveType::CHAR(s.chars().nth(i).unwrap()))
+        }
+        _ => Err(Diagnostic::error(&format!("cannot index into a value of type {}", v.type_name()))
+            .with_label(span, "not indexable")),
+    };
+}
+
+fn slice_bound(scope: Rc<RefCell<Scope>>, x: &Expr) -> RunResult<i128> {
+    return match get_value(scope, x)? {
+        PrimitiveType::INT(i, _) => Ok(i),
+        v => Err(Diagnostic::error(&format!("the bounds of a slice must be integers, found {}", v.type_name()))
+            .with_label(&x.span, "expected an integer")),
+    };
+}
 
+/// `v[from..to]`: a copy of part of an array, or part of a string. Slicing a
+/// C-like array up to its end is an error, as its length is unknown.
+fn slice_value(scope: Rc<RefCell<Scope>>, v: &PrimitiveType, from: &Option<Box<Expr>>, to: &Option<Box<Expr>>, span: &Span) -> RunResult<PrimitiveType> {
+    let (len, sized) = match v {
+        PrimitiveType::ARRAY(x) => (x.items.as_ref().borrow().len(), x.sized),
+        PrimitiveType::STR(s) | PrimitiveType::CSTR(s) => (s.chars().count(), true),
+        _ => return Err(Diagnostic::error(&format!("cannot slice a value of type {}", v.type_name()))
+            .with_label(span, "not sliceable")),
+    };
+    let start = match from {
+        Some(x) => slice_bound(scope.clone(), x)?,
+        None => 0,
+    };
+    let end = match to {
+        Some(x) => slice_bound(scope, x)?,
+        None if sized => len as i128,
+        None => return Err(Diagnostic::error("cannot slice a C-like array up to its end")
+            .with_label(span, "the length of this array is unknown")
+            .with_note("give the end of the slice, as in `a[i..n]`")),
+    };
+    if start < 0 || start > end {
+        return Err(Diagnostic::error(&format!("slice index starts at {} but ends at {}", start, end))
+            .with_label(span, "invalid range"));
+    }
+    if end > len as i128 {
+        return Err(Diagnostic::error(&format!("range end index {} out of range for a length of {}", end, len))
+            .with_label(span, "out of bounds"));
+    }
+    let (start, end) = (start as usize, end as usize);
+    return Ok(match v {
+        PrimitiveType::ARRAY(x) => {
+            let items = x.items.as_ref().borrow()[start..end].to_vec();
+            PrimitiveType::ARRAY(Array { items: Rc::new(RefCell::new(items)), sized: true })
+        }
+        PrimitiveType::CSTR(s) => PrimitiveType::CSTR(s.chars().skip(start).take(end - start).collect()),
+        PrimitiveType::STR(s) => PrimitiveType::STR(s.chars().skip(start).take(end - start).collect()),
+        _ => unreachable!(),
+    });
+}
+
+pub fn to_boolean(v: &PrimitiveType) -> bool {
+    match v {
+        PrimitiveType::INT(x, _) => *x != 0,
+        PrimitiveType::F64(x) => *x != 0.0,
+        PrimitiveType::F32(x) => *x != 0.0,
+        PrimitiveType::VOID => false,
+        _ => true,
+    }
+}
+
+pub fn run_block(new_scope: bool, parent_scope: Option<Rc<RefCell<Scope>>>, blk: &Block) -> RunResult<Completion> {
+    if !new_scope {
+        return run_statements(parent_scope.unwrap(), blk);
+    }
+    let scope = Rc::new(RefCell::new(Scope {
+        parent: parent_scope,
+        local: HashMap::new(),
+    }));
+    let c = run_statements(scope.clone(), blk)?;
+    scope.as_ref().borrow().end();
+    return Ok(c);
+}
+
+/// Runs the statements of `blk` in `scope`
+fn run_statements(scope: Rc<RefCell<Scope>>, blk: &Block) -> RunResult<Completion> {
+    // functions and structs can be used before their declaration in the same block
     for statement in blk.0.iter() {
-        match statement {
-            Statement::ASSIGNMENT(ass) => {
-                let val = get_value(scope.clone(), &ass.val);
-                scope.as_ref().borrow_mut().local.insert(ass.id.0.clone(), val);
+        match &statement.node {
+            Statement::FUNC_DECL(x) => {
+                let closure = Closure { decl: x.clone(), env: scope.clone() };
+                scope.as_ref().borrow_mut().declare(&x.func_name.0, Some(PrimitiveType::FUNCTION(closure)), false);
+            }
+            Statement::STRUCT(x) => {
+                let t = StructType { decl: x.clone(), methods: HashMap::new(), interfaces: vec![] };
+                scope.as_ref().borrow_mut().declare(&x.name.0, Some(PrimitiveType::STRUCT(Rc::new(RefCell::new(t)))), false);
+            }
+            Statement::INTERFACE(x) => {
+                scope.as_ref().borrow_mut().declare(&x.name.0, Some(PrimitiveType::INTERFACE(x.clone())), false);
             }
+            Statement::ENUM(x) => {
+                scope.as_ref().borrow_mut().declare(&x.name.0, Some(PrimitiveType::ENUM(x.clone())), false);
+            }
+            _ => {}
+        }
+    }
+    for statement in blk.0.iter() {
+        if let Statement::IMPL(x) = &statement.node {
+            implement(scope.clone(), x)?;
+        }
+    }
+    let mut opened = vec![];
+    let c = run_sequence(scope, blk, &mut opened);
+    for x in opened.iter() {
+        x.as_ref().borrow().end();
+    }
+    return c;
+}
+
+/// Whether `pattern` declares a variable again in `scope`
+fn shadows(scope: &Scope, pattern: &Spanned<Pattern>) -> bool {
+    return match &pattern.node {
+        Pattern::BIND(id) => scope.local.contains_key(&id.0),
+        Pattern::TUPLE(elements) | Pattern::VARIANT(_, _, elements) => elements.iter().any(|x| shadows(scope, x)),
+        Pattern::WILDCARD | Pattern::LITERAL(_) => false,
+    };
+}
+
+/// Runs the statements of `blk` one after another. A `let` declaring a variable
+/// again opens a scope for the rest of the block, added to `opened`, so that
+/// closures keep seeing the variable they captured.
+fn run_sequence(mut scope: Rc<RefCell<Scope>>, blk: &Block, opened: &mut Vec<Rc<RefCell<Scope>>>) -> RunResult<Completion> {
+    for statement in blk.0.iter() {
+        match &statement.node {
             Statement::RETURN(Return(value)) => {
-                return get_value(scope.clone(), value);
+                let value = match value {
+                    Some(value) => get_value(scope.clone(), value)?,
+                    None => VOID,
+                };
+                return Ok(Completion::RETURN(value));
             }
             Statement::EXPRESSION(exp) => {
-                get_value(scope.clone(), exp);
+                get_value(scope.clone(), exp)?;
+            }
+            Statement::LET(x) => {
+                let val = match &x.init {
+                    Some(init) => {
+                        let val = get_value(scope.clone(), init)?;
+                        let val = copied(scope.clone(), val, init)?;
+                        Some(match &x.ty {
+                            Some(ty) => check_type(&scope, val, ty, init)?,
+                            None => val,
+                        })
+                    }
+                    None => None,
+                };
+                if shadows(&scope.as_ref().borrow(), &x.pattern) {
+                    scope = Rc::new(RefCell::new(Scope {
+                        parent: Some(scope),
+                        local: HashMap::new(),
+                    }));
+                    opened.push(scope.clone());
+                }
+                bind(&scope, &x.pattern, val, x.mutable, x.ty.as_ref())?;
             }
-            Statement::FUNC_DECL(fun) => {
-                scope.as_ref().borrow_mut().local.insert(fun.func_name.0.clone(), PrimitiveType::FUNCTION(fun.clone()));
+            // from here on, the function sees the variables declared again before it
+            Statement::FUNC_DECL(x) if !opened.is_empty() => {
+                let closure = Closure { decl: x.clone(), env: scope.clone() };
+                scope.as_ref().borrow_mut().declare(&x.func_name.0, Some(PrimitiveType::FUNCTION(closure)), false);
             }
+            Statement::FUNC_DECL(_) | Statement::STRUCT(_) | Statement::INTERFACE(_) | Statement::ENUM(_) | Statement::IMPL(_) => { /*declared above*/ }
             Statement::IF(x) => {
                 for i in 0..x.cond.len() {
-                    let v = get_value(scope.clone(), &x.cond[i]);
+                    let v = get_value(scope.clone(), &x.cond[i])?;
                     if to_boolean(&v) {
-                        run_block(false, Some(scope.clone()), &x.then[i]);
+                        match run_block(true, Some(scope.clone()), &x.then[i])? {
+                            Completion::NORMAL(_) => {}
+                            c => return Ok(c),
+                        }
                         break;
                     }
                 }
             }
             Statement::WHILE(x) => {
-                while to_boolean(&get_value(scope.clone(), &x.cond)) {
-                    run_block(false, Some(scope.clone()), &x.then);
+                match run_while(scope.clone(), x)? {
+                    Completion::NORMAL(_) => {}
+                    c => return Ok(c),
+                }
+            }
+            Statement::FOR(x) => {
+                match run_for(scope.clone(), x)? {
+                    Completion::NORMAL(_) => {}
+                    c => return Ok(c),
+                }
+            }
+            Statement::LOOP(x) => {
+                match run_loop(scope.clone(), x)? {
+                    Completion::NORMAL(_) => {}
+                    c => return Ok(c),
+                }
+            }
+            Statement::DO_UNTIL(x) => {
+                match run_do_until(scope.clone(), x)? {
+                    Completion::NORMAL(_) => {}
+                    c => return Ok(c),
+                }
+            }
+            Statement::MATCH(x) => {
+                match run_match(scope.clone(), x)? {
+                    Completion::NORMAL(_) => {}
+                    c => return Ok(c),
                 }
             }
+            Statement::BREAK(x) => {
+                let value = match &x.value {
+                    Some(value) => get_value(scope.clone(), value)?,
+                    None => VOID,
+                };
+                let label = x.label.as_ref().map(|l| l.node.clone());
+                return Ok(Completion::BREAK(label, value, statement.span.clone()));
+            }
+            Statement::CONTINUE(label) => {
+                let label = label.as_ref().map(|l| l.node.clone());
+                return Ok(Completion::CONTINUE(label, statement.span.clone()));
+            }
             Statement::NOTHING => { /*nothing*/ }
+            Statement::ERROR => {
+                return Err(Diagnostic::error("cannot execute a statement that failed to parse")
+                    .with_label(&statement.span, "syntax error here"));
+            }
         }
     }
-    return VOID;
+    return Ok(Completion::NORMAL(VOID));
 }
 
-fn add_builtin(scope: Rc<RefCell<Scope>>, func_call: &FuncCall) -> PrimitiveType {
-    let x = &func_call.arg_list[0];
-    let y = &func_call.arg_list[1];
-    let o1 = get_value(scope.clone(), x);
-    let o2 = get_value(scope.clone(), y);
-    if let PrimitiveType::F64(f1) = o1 {
-        if let PrimitiveType::F64(f2) = o2 {
-            return PrimitiveType::F64(f1 + f2);
+/// Adds the methods of an `impl` block to its struct. Implementing an interface
+/// also adds the provided methods the block does not define.
+fn implement(scope: Rc<RefCell<Scope>>, x: &Impl) -> RunResult<()> {
+    let t = match scope.as_ref().borrow().lookup(&x.target.node.0, &x.target.span)? {
+        PrimitiveType::STRUCT(t) => t,
+        v => return Err(Diagnostic::error(&format!("`{}` is not a struct", x.target.node.0))
+            .with_label(&x.target.span, &format!("`{}` is {}", x.target.node.0, v.type_name()))),
+    };
+    let mut t = t.as_ref().borrow_mut();
+    for m in x.methods.iter() {
+        let closure = Closure { decl: m.node.clone(), env: scope.clone() };
+        t.methods.insert(m.node.func_name.0.clone(), closure);
+    }
+    if let Some(interface) = &x.interface {
+        let decl = match scope.as_ref().borrow().lookup(&interface.node.0, &interface.span)? {
+            PrimitiveType::INTERFACE(decl) => decl,
+            v => return Err(Diagnostic::error(&format!("`{}` is not an interface", interface.node.0))
+                .with_label(&interface.span, &format!("`{}` is {}", interface.node.0, v.type_name()))),
+        };
+        for m in decl.provided.iter() {
+            if !x.methods.iter().any(|x| x.node.func_name == m.node.func_name) {
+                let closure = Closure { decl: m.node.clone(), env: scope.clone() };
+                t.methods.insert(m.node.func_name.0.clone(), closure);
+            }
         }
+        t.interfaces.push(decl.name.0.clone());
     }
-    if let PrimitiveType::I32(f1) = o1 {
-        if let PrimitiveType::I32(f2) = o2 {
-            return PrimitiveType::I32(f1 + f2);
+    return Ok(());
+}
+
+/// The value of a function body or `loop` expression, which no `break` or
+/// `continue` may leave
+fn finished(c: Completion) -> RunResult<PrimitiveType> {
+    return match c {
+        Completion::NORMAL(v) | Completion::RETURN(v) => Ok(v),
+        Completion::BREAK(_, _, span) => Err(Diagnostic::error("`break` outside of a loop")
+            .with_label(&span, "cannot break out of this function or `loop` value")),
+        Completion::CONTINUE(_, span) => Err(Diagnostic::error("`continue` outside of a loop")
+            .with_label(&span, "cannot continue out of this function or `loop` value")),
+    };
+}
+
+/// Decides what a loop labeled `label` does once its body completed with `c`:
+/// `None` runs the next iteration, `Some(NORMAL(value))` leaves the loop with
+/// `value`, and anything else also leaves the enclosing blocks
+fn after_iteration(label: &Option<Spanned<Identifier>>, c: Completion) -> Option<Completion> {
+    let targets = |target: &Option<Identifier>| match (target, label) {
+        (None, _) => true,
+        (Some(target), Some(label)) => *target == label.node,
+        (Some(_), None) => false,
+    };
+    return match c {
+        Completion::NORMAL(_) => None,
+        Completion::CONTINUE(target, _) if targets(&target) => None,
+        Completion::BREAK(target, v, _) if targets(&target) => Some(Completion::NORMAL(v)),
+        c => Some(c),
+    };
+}
+
+fn run_while(scope: Rc<RefCell<Scope>>, x: &While) -> RunResult<Completion> {
+    while to_boolean(&get_value(scope.clone(), &x.cond)?) {
+        if let Some(c) = after_iteration(&x.label, run_block(true, Some(scope.clone()), &x.then)?) {
+            return Ok(c);
         }
     }
+    return Ok(Completion::NORMAL(VOID));
+}
 
-    fatal_(&format!("Unknown error"))
+fn run_loop(scope: Rc<RefCell<Scope>>, x: &Loop) -> RunResult<Completion> {
+    loop {
+        if let Some(c) = after_iteration(&x.label, run_block(true, Some(scope.clone()), &x.then)?) {
+            return Ok(c);
+        }
+    }
 }
 
-fn sub_builtin(scope: Rc<RefCell<Scope>>, func_call: &FuncCall) -> PrimitiveType {
-    let x = &func_call.arg_list[0];
-    let y = &func_call.arg_list[1];
-    let o1 = get_value(scope.clone(), x);
-    let o2 = get_value(scope, y);
-    if let PrimitiveType::F64(f1) = o1 {
-        if let PrimitiveType::F64(f2) = o2 {
-            return PrimitiveType::F64(f1 - f2);
+fn run_do_until(scope: Rc<RefCell<Scope>>, x: &DoUntil) -> RunResult<Completion> {
+    loop {
+        if let Some(c) = after_iteration(&x.label, run_block(true, Some(scope.clone()), &x.then)?) {
+            return Ok(c);
+        }
+        if to_boolean(&get_value(scope.clone(), &x.cond)?) {
+            return Ok(Completion::NORMAL(VOID));
         }
     }
-    if let PrimitiveType::I32(f1) = o1 {
-        if let PrimitiveType::I32(f2) = o2 {
-            return PrimitiveType::I32(f1 - f2);
+}
+
+/// Runs the body of the loop `x` once, in a new scope binding its variable to `value`
+fn run_iteration(scope: Rc<RefCell<Scope>>, x: &For, value: PrimitiveType) -> RunResult<Completion> {
+    let inner = Rc::new(RefCell::new(Scope {
+        parent: Some(scope),
+        local: HashMap::new(),
+    }));
+    bind(&inner, &x.var, Some(value), x.mutable, None)?;
+    let c = run_block(false, Some(inner.clone()), &x.then)?;
+    inner.as_ref().borrow().end();
+    return Ok(c);
+}
+
+/// Evaluates a bound of a counted loop, which must be an integer
+fn loop_bound(scope: Rc<RefCell<Scope>>, x: &Expr, what: &str) -> RunResult<(i128, IntType)> {
+    match get_value(scope, x)? {
+        PrimitiveType::INT(v, t) => Ok((v, t)),
+        v => Err(Diagnostic::error(&format!("the {} of a `for` loop must be an integer, found {}", what, v.type_name()))
+            .with_label(&x.span, "expected an integer")),
+    }
+}
+
+fn run_for(scope: Rc<RefCell<Scope>>, x: &For) -> RunResult<Completion> {
+    match &x.iter {
+        Iteration::COUNTED(c) => {
+            let (from, t1) = loop_bound(scope.clone(), &c.from, "start")?;
+            let (to, t2) = loop_bound(scope.clone(), &c.to, "end")?;
+            let step = match &c.step {
+                Some(step) => {
+                    let (v, _) = loop_bound(scope.clone(), step, "step")?;
+                    if v <= 0 {
+                        return Err(Diagnostic::error("the step of a `for` loop must be positive")
+                            .with_label(&step.span, &format!("this is {}", v))
+                            .with_note("count down with `downto` instead of a negative step"));
+                    }
+                    v
+                }
+                None => 1,
+            };
+            let t = IntType::promote(t1, t2);
+            let mut i = from;
+            while (!c.downto && i <= to) || (c.downto && i >= to) {
+                if let Some(c) = after_iteration(&x.label, run_iteration(scope.clone(), x, PrimitiveType::INT(i, t))?) {
+                    return Ok(c);
+                }
+                i = if c.downto { i - step } else { i + step };
+            }
+        }
+        Iteration::IN(iterable) => {
+            match dereferenced(get_value(scope.clone(), iterable)?, &iterable.span)? {
+                PrimitiveType::ARRAY(a) if a.sized => {
+                    let items = a.items.as_ref().borrow().clone();
+                    for item in items {
+                        // the items of the array are not changed through the variable
+                        if x.mutable {
+                            check_borrow(&scope, &item, iterable)?;
+                        }
+                        let item = duplicate(scope.clone(), item, &iterable.span)?;
+                        if let Some(c) = after_iteration(&x.label, run_iteration(scope.clone(), x, item)?) {
+                            return Ok(c);
+                        }
+                    }
+                }
+                PrimitiveType::ARRAY(_) => {
+                    return Err(Diagnostic::error("cannot iterate over a C-like array")
+                        .with_label(&iterable.span, "the length of this array is unknown")
+                        .with_note("count over its indexes instead, as in `for i = 0 to n - 1`"));
+                }
+                PrimitiveType::STR(s) | PrimitiveType::CSTR(s) => {
+                    for ch in s.chars() {
+                        if let Some(c) = after_iteration(&x.label, run_iteration(scope.clone(), x, PrimitiveType::CHAR(ch))?) {
+                            return Ok(c);
+                        }
+                    }
+                }
+                v => {
+                    return Err(Diagnostic::error(&format!("cannot iterate over a value of type {}", v.type_name()))
+                        .with_label(&iterable.span, "not iterable"));
+                }
+            }
         }
     }
-    fatal_(&format!("Unknown error"))
+    return Ok(Completion::NORMAL(VOID));
 }
 
-fn multi_builtin(scope: Rc<RefCell<Scope>>, func_call: &FuncCall) -> PrimitiveType {
-    let x = &func_call.arg_list[0];
-    let y = &func_call.arg_list[1];
-    let o1 = get_value(scope.clone(), x);
-    let o2 = get_value(scope, y);
-    if let PrimitiveType::F64(f1) = o1 {
-        if let PrimitiveType::F64(f2) = o2 {
-            return PrimitiveType::F64(f1 * f2);
+/// Declares the names of `pattern` with the declared type `ty` in `scope`,
+/// destructuring the tuple `val`. Without a value, as in `let (a, b);`, the
+/// names are declared unassigned.
+fn bind(scope: &Rc<RefCell<Scope>>, pattern: &Spanned<Pattern>, val: Option<PrimitiveType>, mutable: bool, ty: Option<&Type>) -> RunResult<()> {
+    match &pattern.node {
+        Pattern::BIND(id) => scope.as_ref().borrow_mut().declare_typed(&id.0, val, mutable, ty.cloned()),
+        Pattern::TUPLE(patterns) => {
+            let elements = match val {
+                Some(PrimitiveType::TUPLE(x)) if x.len() == patterns.len() => x.into_iter().map(Some).collect(),
+                None => vec![None; patterns.len()],
+                Some(v) => return Err(Diagnostic::error(&format!("mismatched types: expected a tuple with {}, found {}", count(patterns.len(), "element"), v.type_name()))
+                    .with_label(&pattern.span, &format!("expected a tuple with {}", count(patterns.len(), "element")))),
+            };
+            let types = match ty.map(|x| &x.node) {
+                Some(TypeExpr::TUPLE(types)) if types.len() == patterns.len() => types.iter().map(Some).collect(),
+                _ => vec![None; patterns.len()],
+            };
+            for ((x, v), ty) in patterns.iter().zip(elements).zip(types) {
+                bind(scope, x, v, mutable, ty)?;
+            }
         }
+        // literals and variants only appear in the arms of `match`
+        Pattern::WILDCARD | Pattern::LITERAL(_) | Pattern::VARIANT(_, _, _) => {}
     }
-    if let PrimitiveType::I32(f1) = o1 {
-        if let PrimitiveType::I32(f2) = o2 {
-            return PrimitiveType::I32(f1 * f2);
+    return Ok(());
+}
+
+/// Whether `v` matches `pattern`, collecting the values of the names it binds
+fn matches(scope: Rc<RefCell<Scope>>, pattern: &Spanned<Pattern>, v: &PrimitiveType, bound: &mut Vec<(Identifier, PrimitiveType)>) -> RunResult<bool> {
+    match &pattern.node {
+        Pattern::BIND(id) => {
+            bound.push((id.clone(), v.clone()));
+            return Ok(true);
+        }
+        Pattern::WILDCARD => return Ok(true),
+        Pattern::LITERAL(x) => {
+            let literal = get_value(scope, x)?;
+            return Ok(match (&literal, v) {
+                (PrimitiveType::INT(x, _), PrimitiveType::INT(y, _)) => x == y,
+                (PrimitiveType::F64(x), PrimitiveType::F64(y)) => x == y,
+                (PrimitiveType::F64(x), PrimitiveType::F32(y)) => *x as f32 == *y,
+                (PrimitiveType::STR(x), PrimitiveType::STR(y)) => x == y,
+                (PrimitiveType::CSTR(x), PrimitiveType::CSTR(y)) => x == y,
+                (PrimitiveType::CHAR(x), PrimitiveType::CHAR(y)) => x == y,
+                _ => false,
+            });
+        }
+        Pattern::TUPLE(patterns) => {
+            let elements = match v {
+                PrimitiveType::TUPLE(x) if x.len() == patterns.len() => x,
+                _ => return Ok(false),
+            };
+            for (x, v) in patterns.iter().zip(elements.iter()) {
+                if !matches(scope.clone(), x, v, bound)? {
+                    return Ok(false);
+                }
+            }
+            return Ok(true);
+        }
+        Pattern::VARIANT(ty, name, fields) => {
+            let x = match v {
+                PrimitiveType::VARIANT(x) if x.ty.name == *ty && x.name() == name => x,
+                _ => return Ok(false),
+            };
+            if fields.len() != x.fields.len() {
+                return Err(Diagnostic::error(&format!("this pattern has {}, but the variant `{}::{}` has {}", count(fields.len(), "field"), ty.0, name.0, count(x.fields.len(), "field")))
+                    .with_label(&pattern.span, &format!("expected {}", count(x.fields.len(), "field"))));
+            }
+            for (i, field) in fields.iter().enumerate() {
+                if !matches(scope.clone(), field, &x.fields[i], bound)? {
+                    return Ok(false);
+                }
+            }
+            return Ok(true);
         }
     }
-    fatal_(&format!("Unknown error"))
 }
 
-fn div_builtin(scope: Rc<RefCell<Scope>>, func_call: &FuncCall) -> PrimitiveType {
+/// Runs the first arm of `x` whose pattern matches and whose guard holds
+fn run_match(scope: Rc<RefCell<Scope>>, x: &Match) -> RunResult<Completion> {
+    let v = get_value(scope.clone(), &x.value)?;
+    for arm in x.arms.iter() {
+        let mut bound = vec![];
+        if !matches(scope.clone(), &arm.node.pattern, &v, &mut bound)? {
+            continue;
+        }
+        let arm_scope = Rc::new(RefCell::new(Scope {
+            parent: Some(scope.clone()),
+            local: HashMap::new(),
+        }));
+        for (id, v) in bound {
+            arm_scope.as_ref().borrow_mut().declare(&id.0, Some(v), false);
+        }
+        if let Some(guard) = &arm.node.guard {
+            if !to_boolean(&get_value(arm_scope.clone(), guard)?) {
+                continue;
+            }
+        }
+        let c = match &arm.node.body {
+            ArmBody::EXPR(e) => Completion::NORMAL(get_value(arm_scope.clone(), e)?),
+            ArmBody::BLOCK(b) => run_block(false, Some(arm_scope.clone()), b)?,
+        };
+        arm_scope.as_ref().borrow().end();
+        return Ok(c);
+    }
+    return Err(Diagnostic::error(&format!("no arm of `match` matches `{}`", v))
+        .with_label(&x.value.span, "matched value"));
+}
+
+/// Evaluates both operands of a binary built-in
+fn operands(scope: Rc<RefCell<Scope>>, func_call: &FuncCall) -> RunResult<(PrimitiveType, PrimitiveType)> {
     let x = &func_call.arg_list[0];
     let y = &func_call.arg_list[1];
-    let o1 = get_value(scope.clone(), x);
-    let o2 = get_value(scope, y);
-    if let PrimitiveType::F64(f1) = o1 {
-        if let PrimitiveType::F64(f2) = o2 {
-            return PrimitiveType::F64(f1 / f2);
+    let o1 = get_value(scope.clone(), x)?;
+    let o2 = get_value(scope, y)?;
+    return Ok((o1, o2));
+}
+
+fn mismatched(func_call: &FuncCall, span: &Span, o1: &PrimitiveType, o2: &PrimitiveType) -> Diagnostic {
+    return Diagnostic::error(&format!("cannot apply `{}` to {} and {}", func_call.func_name.0, o1.type_name(), o2.type_name()))
+        .with_label(span, "mismatched operand types")
+        .with_secondary(&func_call.arg_list[0].span, &o1.type_name())
+        .with_secondary(&func_call.arg_list[1].span, &o2.type_name());
+}
+
+/// Applies a binary arithmetic operator to two numbers. Integers of different
+/// types are promoted to a common type; the result must fit into that type.
+fn arithmetic(
+    func_call: &FuncCall,
+    span: &Span,
+    o1: PrimitiveType,
+    o2: PrimitiveType,
+    int: fn(i128, i128) -> Option<i128>,
+    float: fn(f64, f64) -> f64,
+) -> RunResult<PrimitiveType> {
+    match (&o1, &o2) {
+        (PrimitiveType::INT(x, t1), PrimitiveType::INT(y, t2)) => {
+            let t = IntType::promote(*t1, *t2);
+            if let Some(r) = int(*x, *y) {
+                if t.contains(r) {
+                    return Ok(PrimitiveType::INT(r, t));
+                }
+            }
+            if *y == 0 && (func_call.func_name.0 == "/" || func_call.func_name.0 == "%") {
+                return Err(Diagnostic::error("attempt to divide by zero")
+                    .with_label(span, "division by zero")
+                    .with_secondary(&func_call.arg_list[1].span, "this is 0"));
+            }
+            return Err(Diagnostic::error(&format!("arithmetic overflow: `{} {} {}` does not fit in {}", x, func_call.func_name.0, y, t))
+                .with_label(span, &format!("the result overflows {}", t)));
+        }
+        (PrimitiveType::F64(x), PrimitiveType::F64(y)) => Ok(PrimitiveType::F64(float(*x, *y))),
+        (PrimitiveType::F32(x), PrimitiveType::F32(y)) => Ok(PrimitiveType::F32(float(*x as f64, *y as f64) as f32)),
+        _ => Err(mismatched(func_call, span, &o1, &o2)),
+    }
+}
+
+fn add_builtin(scope: Rc<RefCell<Scope>>, func_call: &FuncCall, span: &Span) -> RunResult<PrimitiveType> {
+    let (o1, o2) = operands(scope, func_call)?;
+    if let (PrimitiveType::STR(x), PrimitiveType::STR(y)) = (&o1, &o2) {
+        return Ok(PrimitiveType::STR(format!("{}{}", x, y)));
+    }
+    return arithmetic(func_call, span, o1, o2, |x, y| x.checked_add(y), |x, y| x + y);
+}
+
+fn sub_builtin(scope: Rc<RefCell<Scope>>, func_call: &FuncCall, span: &Span) -> RunResult<PrimitiveType> {
+    if func_call.arg_list.len() == 1 {
+        return neg_builtin(scope, func_call, span);
+    }
+    let (o1, o2) = operands(scope, func_call)?;
+    return arithmetic(func_call, span, o1, o2, |x, y| x.checked_sub(y), |x, y| x - y);
+}
+
+fn multi_builtin(scope: Rc<RefCell<Scope>>, func_call: &FuncCall, span: &Span) -> RunResult<PrimitiveType> {
+    let (o1, o2) = operands(scope, func_call)?;
+    return arithmetic(func_call, span, o1, o2, |x, y| x.checked_mul(y), |x, y| x * y);
+}
+
+fn div_builtin(scope: Rc<RefCell<Scope>>, func_call: &FuncCall, span: &Span) -> RunResult<PrimitiveType> {
+    let (o1, o2) = operands(scope, func_call)?;
+    return arithmetic(func_call, span, o1, o2, |x, y| x.checked_div(y), |x, y| x / y);
+}
+
+fn rem_builtin(scope: Rc<RefCell<Scope>>, func_call: &FuncCall, span: &Span) -> RunResult<PrimitiveType> {
+    let (o1, o2) = operands(scope, func_call)?;
+    return arithmetic(func_call, span, o1, o2, |x, y| x.checked_rem(y), |x, y| x % y);
+}
+
+fn pow_builtin(scope: Rc<RefCell<Scope>>, func_call: &FuncCall, span: &Span) -> RunResult<PrimitiveType> {
+    let (o1, o2) = operands(scope, func_call)?;
+    if let (PrimitiveType::INT(_, _), PrimitiveType::INT(y, _)) = (&o1, &o2) {
+        if *y < 0 {
+            return Err(Diagnostic::error("cannot raise an integer to a negative power")
+                .with_label(span, "negative exponent")
+                .with_secondary(&func_call.arg_list[1].span, &format!("this is {}", y)));
+        }
+    }
+    return arithmetic(func_call, span, o1, o2, |x, y| u32::try_from(y).ok().and_then(|y| x.checked_pow(y)), |x, y| x.powf(y));
+}
+
+/// Evaluates the single operand of a unary built-in
+fn operand(scope: Rc<RefCell<Scope>>, func_call: &FuncCall) -> RunResult<PrimitiveType> {
+    return get_value(scope, &func_call.arg_list[0]);
+}
+
+fn neg_builtin(scope: Rc<RefCell<Scope>>, func_call: &FuncCall, span: &Span) -> RunResult<PrimitiveType> {
+    match operand(scope, func_call)? {
+        PrimitiveType::INT(x, t) => {
+            // an unsuffixed literal takes the type of its negated value
+            let t = match func_call.arg_list[0].node {
+                Value::INT(_, None) => IntType::of_literal(-x),
+                _ => t,
+            };
+            if !t.contains(-x) {
+                return Err(Diagnostic::error(&format!("arithmetic overflow: `-{}` does not fit in {}", x, t))
+                    .with_label(span, &format!("the result overflows {}", t)));
+            }
+            Ok(PrimitiveType::INT(-x, t))
+        }
+        PrimitiveType::F64(x) => Ok(PrimitiveType::F64(-x)),
+        PrimitiveType::F32(x) => Ok(PrimitiveType::F32(-x)),
+        o => Err(Diagnostic::error(&format!("cannot apply `-` to {}", o.type_name()))
+            .with_label(span, "expected a number")),
+    }
+}
+
+fn not_builtin(scope: Rc<RefCell<Scope>>, func_call: &FuncCall, _span: &Span) -> RunResult<PrimitiveType> {
+    let v = operand(scope, func_call)?;
+    return Ok(PrimitiveType::INT(!to_boolean(&v) as i128, IntType::I32));
+}
+
+fn bit_not_builtin(scope: Rc<RefCell<Scope>>, func_call: &FuncCall, span: &Span) -> RunResult<PrimitiveType> {
+    match operand(scope, func_call)? {
+        PrimitiveType::INT(x, t) => Ok(PrimitiveType::INT(t.wrap(!x), t)),
+        o => Err(Diagnostic::error(&format!("cannot apply `~` to {}", o.type_name()))
+            .with_label(span, "expected an integer")),
+    }
+}
+
+/// Applies a binary bitwise operator to two integers promoted to a common type
+fn bitwise(func_call: &FuncCall, span: &Span, o1: PrimitiveType, o2: PrimitiveType, int: fn(i128, i128) -> i128) -> RunResult<PrimitiveType> {
+    match (&o1, &o2) {
+        (PrimitiveType::INT(x, t1), PrimitiveType::INT(y, t2)) => {
+            let t = IntType::promote(*t1, *t2);
+            return Ok(PrimitiveType::INT(t.wrap(int(*x, *y)), t));
+        }
+        _ => Err(mismatched(func_call, span, &o1, &o2)),
+    }
+}
+
+fn bit_and_builtin(scope: Rc<RefCell<Scope>>, func_call: &FuncCall, span: &Span) -> RunResult<PrimitiveType> {
+    let (o1, o2) = operands(scope, func_call)?;
+    return bitwise(func_call, span, o1, o2, |x, y| x & y);
+}
+
+fn bit_or_builtin(scope: Rc<RefCell<Scope>>, func_call: &FuncCall, span: &Span) -> RunResult<PrimitiveType> {
+    let (o1, o2) = operands(scope, func_call)?;
+    return bitwise(func_call, span, o1, o2, |x, y| x | y);
+}
+
+fn bit_xor_builtin(scope: Rc<RefCell<Scope>>, func_call: &FuncCall, span: &Span) -> RunResult<PrimitiveType> {
+    let (o1, o2) = operands(scope, func_call)?;
+    return bitwise(func_call, span, o1, o2, |x, y| x ^ y);
+}
+
+/// Shifts an integer, keeping its type. Bits shifted out are lost; the shift
+/// amount must be smaller than the width of the type.
+fn shift(func_call: &FuncCall, span: &Span, o1: PrimitiveType, o2: PrimitiveType, left: bool) -> RunResult<PrimitiveType> {
+    match (&o1, &o2) {
+        (PrimitiveType::INT(x, t), PrimitiveType::INT(n, _)) => {
+            if *n < 0 || *n >= t.bits() as i128 {
+                return Err(Diagnostic::error(&format!("shift amount {} is out of range for {}", n, t))
+                    .with_label(span, "shift overflow")
+                    .with_secondary(&func_call.arg_list[1].span, &format!("must be between 0 and {}", t.bits() - 1)));
+            }
+            let r = if left { x << n } else { x >> n };
+            return Ok(PrimitiveType::INT(t.wrap(r), *t));
         }
+        _ => Err(mismatched(func_call, span, &o1, &o2)),
     }
-    if let PrimitiveType::I32(f1) = o1 {
-        if let PrimitiveType::I32(f2) = o2 {
-            return PrimitiveType::I32(f1 / f2);
+}
+
+fn shl_builtin(scope: Rc<RefCell<Scope>>, func_call: &FuncCall, span: &Span) -> RunResult<PrimitiveType> {
+    let (o1, o2) = operands(scope, func_call)?;
+    return shift(func_call, span, o1, o2, true);
+}
+
+fn shr_builtin(scope: Rc<RefCell<Scope>>, func_call: &FuncCall, span: &Span) -> RunResult<PrimitiveType> {
+    let (o1, o2) = operands(scope, func_call)?;
+    return shift(func_call, span, o1, o2, false);
+}
+
+/// Compares two values of the same kind; integers of any types compare by value.
+/// `None` for unordered floats.
+fn compare(func_call: &FuncCall, span: &Span, o1: &PrimitiveType, o2: &PrimitiveType) -> RunResult<Option<Ordering>> {
+    return match (o1, o2) {
+        (PrimitiveType::INT(x, _), PrimitiveType::INT(y, _)) => Ok(Some(x.cmp(y))),
+        (PrimitiveType::F64(x), PrimitiveType::F64(y)) => Ok(x.partial_cmp(y)),
+        (PrimitiveType::F32(x), PrimitiveType::F32(y)) => Ok(x.partial_cmp(y)),
+        (PrimitiveType::STR(x), PrimitiveType::STR(y)) => Ok(Some(x.cmp(y))),
+        (PrimitiveType::CSTR(x), PrimitiveType::CSTR(y)) => Ok(Some(x.cmp(y))),
+        (PrimitiveType::CHAR(x), PrimitiveType::CHAR(y)) => Ok(Some(x.cmp(y))),
+        (PrimitiveType::REF(x), PrimitiveType::REF(y)) => Ok(if x == y { Some(Ordering::Equal) } else { None }),
+        // tuples compare element by element
+        (PrimitiveType::TUPLE(x), PrimitiveType::TUPLE(y)) if x.len() == y.len() => {
+            for (a, b) in x.iter().zip(y.iter()) {
+                match compare(func_call, span, a, b)? {
+                    Some(Ordering::Equal) => {}
+                    ordering => return Ok(ordering),
+                }
+            }
+            Ok(Some(Ordering::Equal))
         }
+        _ => Err(mismatched(func_call, span, o1, o2)),
+    };
+}
+
+fn comparison(scope: Rc<RefCell<Scope>>, func_call: &FuncCall, span: &Span, test: fn(Option<Ordering>) -> bool) -> RunResult<PrimitiveType> {
+    let (o1, o2) = operands(scope, func_call)?;
+    let ordering = compare(func_call, span, &o1, &o2)?;
+    return Ok(PrimitiveType::INT(test(ordering) as i128, IntType::I32));
+}
+
+/// `==` if `equal`, else `!=`. Objects are compared by the `eq` method of
+/// their `Eq` implementation, which is called with the right-hand side.
+fn equality(scope: Rc<RefCell<Scope>>, func_call: &FuncCall, span: &Span, equal: bool) -> RunResult<PrimitiveType> {
+    let o1 = get_value(scope.clone(), &func_call.arg_list[0])?;
+    if let PrimitiveType::OBJECT(_) = &o1 {
+        let eq = Identifier("eq".to_string());
+        let m = match method(&o1, &eq) {
+            Some(m) => m,
+            None => return Err(Diagnostic::error(&format!("cannot compare {} with `{}`", o1.type_name(), func_call.func_name.0))
+                .with_label(&func_call.arg_list[0].span, &format!("{} does not implement `Eq`", o1.type_name()))
+                .with_note("implement `Eq` for it, or derive it with `#[derive(Eq)]`")),
+        };
+        let fc = FuncCall { func_name: eq, arg_list: vec![func_call.arg_list[1].clone()] };
+        let same = to_boolean(&call(scope, PrimitiveType::FUNCTION(m), Some(o1), &fc, span)?);
+        return Ok(PrimitiveType::INT((same == equal) as i128, IntType::I32));
+    }
+    if let PrimitiveType::VARIANT(_) = &o1 {
+        return Err(Diagnostic::error(&format!("cannot compare {} with `{}`", o1.type_name(), func_call.func_name.0))
+            .with_label(&func_call.arg_list[0].span, "the variants of an enum cannot be compared")
+            .with_note("tell them apart with `match` instead"));
     }
+    let o2 = get_value(scope, &func_call.arg_list[1])?;
+    let same = compare(func_call, span, &o1, &o2)? == Some(Ordering::Equal);
+    return Ok(PrimitiveType::INT((same == equal) as i128, IntType::I32));
+}
+
+fn eq_builtin(scope: Rc<RefCell<Scope>>, func_call: &FuncCall, span: &Span) -> RunResult<PrimitiveType> {
+    return equality(scope, func_call, span, true);
+}
+
+fn ne_builtin(scope: Rc<RefCell<Scope>>, func_call: &FuncCall, span: &Span) -> RunResult<PrimitiveType> {
+    return equality(scope, func_call, span, false);
+}
+
+fn lt_builtin(scope: Rc<RefCell<Scope>>, func_call: &FuncCall, span: &Span) -> RunResult<PrimitiveType> {
+    return comparison(scope, func_call, span, |x| x == Some(Ordering::Less));
+}
+
+fn le_builtin(scope: Rc<RefCell<Scope>>, func_call: &FuncCall, span: &Span) -> RunResult<PrimitiveType> {
+    return comparison(scope, func_call, span, |x| x == Some(Ordering::Less) || x == Some(Ordering::Equal));
+}
 
-    fatal_(&format!("Unknown error"))
+fn gt_builtin(scope: Rc<RefCell<Scope>>, func_call: &FuncCall, span: &Span) -> RunResult<PrimitiveType> {
+    return comparison(scope, func_call, span, |x| x == Some(Ordering::Greater));
 }
 
-fn print(scope: Rc<RefCell<Scope>>, func_call: &FuncCall) -> PrimitiveType {
+fn ge_builtin(scope: Rc<RefCell<Scope>>, func_call: &FuncCall, span: &Span) -> RunResult<PrimitiveType> {
+    return comparison(scope, func_call, span, |x| x == Some(Ordering::Greater) || x == Some(Ordering::Equal));
+}
+
+/// `to_string(x)`: the `debug()` of an object implementing `Debug`, or else `x`
+/// as `print` would write it
+fn to_string(scope: Rc<RefCell<Scope>>, func_call: &FuncCall, span: &Span) -> RunResult<PrimitiveType> {
+    if func_call.arg_list.len() != 1 {
+        return Err(Diagnostic::error(&format!("function `to_string` takes 1 argument but {}", supplied(func_call.arg_list.len())))
+            .with_label(span, "expected 1 argument"));
+    }
+    let v = get_value(scope.clone(), &func_call.arg_list[0])?;
+    if let PrimitiveType::OBJECT(x) = &v {
+        let is_debug = x.as_ref().borrow().ty.as_ref().borrow().implements("Debug");
+        if is_debug {
+            if let Some(debug) = method(&v, &Identifier("debug".to_string())) {
+                let fc = FuncCall { func_name: Identifier("debug".to_string()), arg_list: vec![] };
+                return call(scope, PrimitiveType::FUNCTION(debug), Some(v), &fc, span);
+            }
+        }
+    }
+    return Ok(PrimitiveType::STR(v.to_string()));
+}
+
+/// `new(x)`: a reference to `x` moved to the heap, until it is deleted
+fn new(scope: Rc<RefCell<Scope>>, func_call: &FuncCall, span: &Span) -> RunResult<PrimitiveType> {
+    if func_call.arg_list.len() != 1 {
+        return Err(Diagnostic::error(&format!("function `new` takes 1 argument but {}", supplied(func_call.arg_list.len())))
+            .with_label(span, "expected 1 argument"));
+    }
+    let arg = &func_call.arg_list[0];
+    let v = get_value(scope.clone(), arg)?;
+    let v = copied(scope, v, arg)?;
+    let x = Allocation { value: Some(v), deleted: None };
+    return Ok(PrimitiveType::REF(Reference { target: Target::HEAP(Rc::new(RefCell::new(x))), pointer: false }));
+}
+
+/// `delete(r)`: frees what `new` allocated. A variable passed to it is reset
+/// to null; other references to the value are left dangling.
+fn delete(scope: Rc<RefCell<Scope>>, func_call: &FuncCall, span: &Span) -> RunResult<PrimitiveType> {
+    if func_call.arg_list.len() != 1 {
+        return Err(Diagnostic::error(&format!("function `delete` takes 1 argument but {}", supplied(func_call.arg_list.len())))
+            .with_label(span, "expected 1 argument"));
+    }
+    let arg = &func_call.arg_list[0];
+    let r = match get_value(scope.clone(), arg)? {
+        PrimitiveType::REF(r) => r,
+        v => return Err(Diagnostic::error(&format!("cannot delete a value of type {}", v.type_name()))
+            .with_label(&arg.span, "not a reference")
+            .with_note("only references returned by `new` can be deleted")),
+    };
+    match &r.target {
+        Target::HEAP(x) => {
+            if let Some(at) = x.as_ref().borrow().deleted.clone() {
+                return Err(Diagnostic::error("double delete")
+                    .with_label(&arg.span, "this reference was already deleted")
+                    .with_secondary(&at, "first deleted here"));
+            }
+            let mut x = x.as_ref().borrow_mut();
+            x.value = None;
+            x.deleted = Some(span.clone());
+        }
+        Target::STACK(_, name, borrowed) => {
+            return Err(Diagnostic::error("cannot delete a reference to a stack value")
+                .with_label(&arg.span, &format!("this refers to the variable `{}`", name))
+                .with_secondary(borrowed, "borrowed here")
+                .with_note("only references returned by `new` can be deleted"));
+        }
+        Target::NULL(Some(at)) => {
+            return Err(Diagnostic::error("double delete")
+                .with_label(&arg.span, "this reference is null")
+                .with_secondary(at, "already deleted here"));
+        }
+        Target::NULL(None) => {
+            return Err(Diagnostic::error("cannot delete a null reference")
+                .with_label(&arg.span, "this reference is null"));
+        }
+    }
+    if let Value::VAR(id) = &arg.node {
+        let slot = scope.as_ref().borrow().slot(&id.0);
+        if let Some(x) = slot {
+            let null = Reference { target: Target::NULL(Some(span.clone())), pointer: r.pointer };
+            x.as_ref().borrow_mut().value = Some(PrimitiveType::REF(null));
+        }
+    }
+    return Ok(VOID);
+}
+
+/// `len(x)`: the number of items of a Rust-like array, or of characters in a
+/// string. A C-like array does not know its length.
+fn len(scope: Rc<RefCell<Scope>>, func_call: &FuncCall, span: &Span) -> RunResult<PrimitiveType> {
+    if func_call.arg_list.len() != 1 {
+        return Err(Diagnostic::error(&format!("function `len` takes 1 argument but {}", supplied(func_call.arg_list.len())))
+            .with_label(span, "expected 1 argument"));
+    }
+    let arg = &func_call.arg_list[0];
+    let n = match get_value(scope, arg)? {
+        PrimitiveType::ARRAY(x) if x.sized => x.items.as_ref().borrow().len(),
+        PrimitiveType::ARRAY(_) => {
+            return Err(Diagnostic::error("cannot take the length of a C-like array")
+                .with_label(&arg.span, "this array does not store its length")
+                .with_note("use a Rust-like array `[T]`, or bind its length with `T[n]` and use `n`"));
+        }
+        PrimitiveType::STR(s) | PrimitiveType::CSTR(s) => s.chars().count(),
+        v => return Err(Diagnostic::error(&format!("cannot take the length of {}", v.type_name()))
+            .with_label(&arg.span, "expected an array or a string")),
+    };
+    return Ok(PrimitiveType::INT(n as i128, IntType::U64));
+}
+
+fn print(scope: Rc<RefCell<Scope>>, func_call: &FuncCall, _span: &Span) -> RunResult<PrimitiveType> {
     for o in func_call.arg_list.iter() {
-        let v = get_value(scope.clone(), o);
+        let v = get_value(scope.clone(), o)?;
         print!("{} ", v);
     }
     println!();
-    return VOID;
+    return Ok(VOID);
 }
 
-pub fn run_code(root: &Block) {
+pub fn run_code(root: &Block, diags: &mut Diagnostics) {
     let root_scope = Rc::new(RefCell::new(Scope {
         parent: None,
         local: Default::default(),
@@ -244,12 +2161,33 @@ pub fn run_code(root: &Block) {
     {
         let mut ref_mut = root_scope.as_ref().borrow_mut();
 
-        ref_mut.local.insert("-".to_string(), PrimitiveType::BUILTIN(BuiltInFunc { execute: sub_builtin }));
-        ref_mut.local.insert("+".to_string(), PrimitiveType::BUILTIN(BuiltInFunc { execute: add_builtin }));
-        ref_mut.local.insert("*".to_string(), PrimitiveType::BUILTIN(BuiltInFunc { execute: multi_builtin }));
-        ref_mut.local.insert("/".to_string(), PrimitiveType::BUILTIN(BuiltInFunc { execute: div_builtin }));
-        ref_mut.local.insert("print".to_string(), PrimitiveType::BUILTIN(BuiltInFunc { execute: print }));
+        ref_mut.declare("-", Some(PrimitiveType::BUILTIN(BuiltInFunc { execute: sub_builtin })), false);
+        ref_mut.declare("+", Some(PrimitiveType::BUILTIN(BuiltInFunc { execute: add_builtin })), false);
+        ref_mut.declare("*", Some(PrimitiveType::BUILTIN(BuiltInFunc { execute: multi_builtin })), false);
+        ref_mut.declare("/", Some(PrimitiveType::BUILTIN(BuiltInFunc { execute: div_builtin })), false);
+        ref_mut.declare("%", Some(PrimitiveType::BUILTIN(BuiltInFunc { execute: rem_builtin })), false);
+        ref_mut.declare("**", Some(PrimitiveType::BUILTIN(BuiltInFunc { execute: pow_builtin })), false);
+        ref_mut.declare("!", Some(PrimitiveType::BUILTIN(BuiltInFunc { execute: not_builtin })), false);
+        ref_mut.declare("~", Some(PrimitiveType::BUILTIN(BuiltInFunc { execute: bit_not_builtin })), false);
+        ref_mut.declare("&", Some(PrimitiveType::BUILTIN(BuiltInFunc { execute: bit_and_builtin })), false);
+        ref_mut.declare("|", Some(PrimitiveType::BUILTIN(BuiltInFunc { execute: bit_or_builtin })), false);
+        ref_mut.declare("^", Some(PrimitiveType::BUILTIN(BuiltInFunc { execute: bit_xor_builtin })), false);
+        ref_mut.declare("<<", Some(PrimitiveType::BUILTIN(BuiltInFunc { execute: shl_builtin })), false);
+        ref_mut.declare(">>", Some(PrimitiveType::BUILTIN(BuiltInFunc { execute: shr_builtin })), false);
+        ref_mut.declare("==", Some(PrimitiveType::BUILTIN(BuiltInFunc { execute: eq_builtin })), false);
+        ref_mut.declare("!=", Some(PrimitiveType::BUILTIN(BuiltInFunc { execute: ne_builtin })), false);
+        ref_mut.declare("<", Some(PrimitiveType::BUILTIN(BuiltInFunc { execute: lt_builtin })), false);
+        ref_mut.declare("<=", Some(PrimitiveType::BUILTIN(BuiltInFunc { execute: le_builtin })), false);
+        ref_mut.declare(">", Some(PrimitiveType::BUILTIN(BuiltInFunc { execute: gt_builtin })), false);
+        ref_mut.declare(">=", Some(PrimitiveType::BUILTIN(BuiltInFunc { execute: ge_builtin })), false);
+        ref_mut.declare("print", Some(PrimitiveType::BUILTIN(BuiltInFunc { execute: print })), false);
+        ref_mut.declare("to_string", Some(PrimitiveType::BUILTIN(BuiltInFunc { execute: to_string })), false);
+        ref_mut.declare("len", Some(PrimitiveType::BUILTIN(BuiltInFunc { execute: len })), false);
+        ref_mut.declare("new", Some(PrimitiveType::BUILTIN(BuiltInFunc { execute: new })), false);
+        ref_mut.declare("delete", Some(PrimitiveType::BUILTIN(BuiltInFunc { execute: delete })), false);
     }
 
-    run_block(false, Some(root_scope), root);
-}
\ No newline at end of file
+    if let Err(e) = run_block(false, Some(root_scope), root).and_then(finished) {
+        diags.emit(e);
+    }
+}
@@ -0,0 +1,128 @@
+use std::fmt::{Debug, Display, Error, Formatter};
+use std::rc::Rc;
+
+/// A loaded source file together with the byte offsets of its line starts,
+/// so that any byte position can be resolved to a line and a column.
+pub struct SourceFile {
+    pub name: String,
+    pub text: String,
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    pub fn new(name: &str, text: String) -> Rc<SourceFile> {
+        let mut line_starts = vec![0];
+        for (i, b) in text.bytes().enumerate() {
+            if b == b'\n' {
+                line_starts.push(i + 1);
+            }
+        }
+        return Rc::new(SourceFile {
+            name: name.to_string(),
+            text,
+            line_starts,
+        });
+    }
+
+    /// 0-based index of the line containing `pos`
+    pub fn line_index(&self, pos: usize) -> usize {
+        return match self.line_starts.binary_search(&pos) {
+            Ok(i) => i,
+            Err(i) => i - 1,
+        };
+    }
+
+    /// Byte offset at which the 0-based line `line` starts
+    pub fn line_start(&self, line: usize) -> usize {
+        return self.line_starts[line];
+    }
+
+    /// 1-based line and column of the byte offset `pos`; columns count characters
+    pub fn line_col(&self, pos: usize) -> (usize, usize) {
+        let line = self.line_index(pos);
+        let start = self.line_starts[line];
+        let end = pos.min(self.text.len());
+        return (line + 1, self.text[start..end].chars().count() + 1);
+    }
+
+    /// Text of the 0-based line `line`, without the line terminator
+    pub fn line_text(&self, line: usize) -> &str {
+        let start = self.line_starts[line];
+        let end = match self.line_starts.get(line + 1) {
+            Some(x) => *x,
+            None => self.text.len(),
+        };
+        return self.text[start..end].trim_end_matches(['\n', '\r']);
+    }
+}
+
+/// A byte range `start..end` inside `file`
+#[derive(Clone)]
+pub struct Span {
+    pub file: Rc<SourceFile>,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(file: &Rc<SourceFile>, start: usize, end: usize) -> Span {
+        return Span {
+            file: file.clone(),
+            start,
+            end,
+        };
+    }
+
+    /// The smallest span covering both `self` and `other`
+    pub fn to(&self, other: &Span) -> Span {
+        return Span::new(&self.file, self.start.min(other.start), self.end.max(other.end));
+    }
+
+    pub fn line_col(&self) -> (usize, usize) {
+        return self.file.line_col(self.start);
+    }
+
+    pub fn text(&self) -> &str {
+        return &self.file.text[self.start..self.end];
+    }
+}
+
+impl PartialEq for Span {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.file, &other.file) && self.start == other.start && self.end == other.end
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        let (line, col) = self.line_col();
+        return write!(f, "{}:{}:{}", self.file.name, line, col);
+    }
+}
+
+impl Debug for Span {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        let (line, col) = self.line_col();
+        let (end_line, end_col) = self.file.line_col(self.end);
+        return write!(f, "{}:{}:{}-{}:{}", self.file.name, line, col, end_line, end_col);
+    }
+}
+
+/// An AST node together with the source range it was parsed from
+#[derive(PartialEq, Clone)]
+pub struct Spanned<T> {
+    pub node: T,
+    pub span: Span,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(node: T, span: Span) -> Spanned<T> {
+        return Spanned { node, span };
+    }
+}
+
+impl<T: Debug> Debug for Spanned<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        self.node.fmt(f)
+    }
+}
@@ -0,0 +1,140 @@
+use std::fmt::{Display, Error, Formatter};
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum IntType {
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+}
+
+impl IntType {
+    /// Looks up an integer type by name, including the aliases from the README
+    /// and the `ul` literal suffix
+    pub fn from_name(name: &str) -> Option<IntType> {
+        return match name {
+            "i8" => Some(IntType::I8),
+            "i16" | "short" => Some(IntType::I16),
+            "i32" | "int" => Some(IntType::I32),
+            "i64" | "long" | "isize" => Some(IntType::I64),
+            "u8" | "byte" | "uchar" => Some(IntType::U8),
+            "u16" | "ushort" => Some(IntType::U16),
+            "u32" | "uint" => Some(IntType::U32),
+            "u64" | "ulong" | "usize" | "ul" => Some(IntType::U64),
+            _ => None,
+        };
+    }
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            IntType::I8 => "i8",
+            IntType::I16 => "i16",
+            IntType::I32 => "i32",
+            IntType::I64 => "i64",
+            IntType::U8 => "u8",
+            IntType::U16 => "u16",
+            IntType::U32 => "u32",
+            IntType::U64 => "u64",
+        };
+    }
+
+    pub fn bits(&self) -> u32 {
+        return match self {
+            IntType::I8 | IntType::U8 => 8,
+            IntType::I16 | IntType::U16 => 16,
+            IntType::I32 | IntType::U32 => 32,
+            IntType::I64 | IntType::U64 => 64,
+        };
+    }
+
+    pub fn signed(&self) -> bool {
+        return matches!(self, IntType::I8 | IntType::I16 | IntType::I32 | IntType::I64);
+    }
+
+    pub fn min(&self) -> i128 {
+        if self.signed() {
+            return -(1i128 << (self.bits() - 1));
+        }
+        return 0;
+    }
+
+    pub fn max(&self) -> i128 {
+        if self.signed() {
+            return (1i128 << (self.bits() - 1)) - 1;
+        }
+        return (1i128 << self.bits()) - 1;
+    }
+
+    pub fn contains(&self, x: i128) -> bool {
+        return self.min() <= x && x <= self.max();
+    }
+
+    /// `x` truncated to the width of the type, as by an `as` cast
+    pub fn wrap(&self, x: i128) -> i128 {
+        let bits = self.bits();
+        let low = x & ((1i128 << bits) - 1);
+        if low > self.max() {
+            return low - (1i128 << bits);
+        }
+        return low;
+    }
+
+    /// Type of the value `x` of an unsuffixed integer literal, which may be
+    /// negated: `i32`, or the first of `i64` and `u64` wide enough to hold it
+    pub fn of_literal(x: i128) -> IntType {
+        for t in [IntType::I32, IntType::I64].iter() {
+            if t.contains(x) {
+                return *t;
+            }
+        }
+        return IntType::U64;
+    }
+
+    /// The common type two integers are converted to before an arithmetic
+    /// operation: the wider one, and the unsigned one if they are as wide
+    pub fn promote(a: IntType, b: IntType) -> IntType {
+        if a.bits() != b.bits() {
+            return if a.bits() > b.bits() { a } else { b };
+        }
+        return if a.signed() { b } else { a };
+    }
+}
+
+impl Display for IntType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum FloatType {
+    F32,
+    F64,
+}
+
+impl FloatType {
+    pub fn from_name(name: &str) -> Option<FloatType> {
+        return match name {
+            "f32" | "float" => Some(FloatType::F32),
+            "f64" | "double" => Some(FloatType::F64),
+            _ => None,
+        };
+    }
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            FloatType::F32 => "f32",
+            FloatType::F64 => "f64",
+        };
+    }
+}
+
+impl Display for FloatType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        f.write_str(self.name())
+    }
+}
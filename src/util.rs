@@ -1,19 +1,4 @@
-
-pub fn error(err: &str) {
-    eprintln!("{}", err);
-}
-
-pub fn fatal_<T>(err: &str) -> T {
-    panic!("{}", err);
-
-}
-pub fn fatal(err: &str) {
-    panic!("{}", err);
-}
+/// The character starting at byte `i` of `s`, or `'\u{FFFF}'` past the end
 pub fn get(s: &str, i: usize) -> char {
-    if i >= s.len() {
-        return '\u{FFFF}';
-    }
-    return s.as_bytes()[i] as char;
+    return s.get(i..).and_then(|x| x.chars().next()).unwrap_or('\u{FFFF}');
 }
-
@@ -0,0 +1,216 @@
+#![allow(clippy::needless_return)]
+mod common;
+
+use common::*;
+
+#[test]
+fn literals_and_indexing() {
+    assert_prints("\
+let a = [1, 2, 3];
+let z = [0; 4];
+print(a, z, len(a), len(z), a[1]);
+let mut m = [1, 2];
+m[1] = 5;
+print(m);
+let c: int[] = [4, 5, 6];
+print(c[2]);
+", "[1, 2, 3] [0, 0, 0, 0] 3 4 2\n[1, 5]\n6");
+}
+
+#[test]
+fn assigned_items_take_the_item_type() {
+    assert_prints("\
+let mut a: [u8; 2] = [1, 2];
+a[0] = 5;
+fn f(x: [u8; 2]) { print(x); }
+f(a);
+let mut b: [f32; 1] = [1.0];
+b[0] = -2.5;
+let c: [f32; 1] = b;
+print(c);
+", "[5, 2]\n[-2.5]");
+    assert_fails("fn f(mut a) { a[0] = 300; }\nlet mut b: [u8; 1] = [1];\nf(b);\n", "\
+error: literal out of range for u8
+  --> /dev/stdin:1:22
+  |
+1 | fn f(mut a) { a[0] = 300; }
+  |                      ^^^ the range of u8 is 0..=255
+");
+}
+
+#[test]
+fn slices() {
+    assert_prints("let a = [1, 2, 3];\nlet s = a[1..3];\nprint(s, len(s), a[1..]);", "[2, 3] 2 [2, 3]");
+    assert_fails("let a = [1, 2, 3];\nprint(a[2..1]);\n", "\
+error: slice index starts at 2 but ends at 1
+  --> /dev/stdin:2:7
+  |
+2 | print(a[2..1]);
+  |       ^^^^^^^ invalid range
+");
+    assert_fails("let a = [1, 2, 3];\nprint(a[1..5]);\n", "\
+error: range end index 5 out of range for a length of 3
+  --> /dev/stdin:2:7
+  |
+2 | print(a[1..5]);
+  |       ^^^^^^^ out of bounds
+");
+}
+
+#[test]
+fn accesses_are_bounds_checked() {
+    assert_prints_then_fails("let a = [1, 2];\nprint(a[1]);\nprint(a[2]);\n", "2", "\
+error: index out of bounds: the length is 2 but the index is 2
+  --> /dev/stdin:3:9
+  |
+3 | print(a[2]);
+  |         ^ out of bounds
+");
+    assert_fails("let a = [1, 2];\nprint(a[-1]);\n", "\
+error: index out of bounds: the length is 2 but the index is -1
+  --> /dev/stdin:2:9
+  |
+2 | print(a[-1]);
+  |         ^^ out of bounds
+");
+    assert_fails("let a = [1, 2];\nprint(a[\"x\"]);\n", "\
+error: cannot index with ref str
+  --> /dev/stdin:2:9
+  |
+2 | print(a[\"x\"]);
+  |         ^^^ expected an integer
+");
+}
+
+#[test]
+fn arrays_are_copied_when_bound() {
+    assert_prints("let a = [1, 2, 3];\nlet mut b = a;\nb[0] = 9;\nprint(a, b);", "[1, 2, 3] [9, 2, 3]");
+    assert_prints("let mut a = [1, 2];\nlet mut b = [0, 0];\nb = a;\nb[1] = 5;\nprint(a, b);", "[1, 2] [1, 5]");
+    assert_prints("let a = [[1, 2], [3, 4]];\nlet mut b = a;\nb[0][0] = 9;\nprint(a, b);", "[[1, 2], [3, 4]] [[9, 2], [3, 4]]");
+    assert_prints("struct P { v: [int; 2] }\nlet a = [1, 2];\nlet mut p = P(a);\np.v[0] = 9;\nprint(a, p.v);", "[1, 2] [9, 2]");
+}
+
+#[test]
+fn repeated_items_are_copies() {
+    assert_prints("let mut a = [[0; 2]; 2];\na[0][1] = 5;\nprint(a);", "[[0, 5], [0, 0]]");
+    assert_prints("#[derive(Copy)]\nstruct P { a: int }\nlet mut ps = [P(1); 2];\nps[0].a = 9;\nprint(ps);", "[P { a: 9 }, P { a: 1 }]");
+    assert_fails("let a = [0; 100000000000000000];\n", "\
+error: cannot allocate an array of 100000000000000000 items
+  --> /dev/stdin:1:13
+  |
+1 | let a = [0; 100000000000000000];
+  |             ^^^^^^^^^^^^^^^^^^ too many items
+");
+    assert_fails("struct P { a: int }\nlet ps = [P(1); 2];\n", "\
+error: cannot repeat `P(1)`, as `P` does not implement `Copy`
+  --> /dev/stdin:2:11
+  |
+2 | let ps = [P(1); 2];
+  |           ^^^^ repeated here
+  = note: derive it with `#[derive(Copy)]`
+");
+}
+
+#[test]
+fn items_are_copied_when_bound() {
+    assert_prints("\
+#[derive(Copy)]
+struct P { a: int }
+let ps = [P(1), P(2)];
+for mut p in ps { p.a = 9; }
+let mut q = ps[0];
+q.a = 7;
+print(ps, q);
+", "[P { a: 1 }, P { a: 2 }] P { a: 7 }");
+    assert_prints("let mut n = [[1]];\nfor mut x in n { x[0] = 2; }\nprint(n);", "[[1]]");
+    assert_fails("struct P { a: int }\nlet mut ps = [P(1)];\nlet mut q = ps[0];\n", "\
+error: cannot copy `ps[0]`, as `P` does not implement `Copy`
+  --> /dev/stdin:3:13
+  |
+3 | let mut q = ps[0];
+  |             ^^^^^ copied here
+  = note: derive it with `#[derive(Copy)]`
+");
+    assert_prints_then_fails("\
+struct P { a: int }
+let ps = [P(1)];
+for p in ps { print(p.a); }
+for mut p in ps { p.a = 9; }
+", "1", "\
+error: cannot borrow `ps` as mutable, as `ps` is not declared as mutable
+  --> /dev/stdin:4:14
+  |
+4 | for mut p in ps { p.a = 9; }
+  |              ^^ cannot borrow as mutable
+  = note: consider making this binding mutable: `mut ps`
+");
+}
+
+#[test]
+fn arrays_are_passed_by_reference() {
+    assert_prints("fn f(mut x: [int; 2]) { x[0] = 7; }\nlet mut a = [1, 2];\nf(a);\nprint(a);", "[7, 2]");
+    assert_fails("fn f(mut x: [int; 2]) { x[0] = 7; }\nlet a = [1, 2];\nf(a);\n", "\
+error: cannot borrow `a` as mutable, as `a` is not declared as mutable
+  --> /dev/stdin:3:3
+  |
+3 | f(a);
+  |   ^ cannot borrow as mutable
+  = note: consider making this binding mutable: `mut a`
+");
+    assert_fails("let a = [1, 2];\na[0] = 3;\n", "\
+error: cannot assign to `a[0]`, as `a` is not declared as mutable
+  --> /dev/stdin:2:1
+  |
+2 | a[0] = 3;
+  | ^^^^^^^^ cannot assign
+  = note: consider making this binding mutable: `mut a`
+");
+}
+
+#[test]
+fn c_like_arrays_do_not_know_their_length() {
+    assert_prints_then_fails("\
+fn f(xs: int[n]) -> int { return n; }
+let c: int[3] = [1, 2, 3];
+print(f(c));
+let d: int[] = [1];
+print(f(d));
+", "3", "\
+error: cannot bind the length of a C-like array to `n`
+  --> /dev/stdin:5:9
+  |
+5 | print(f(d));
+  |         ^ the length of this array is unknown
+");
+}
+
+#[test]
+fn items_share_a_type() {
+    assert_prints("let x: u8 = 3;\nprint([1, x, 2], [1.5, 2.0]);", "[1, 3, 2] [1.5, 2]");
+    assert_fails("let a = [1, \"a\"];\nlet b = [1, 2.5];\n", "\
+error: mismatched types: expected integer, found ref str
+  --> /dev/stdin:1:13
+  |
+1 | let a = [1, \"a\"];
+  |             ^^^ expected integer, found ref str
+  |          - the type of the array is set by its first item
+
+error: mismatched types: expected integer, found float
+  --> /dev/stdin:2:13
+  |
+2 | let b = [1, 2.5];
+  |             ^^^ expected integer, found float
+  |          - the type of the array is set by its first item
+");
+}
+
+#[test]
+fn syntax_errors() {
+    assert_fails("let a = [1, 2;\n", "\
+error: expected ',' or ']' in the items of an array, found `;`
+  --> /dev/stdin:1:14
+  |
+1 | let a = [1, 2;
+  |              ^ expected ',' or ']' in the items of an array
+");
+}
@@ -0,0 +1,106 @@
+#![allow(clippy::needless_return)]
+mod common;
+
+use common::*;
+
+#[test]
+fn lambdas() {
+    assert_prints("let inc = |x| x + 1;\nlet f = || 1;\nlet sq = fn(x) -> int { return x * x; };\nprint(inc(1), f(), sq(4));", "2 1 16");
+    assert_prints("let f = |x| x;\nprint(f);", "fn lambda");
+}
+
+#[test]
+fn closures_capture_their_environment() {
+    assert_prints("\
+fn make_adder(k: int) -> fn(int) -> int {
+    return |x| x + k;
+}
+let add2 = make_adder(2);
+print(add2(3));
+", "5");
+    assert_prints("\
+fn counter() -> fn() -> int {
+    let mut n = 0;
+    return || {
+        n = n + 1;
+        return n;
+    };
+}
+let c = counter();
+let d = counter();
+print(c(), c(), d());
+", "1 2 1");
+    assert_prints("let mut total = 0;\nlet add = |x| { total = total + x; };\nadd(2);\nadd(3);\nprint(total);", "5");
+}
+
+#[test]
+fn scoping_is_lexical() {
+    assert_fails("fn f() {\n    print(secret);\n}\nfn g() {\n    let secret = 1;\n    f();\n}\ng();\n", "\
+error: cannot find value `secret` in this scope
+  --> /dev/stdin:2:11
+  |
+2 |     print(secret);
+  |           ^^^^^^ not found in this scope
+");
+    assert_prints("fn fact(n: int) -> int {\n    if n <= 1 {\n        return 1;\n    }\n    return n * fact(n - 1);\n}\nprint(fact(5));", "120");
+}
+
+#[test]
+fn shadowing_keeps_the_captured_variable() {
+    assert_prints("let x = 1;\nlet f = || x;\nlet x = 2;\nprint(f(), x);", "1 2");
+    assert_prints("let x = 1;\nfn show() { print(x); }\nlet x = 2;\nshow();\nfn again() { print(x); }\nagain();", "1\n2");
+    assert_prints("let mut a = 1;\nlet f = || { a = a + 1; };\nlet a = 10;\nf();\nprint(a);", "10");
+}
+
+#[test]
+fn nested_functions_are_local() {
+    assert_prints_then_fails("\
+fn outer() -> int {
+    fn inner(a) -> int { return a * 2; }
+    return inner(4);
+}
+print(outer());
+inner(1);
+", "8", "\
+error: cannot find value `inner` in this scope
+  --> /dev/stdin:6:1
+  |
+6 | inner(1);
+  | ^^^^^^^^ not found in this scope
+");
+}
+
+#[test]
+fn calling_values() {
+    assert_prints_then_fails("let f = |a, b| a + b;\nprint(f(1, 2));\nf(1);\n", "3", "\
+error: function `f` takes 2 arguments but 1 was supplied
+  --> /dev/stdin:3:1
+  |
+3 | f(1);
+  | ^^^^ expected 2 arguments
+");
+    assert_fails("let n = 5;\nprint(n(1));\n", "\
+error: `n` is not a function or built-in function
+  --> /dev/stdin:2:7
+  |
+2 | print(n(1));
+  |       ^^^^ `n` is i32
+");
+}
+
+#[test]
+fn syntax_errors() {
+    assert_fails("let f = |x -> x;\n", "\
+error: expected ',' or '|' in the argument list of a function, found `->`
+  --> /dev/stdin:1:12
+  |
+1 | let f = |x -> x;
+  |            ^^ expected ',' or '|' in the argument list of a function
+
+error: expected the body of the lambda, found `;`
+  --> /dev/stdin:1:16
+  |
+1 | let f = |x -> x;
+  |                ^ expected the body of the lambda
+");
+}
@@ -0,0 +1,40 @@
+#![allow(clippy::needless_return)]
+mod common;
+
+use common::*;
+
+#[test]
+fn comments_are_skipped() {
+    assert_prints("// line\n# hash\n/* block */ print(1); // trailing\nprint(/**/ 2);\n", "1\n2");
+}
+
+#[test]
+fn block_comments_nest() {
+    assert_prints("/* outer /* inner */ still a comment */ print(1);", "1");
+    assert_fails("print(1);\n/* open /* nested */\n", "\
+error: unterminated block comment
+  --> /dev/stdin:2:1
+  |
+2 | /* open /* nested */
+  | ^^^^^^^^^^^^^^^^^^^^ invalid token
+");
+}
+
+#[test]
+fn doc_comments_are_tokens() {
+    assert_eq!(token_kinds("/// a\n////  not doc\n/// b\nfn f() {}"), vec![
+        "DOC_COMMENT(\"a\")",
+        "DOC_COMMENT(\"b\")",
+        "KEYWORD(\"fn\")",
+        "IDENTIFIER(ID#f)",
+        "OPERATOR(\"(\")",
+        "OPERATOR(\")\")",
+        "OPERATOR(\"{\")",
+        "OPERATOR(\"}\")",
+    ]);
+}
+
+#[test]
+fn hash_starts_an_attribute_only_before_a_bracket() {
+    assert_eq!(token_kinds("# comment\n#[derive(Copy)]")[..2], ["OPERATOR(\"#[\")", "IDENTIFIER(ID#derive)"]);
+}
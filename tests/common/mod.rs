@@ -0,0 +1,66 @@
+#![allow(dead_code, clippy::needless_return)]
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Runs the interpreter with `args` on `source` given on stdin, returning its
+/// exit status, stdout and stderr. Diagnostics name the file `/dev/stdin`.
+pub fn run_with(args: &[&str], source: impl AsRef<[u8]>) -> (bool, String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_alg_lang"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("cannot start the interpreter");
+    child.stdin.take().unwrap().write_all(source.as_ref()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    return (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    );
+}
+
+/// The kinds of the tokens of `source` dumped by `--tokens`, without their
+/// spans and the EOF
+pub fn token_kinds(source: &str) -> Vec<String> {
+    let (ok, stdout, stderr) = run_with(&["--tokens"], source);
+    assert!(ok, "{}", stderr);
+    let mut kinds: Vec<String> = stdout.lines().map(|x| x.split_once(' ').unwrap().1.to_string()).collect();
+    kinds.pop();
+    return kinds;
+}
+
+pub fn run(source: &str) -> (bool, String, String) {
+    return run_with(&[], source);
+}
+
+/// `print` separates and ends its values with spaces, and diagnostics end
+/// with a blank line, which are not compared
+fn trim_lines(s: &str) -> String {
+    return s.lines().map(|x| x.trim_end()).collect::<Vec<_>>().join("\n").trim_end().to_string();
+}
+
+/// Asserts that `source` runs without diagnostics and prints the lines `expected`
+pub fn assert_prints(source: &str, expected: &str) {
+    let (ok, stdout, stderr) = run(source);
+    assert!(ok, "{} failed:\n{}", source, stderr);
+    assert_eq!(stderr, "", "diagnostics of {}", source);
+    assert_eq!(trim_lines(&stdout), expected, "output of {}", source);
+}
+
+/// Asserts that `source` fails, rendering exactly the diagnostics `expected`
+pub fn assert_fails(source: &str, expected: &str) {
+    let (ok, _, stderr) = run(source);
+    assert!(!ok, "{} did not fail", source);
+    assert_eq!(trim_lines(&stderr), trim_lines(expected), "diagnostics of {}", source);
+}
+
+/// Asserts that `source` fails after printing the lines `stdout`, rendering
+/// exactly the diagnostics `expected`
+pub fn assert_prints_then_fails(source: &str, stdout: &str, expected: &str) {
+    let (ok, out, stderr) = run(source);
+    assert!(!ok, "{} did not fail", source);
+    assert_eq!(trim_lines(&out), stdout, "output of {}", source);
+    assert_eq!(trim_lines(&stderr), trim_lines(expected), "diagnostics of {}", source);
+}
@@ -0,0 +1,137 @@
+#![allow(clippy::needless_return)]
+mod common;
+
+use common::*;
+
+#[test]
+fn let_and_let_mut() {
+    assert_prints("\
+let a = 1;
+let mut b: u8 = 2;
+b = b + 1u8;
+let c: f32 = 1.5;
+let d;
+d = \"set\";
+let a = \"shadowed\";
+print(a, b, c, d);
+", "shadowed 3 1.5 set");
+    assert_prints("let b: byte = 0x6C; print(b);", "108");
+}
+
+#[test]
+fn immutable_variables_are_assigned_once() {
+    assert_fails("let x;\nx = 1;\nx = 2;\n", "\
+error: cannot assign twice to immutable variable `x`
+  --> /dev/stdin:3:1
+  |
+3 | x = 2;
+  | ^^^^^ cannot assign twice to immutable variable
+  = note: consider making this binding mutable: `mut x`
+");
+}
+
+#[test]
+fn uninitialized_and_undeclared_variables() {
+    assert_fails("let x;\nprint(x);\n", "\
+error: used binding `x` isn't initialized
+  --> /dev/stdin:2:7
+  |
+2 | print(x);
+  |       ^ `x` used here but it isn't initialized
+");
+    assert_fails("y = 1;\n", "\
+error: cannot assign to undeclared variable `y`
+  --> /dev/stdin:1:1
+  |
+1 | y = 1;
+  | ^^^^^ not declared in this scope
+  = note: declare it first with `let y = ...`
+");
+}
+
+#[test]
+fn initializers_have_the_declared_type() {
+    assert_fails("let b: u8 = 256;\n", "\
+error: literal out of range for u8
+  --> /dev/stdin:1:13
+  |
+1 | let b: u8 = 256;
+  |             ^^^ the range of u8 is 0..=255
+  |        -- expected due to this type
+");
+}
+
+#[test]
+fn negated_literals_have_the_declared_type() {
+    assert_prints("let a: i8 = -128;\nlet b: f32 = -1.5;\nlet t: (i8, i64) = (-1, -3000000000);\nprint(a, b, t);", "-128 -1.5 (-1, -3000000000)");
+    assert_fails("let a: u8 = -1;\n", "\
+error: literal out of range for u8
+  --> /dev/stdin:1:13
+  |
+1 | let a: u8 = -1;
+  |             ^^ the range of u8 is 0..=255
+  |        -- expected due to this type
+");
+}
+
+#[test]
+fn negated_literals_are_typed_by_their_value() {
+    assert_prints("let x = -2147483648;\nlet y: i32 = x;\nprint(y);", "-2147483648");
+    assert_fails("let x = -2147483649;\nlet y: i32 = x;\n", "\
+error: mismatched types: expected i32, found i64
+  --> /dev/stdin:2:14
+  |
+2 | let y: i32 = x;
+  |              ^ expected i32, found i64
+  |        --- expected due to this type
+");
+}
+
+#[test]
+fn assignments_have_the_declared_type() {
+    assert_fails("let mut b: byte = 1;\nb = \"hello\";\n", "\
+error: mismatched types: expected u8, found ref str
+  --> /dev/stdin:2:5
+  |
+2 | b = \"hello\";
+  |     ^^^^^^^ expected u8, found ref str
+  | - expected due to the type of `b`
+");
+    assert_fails("let mut b: u8 = 1;\nb = 300;\n", "\
+error: literal out of range for u8
+  --> /dev/stdin:2:5
+  |
+2 | b = 300;
+  |     ^^^ the range of u8 is 0..=255
+  | - expected due to the type of `b`
+");
+    assert_fails("let mut n = 1;\nn = \"one\";\n", "\
+error: mismatched types: expected i32, found ref str
+  --> /dev/stdin:2:5
+  |
+2 | n = \"one\";
+  |     ^^^^^ expected i32, found ref str
+  | - expected due to the type of `n`
+");
+}
+
+#[test]
+fn assignments_of_unknown_type_are_checked_when_running() {
+    assert_prints_then_fails("\
+fn set(v) {
+    let mut b: u8 = 1;
+    b = v;
+    print(b);
+}
+set(7u8);
+set(\"hello\");
+", "7", "\
+error: mismatched types: expected u8, found ref str
+  --> /dev/stdin:3:9
+  |
+2 |     let mut b: u8 = 1;
+  |                -- expected due to this type
+3 |     b = v;
+  |         ^ expected u8
+");
+}
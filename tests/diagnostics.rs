@@ -0,0 +1,107 @@
+#![allow(clippy::needless_return)]
+mod common;
+
+use common::*;
+
+#[test]
+fn primary_and_secondary_labels_on_one_line() {
+    assert_fails("fn f() -> int { return \"s\"; }", "\
+error: mismatched types: expected i32, found ref str
+  --> /dev/stdin:1:24
+  |
+1 | fn f() -> int { return \"s\"; }
+  |                        ^^^ expected i32, found ref str
+  |           --- expected due to this return type
+");
+}
+
+#[test]
+fn labels_are_shown_in_source_order() {
+    assert_fails("fn f(a: int, b: int) {}\nf(1);\n", "\
+error: function `f` takes 2 arguments but 1 was supplied
+  --> /dev/stdin:2:1
+  |
+1 | fn f(a: int, b: int) {}
+  |      -------------- parameters declared here
+2 | f(1);
+  | ^^^^ expected 2 arguments
+");
+}
+
+#[test]
+fn gutter_fits_the_widest_line_number() {
+    assert_fails("fn f(a: int, b: int) {}\n\n\n\n\n\n\n\n\n\nf(1);\n", "\
+error: function `f` takes 2 arguments but 1 was supplied
+   --> /dev/stdin:11:1
+   |
+ 1 | fn f(a: int, b: int) {}
+   |      -------------- parameters declared here
+11 | f(1);
+   | ^^^^ expected 2 arguments
+");
+}
+
+#[test]
+fn notes() {
+    assert_fails("let x = 1;\nx = 2;\n", "\
+error: cannot assign twice to immutable variable `x`
+  --> /dev/stdin:2:1
+  |
+2 | x = 2;
+  | ^^^^^ cannot assign twice to immutable variable
+  = note: consider making this binding mutable: `mut x`
+");
+}
+
+#[test]
+fn diagnostics_are_sorted_by_position() {
+    // the `impl` is checked before the statements, but reported after them
+    assert_fails("let a: ref str = 1;\nstruct S {}\ninterface I { fn f(self); }\nimpl I for S {}\n", "\
+error: mismatched types: expected ref str, found integer
+  --> /dev/stdin:1:18
+  |
+1 | let a: ref str = 1;
+  |                  ^ expected ref str, found integer
+  |        ------- expected due to this type
+
+error: not all methods of `I` are implemented: `f`
+  --> /dev/stdin:4:12
+  |
+3 | interface I { fn f(self); }
+  |               ----------- `f` declared here
+4 | impl I for S {}
+  |            ^ missing `f`
+");
+}
+
+#[test]
+fn warnings_do_not_stop_the_program() {
+    let (ok, stdout, stderr) = run("/// lost\nlet x = 1;\nprint(x);\n");
+    assert!(ok);
+    assert_eq!(stdout, "1 \n");
+    assert_eq!(stderr, "\
+warning: unused doc comment
+  --> /dev/stdin:1:1
+  |
+1 | /// lost
+  | ^^^^^^^^ doc comments are only attached to functions and types
+
+");
+}
+
+#[test]
+fn unreadable_sources_are_reported() {
+    let (ok, _, stderr) = run_with(&[], b"print(1);\xff\n");
+    assert!(!ok);
+    assert_eq!(stderr, "\
+error: `/dev/stdin` is not valid UTF-8
+  --> /dev/stdin:1:10
+  |
+1 | print(1);\u{FFFD}
+  |          ^ invalid UTF-8 here
+
+");
+    let (ok, _, stderr) = run_with(&["-i", "missing.alg"], "");
+    assert!(!ok);
+    assert!(stderr.starts_with("error: cannot read `missing.alg`: "), "{}", stderr);
+}
@@ -0,0 +1,179 @@
+#![allow(clippy::needless_return)]
+mod common;
+
+use common::*;
+
+const SHAPE: &str = "enum S { A(int), B(int, int), C }\n";
+
+#[test]
+fn variants_carry_payloads() {
+    assert_prints("\
+enum Shape { Circle(f64), Rect(f64, f64), Empty }
+fn area(s: Shape) -> f64 {
+    return match s {
+        Shape::Circle(r) => 3.0 * r * r,
+        Shape::Rect(w, h) => w * h,
+        Shape::Empty => 0.0,
+    };
+}
+print(area(Shape::Circle(1.0)), area(Shape::Rect(2.0, 3.5)), area(Shape::Empty));
+print(Shape::Rect(1.0, 2.0));
+", "3 7 0\nShape::Rect(1, 2)");
+    assert_prints("\
+enum O { Some(int), None }
+enum W { X(O) }
+let w = W::X(O::Some(4));
+match w {
+    W::X(O::Some(n)) if n > 5 => print(\"big\", n),
+    W::X(O::Some(n)) => print(\"small\", n),
+    W::X(O::None) => print(\"none\"),
+}
+", "small 4");
+}
+
+#[test]
+fn literal_binding_tuple_and_guard_patterns() {
+    assert_prints("\
+let x = 7;
+let d = match x {
+    0 => \"zero\",
+    n if n < 0 => \"negative\",
+    1 => \"one\",
+    _ => \"big\",
+};
+print(d);
+match (1, \"a\") {
+    (0, _) => print(\"first\"),
+    (n, s) => print(n, s),
+}
+", "big\n1 a");
+}
+
+#[test]
+fn matches_must_be_exhaustive() {
+    assert_fails(&format!("{}let s = S::C;\nmatch s {{\n    S::A(x) => print(x),\n    S::C => print(0),\n}}\n", SHAPE), "\
+error: non-exhaustive patterns: `S::B(_, _)` not covered
+  --> /dev/stdin:3:7
+  |
+3 | match s {
+  |       ^ pattern `S::B(_, _)` not covered
+  = note: add an arm for it, or a `_` arm matching anything
+");
+    assert_fails("let x = 3;\nmatch x {\n    0 => print(0),\n    n if n > 0 => print(n),\n}\n", "\
+error: non-exhaustive patterns: `_` not covered
+  --> /dev/stdin:2:7
+  |
+2 | match x {
+  |       ^ pattern `_` not covered
+  = note: add an arm for it, or a `_` arm matching anything
+");
+    assert_fails("let b = (1, 2);\nmatch b {\n    (0, y) => print(y),\n}\n", "\
+error: non-exhaustive patterns: `(_, _)` not covered
+  --> /dev/stdin:2:7
+  |
+2 | match b {
+  |       ^ pattern `(_, _)` not covered
+  = note: add an arm for it, or a `_` arm matching anything
+");
+}
+
+#[test]
+fn constructing_variants() {
+    assert_fails(&format!("{}let s = S::B(1);\n", SHAPE), "\
+error: variant `S::B` has 2 fields but 1 was supplied
+  --> /dev/stdin:2:9
+  |
+1 | enum S { A(int), B(int, int), C }
+  |                  ----------- variant declared here
+2 | let s = S::B(1);
+  |         ^^^^^^^ expected 2 arguments
+");
+    assert_fails(&format!("{}let s = S::A(1, 2);\n", SHAPE), "\
+error: variant `S::A` has 1 field but 2 were supplied
+  --> /dev/stdin:2:9
+  |
+1 | enum S { A(int), B(int, int), C }
+  |          ------ variant declared here
+2 | let s = S::A(1, 2);
+  |         ^^^^^^^^^^ expected 1 argument
+");
+    assert_fails(&format!("{}let s = S::A(\"x\");\n", SHAPE), "\
+error: mismatched types: expected i32, found ref str
+  --> /dev/stdin:2:14
+  |
+1 | enum S { A(int), B(int, int), C }
+  |            --- expected due to the type of this field
+2 | let s = S::A(\"x\");
+  |              ^^^ expected i32, found ref str
+");
+    assert_fails(&format!("{}let s = S::D;\n", SHAPE), "\
+error: no variant named `D` in enum `S`
+  --> /dev/stdin:2:9
+  |
+2 | let s = S::D;
+  |         ^^^^ variant not found
+");
+    assert_fails("enum E { A, A }\n", "\
+error: variant `A` is already declared
+  --> /dev/stdin:1:13
+  |
+1 | enum E { A, A }
+  |             ^ variant already declared
+  |          - first declared here
+");
+}
+
+#[test]
+fn patterns_are_checked() {
+    assert_fails(&format!("{}match S::C {{\n    S::B(x) => print(x),\n    _ => {{}}\n}}\n", SHAPE), "\
+error: this pattern has 1 field, but the variant `S::B` has 2 fields
+  --> /dev/stdin:3:5
+  |
+1 | enum S { A(int), B(int, int), C }
+  |                  ----------- variant declared here
+3 |     S::B(x) => print(x),
+  |     ^^^^^^^ expected 2 fields
+");
+    assert_fails("let x = 1;\nmatch x {\n    \"a\" => print(1),\n    2.5 => print(1),\n    _ => {}\n}\n", "\
+error: mismatched types: expected i32, found ref str
+  --> /dev/stdin:3:5
+  |
+3 |     \"a\" => print(1),
+  |     ^^^ expected i32, found ref str
+
+error: mismatched types: expected i32, found float
+  --> /dev/stdin:4:5
+  |
+4 |     2.5 => print(1),
+  |     ^^^ expected i32, found float
+");
+}
+
+#[test]
+fn variants_are_not_compared() {
+    assert_fails("enum E { A }\nprint(E::A == E::A);\n", "\
+error: cannot compare E with `==`
+  --> /dev/stdin:2:7
+  |
+2 | print(E::A == E::A);
+  |       ^^^^ the variants of an enum cannot be compared
+  = note: tell them apart with `match` instead
+");
+}
+
+#[test]
+fn syntax_errors() {
+    assert_fails("match 1 {\n    1 -> print(1),\n}\nenum E { A(int }\n", "\
+error: expected '=>' after the pattern, found `->`
+  --> /dev/stdin:2:7
+  |
+2 |     1 -> print(1),
+  |       ^^ expected '=>' after the pattern
+
+error: expected ',' or ')' in the fields of a variant, found `}`
+  --> /dev/stdin:4:16
+  |
+4 | enum E { A(int }
+  |                ^ expected ',' or ')' in the fields of a variant
+");
+}
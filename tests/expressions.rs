@@ -0,0 +1,70 @@
+#![allow(clippy::needless_return)]
+mod common;
+
+use common::*;
+
+#[test]
+fn boolean_literals() {
+    assert_prints("print(true, false, !true, true && false, true || false);", "1 0 0 0 1");
+    assert_prints("let b: i32 = true; if b { print(\"yes\"); }", "yes");
+    assert_prints(
+        "match 1 < 2 { true => print(\"less\"), false => print(\"not less\"), _ => {} }",
+        "less",
+    );
+}
+
+#[test]
+fn boolean_literals_are_not_names() {
+    let (ok, _, stderr) = run("let true = 1;");
+    assert!(!ok);
+    assert!(stderr.contains("expected a name after 'let', found keyword `true`"), "{}", stderr);
+}
+
+#[test]
+fn precedence_and_associativity() {
+    assert_prints("print(2**3**2, -2**2, (-2)**2);", "512 -4 4");
+    assert_prints("print(1 + 2 * 3, (1 + 2) * 3, 10 - 4 - 3, 2 * 3 % 4);", "7 9 3 2");
+    assert_prints("print(1 | 2 & 3, 1 + 2 << 1, !1 || 1 && 0, (1 < 2) == 1);", "3 6 0 1");
+    assert_prints("print(7 / 2, -7 / 2, -7 % 3, 1 as f64 / 2 as f64);", "3 -3 -1 0.5");
+}
+
+#[test]
+fn comparisons_cannot_be_chained() {
+    assert_fails("print(1 < 2 < 3);", "\
+error: comparison operators cannot be chained
+  --> /dev/stdin:1:13
+  |
+1 | print(1 < 2 < 3);
+  |             ^ second comparison
+  |       ----- first comparison
+  = note: split the comparison with `&&`, as in `a < b && b < c`
+");
+}
+
+#[test]
+fn operand_errors() {
+    assert_fails("print(2147483647 + 1);", "\
+error: arithmetic overflow: `2147483647 + 1` does not fit in i32
+  --> /dev/stdin:1:7
+  |
+1 | print(2147483647 + 1);
+  |       ^^^^^^^^^^^^^^ the result overflows i32
+");
+    assert_fails("print(2 ** -1);", "\
+error: cannot raise an integer to a negative power
+  --> /dev/stdin:1:7
+  |
+1 | print(2 ** -1);
+  |       ^^^^^^^ negative exponent
+  |            -- this is -1
+");
+    assert_fails("print(1 / 0.0);", "\
+error: cannot apply `/` to i32 and f64
+  --> /dev/stdin:1:7
+  |
+1 | print(1 / 0.0);
+  |       ^^^^^^^ mismatched operand types
+  |       - i32
+  |           --- f64
+");
+}
@@ -0,0 +1,102 @@
+#![allow(clippy::needless_return)]
+mod common;
+
+use common::*;
+
+#[test]
+fn counted_loops_include_both_bounds() {
+    assert_prints("for i = 0 to 2 { print(i); }\nfor i = 2 downto 0 { print(i); }", "0\n1\n2\n2\n1\n0");
+    assert_prints("for i = 5 to 1 { print(i); }\nprint(\"none\");", "none");
+    assert_prints("for i = 0 to 1 {\n    for j = i downto 0 {\n        print(i, j);\n    }\n}", "0 0\n1 1\n1 0");
+}
+
+#[test]
+fn steps() {
+    assert_prints("for i = 0 to 10 step 3 { print(i); }\nfor i = 10 downto 0 step 4 { print(i); }", "0\n3\n6\n9\n10\n6\n2");
+    assert_prints("let n = 3;\nfor i = 1 to n * 2 step n { print(i); }", "1\n4");
+    assert_fails("for i = 0 to 3 step -1 { print(i); }\n", "\
+error: the step of a `for` loop must be positive
+  --> /dev/stdin:1:21
+  |
+1 | for i = 0 to 3 step -1 { print(i); }
+  |                     ^^ this is -1
+  = note: count down with `downto` instead of a negative step
+");
+    assert_fails("for i = 0 to \"a\" { }\n", "\
+error: the end of a `for` loop must be an integer, found ref str
+  --> /dev/stdin:1:14
+  |
+1 | for i = 0 to \"a\" { }
+  |              ^^^ expected an integer
+");
+}
+
+#[test]
+fn iterating_over_values() {
+    assert_prints("for x in [1, 2] { print(x); }\nfor c in \"ab\" { print(c); }", "1\n2\na\nb");
+    assert_prints("let a = [1, 2];\nlet r = &a;\nfor x in r { print(x); }", "1\n2");
+    assert_prints("for (a, b) in [(1, 2), (3, 4)] { print(a + b); }", "3\n7");
+    assert_fails("for x in 5 { }\n", "\
+error: cannot iterate over a value of type i32
+  --> /dev/stdin:1:10
+  |
+1 | for x in 5 { }
+  |          ^ not iterable
+");
+    assert_fails("let a: int[] = [1, 2];\nfor x in a { }\n", "\
+error: cannot iterate over a C-like array
+  --> /dev/stdin:2:10
+  |
+2 | for x in a { }
+  |          ^ the length of this array is unknown
+  = note: count over its indexes instead, as in `for i = 0 to n - 1`
+");
+}
+
+#[test]
+fn the_variable_is_scoped_to_the_body() {
+    assert_prints("let i = 7;\nfor i = 0 to 1 { }\nprint(i);", "7");
+    assert_fails("for i = 0 to 2 { }\nprint(i);\n", "\
+error: cannot find value `i` in this scope
+  --> /dev/stdin:2:7
+  |
+2 | print(i);
+  |       ^ not found in this scope
+");
+}
+
+#[test]
+fn the_variable_is_immutable_unless_declared_mut() {
+    assert_prints("for mut i = 0 to 2 {\n    i = i * 10;\n    print(i);\n}", "0\n10\n20");
+    assert_fails("for i = 0 to 2 { i = 5; }\n", "\
+error: cannot assign twice to immutable variable `i`
+  --> /dev/stdin:1:18
+  |
+1 | for i = 0 to 2 { i = 5; }
+  |                  ^^^^^ cannot assign twice to immutable variable
+  = note: consider making this binding mutable: `mut i`
+");
+}
+
+#[test]
+fn syntax_errors() {
+    assert_fails("for i = 0 { }\nfor i in 0 to 3 { }\nfor i: u8 = 1 to 2 { }\n", "\
+error: expected 'to' or 'downto' after the start of the loop, found `{`
+  --> /dev/stdin:1:11
+  |
+1 | for i = 0 { }
+  |           ^ expected 'to' or 'downto' after the start of the loop
+
+error: expected '{' before the body of 'for', found keyword `to`
+  --> /dev/stdin:2:12
+  |
+2 | for i in 0 to 3 { }
+  |            ^^ expected '{' before the body of 'for'
+
+error: expected '=' or 'in' after the loop variable, found `:`
+  --> /dev/stdin:3:6
+  |
+3 | for i: u8 = 1 to 2 { }
+  |      ^ expected '=' or 'in' after the loop variable
+");
+}
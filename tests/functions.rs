@@ -0,0 +1,140 @@
+#![allow(clippy::needless_return)]
+mod common;
+
+use common::*;
+
+#[test]
+fn typed_signatures() {
+    assert_prints("\
+fn add(a: int, b: int) -> int { return a + b; }
+fn greet(name: ref str) { print(\"hi\", name); }
+fn scale(p: &int, k: int) { *p = *p * k; }
+print(add(2, 3));
+greet(\"bob\");
+let mut x = 3;
+scale(&x, 2);
+print(x);
+", "5\nhi bob\n6");
+}
+
+#[test]
+fn functions_without_return_type_return_void() {
+    assert_prints("fn greet() { print(\"hi\"); }\nprint(greet());", "hi\nvoid");
+    assert_fails("fn f() { return 1; }\n", "\
+error: mismatched types: `f` returns void but a value is returned
+  --> /dev/stdin:1:17
+  |
+1 | fn f() { return 1; }
+  |                 ^ unexpected return value
+  |        - add a return type here, as in `-> int`
+");
+}
+
+#[test]
+fn functions_are_values() {
+    assert_prints("\
+print(twice(inc, 1));
+fn twice(f: fn(int) -> int, x: int) -> int { return f(f(x)); }
+fn inc(x: int) -> int { return x + 1; }
+", "3");
+}
+
+#[test]
+fn calls_are_checked_before_running() {
+    assert_fails("\
+fn add(a: int, b: int) -> int { return a + b; }
+print(\"before\");
+print(add(1));
+add(1, \"two\");
+", "\
+error: function `add` takes 2 arguments but 1 was supplied
+  --> /dev/stdin:3:7
+  |
+1 | fn add(a: int, b: int) -> int { return a + b; }
+  |        -------------- parameters declared here
+3 | print(add(1));
+  |       ^^^^^^ expected 2 arguments
+
+error: mismatched types: expected i32, found ref str
+  --> /dev/stdin:4:8
+  |
+1 | fn add(a: int, b: int) -> int { return a + b; }
+  |                   --- parameter `b` declared here
+4 | add(1, \"two\");
+  |        ^^^^^ expected i32, found ref str
+");
+    assert_fails("fn f(x: u8) {}\nf(300);\n", "\
+error: literal out of range for u8
+  --> /dev/stdin:2:3
+  |
+1 | fn f(x: u8) {}
+  |         -- parameter `x` declared here
+2 | f(300);
+  |   ^^^ the range of u8 is 0..=255
+");
+}
+
+#[test]
+fn return_types_are_checked() {
+    assert_fails("fn f() -> int { return \"s\"; }\n", "\
+error: mismatched types: expected i32, found ref str
+  --> /dev/stdin:1:24
+  |
+1 | fn f() -> int { return \"s\"; }
+  |                        ^^^ expected i32, found ref str
+  |           --- expected due to this return type
+");
+    assert_prints_then_fails("\
+fn f(x: int) -> int {
+    if x > 0 {
+        return 1;
+    }
+}
+print(f(1));
+print(f(0));
+", "1", "\
+error: mismatched types: `f` returned void but is declared to return i32
+  --> /dev/stdin:7:7
+  |
+1 | fn f(x: int) -> int {
+  |                 --- expected due to this return type
+7 | print(f(0));
+  |       ^^^^ returned void
+");
+}
+
+#[test]
+fn parameters_are_bound_once() {
+    assert_fails("fn f(a: int, a: int) {}\nlet g = |x, (y, x)| x;\n", "\
+error: identifier `a` is bound more than once in this parameter list
+  --> /dev/stdin:1:14
+  |
+1 | fn f(a: int, a: int) {}
+  |              ^ used as parameter more than once
+  |      - first bound here
+
+error: identifier `x` is bound more than once in this parameter list
+  --> /dev/stdin:2:17
+  |
+2 | let g = |x, (y, x)| x;
+  |                 ^ used as parameter more than once
+  |          - first bound here
+");
+}
+
+#[test]
+fn syntax_errors() {
+    assert_fails("fn f(a int) {}\nfn g() -> {}\n", "\
+error: expected ',' or ')' in the argument list of a function, found identifier `int`
+  --> /dev/stdin:1:8
+  |
+1 | fn f(a int) {}
+  |        ^^^ expected ',' or ')' in the argument list of a function
+
+error: expected a return type after '->', found `{`
+  --> /dev/stdin:2:11
+  |
+2 | fn g() -> {}
+  |           ^ expected a return type after '->'
+");
+}
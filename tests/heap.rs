@@ -0,0 +1,173 @@
+#![allow(clippy::needless_return)]
+mod common;
+
+use common::*;
+
+const FOO: &str = "struct Foo { x: int }\n";
+
+#[test]
+fn new_allocates_on_the_heap() {
+    assert_prints(&format!("\
+{}let foo2 = new(Foo(2));
+print(foo2.x, *foo2);
+foo2.x = 5;
+print(foo2.x);
+delete(foo2);
+print(foo2);
+", FOO), "2 Foo { x: 2 }\n5\nnull");
+    assert_prints("let h = new(7);\n*h = *h + 1;\nprint(*h);\nlet t = new((1, 2));\nprint(t.1);", "8\n2");
+    assert_prints("let a = new([1, 2, 3]);\nprint((*a)[1], a[2], len(*a));\nfor v in a { print(v); }", "2 3 3\n1\n2\n3");
+}
+
+#[test]
+fn assigned_values_take_the_allocated_type() {
+    assert_prints("let r = new(0u8);\n*r = 5;\nlet x: u8 = *r;\nprint(x);", "5");
+    assert_fails("let r = new(0u8);\n*r = 256;\n", "\
+error: literal out of range for u8
+  --> /dev/stdin:2:6
+  |
+2 | *r = 256;
+  |      ^^^ the range of u8 is 0..=255
+");
+}
+
+#[test]
+fn dereferencing_copies() {
+    assert_prints("#[derive(Copy)]\nstruct P { a: int }\nlet r = new(P(1));\nlet mut q = *r;\nq.a = 2;\nprint(r.a, q.a);", "1 2");
+    assert_fails(&format!("{}let r = new(Foo(1));\nlet q = *r;\n", FOO), "\
+error: cannot copy `*r`, as `Foo` does not implement `Copy`
+  --> /dev/stdin:3:9
+  |
+3 | let q = *r;
+  |         ^^ copied here
+  = note: derive it with `#[derive(Copy)]`
+");
+}
+
+#[test]
+fn references_borrow_the_stack() {
+    assert_prints(&format!("{}let foo1 = Foo(1);\nlet r = &foo1;\nprint(r.x);", FOO), "1");
+    assert_prints("let mut n = 3;\nlet p = &n;\n*p = 4;\nprint(n, *p);", "4 4");
+    assert_prints("let x = 1;\nlet r = &x;\nlet x = 2;\nprint(*r, x);", "1 2");
+    assert_fails("let x = 5;\nlet r = &x;\n*r = 6;\n", "\
+error: cannot assign twice to immutable variable `x`
+  --> /dev/stdin:3:1
+  |
+3 | *r = 6;
+  | ^^^^^^ cannot assign twice to immutable variable
+  = note: consider making this binding mutable: `mut x`
+");
+}
+
+#[test]
+fn references_cannot_dangle() {
+    assert_fails("let mut r = new(0);\nif 1 {\n    let y = 5;\n    r = &y;\n}\nprint(*r);\n", "\
+error: use of a reference to `y` after it went out of scope
+  --> /dev/stdin:6:7
+  |
+4 |     r = &y;
+  |         -- borrowed here
+6 | print(*r);
+  |       ^^ this reference dangles
+");
+    assert_fails("fn f() -> &int {\n    let z = 3;\n    return &z;\n}\nlet r = f();\nprint(*r);\n", "\
+error: cannot return a reference to the local variable `z`
+  --> /dev/stdin:3:12
+  |
+3 |     return &z;
+  |            ^^ `z` is local to `f`
+5 | let r = f();
+  |         --- the reference would dangle after this call
+  = note: allocate the value with `new` to return a reference to it
+");
+}
+
+#[test]
+fn use_after_delete() {
+    assert_fails(&format!("{}let a = new(Foo(1));\ndelete(a);\nprint(a.x);\n", FOO), "\
+error: use of a null reference
+  --> /dev/stdin:4:7
+  |
+3 | delete(a);
+  | --------- set to null by this `delete`
+4 | print(a.x);
+  |       ^ this reference is null
+");
+    assert_fails(&format!("{}let a = new(Foo(1));\nlet b = a;\ndelete(a);\nprint(b.x);\n", FOO), "\
+error: use after delete
+  --> /dev/stdin:5:7
+  |
+4 | delete(a);
+  | --------- deleted here
+5 | print(b.x);
+  |       ^ this reference was deleted
+");
+    assert_fails("let a = new(1);\ndelete(a);\n*a = 3;\n", "\
+error: use of a null reference
+  --> /dev/stdin:3:1
+  |
+2 | delete(a);
+  | --------- set to null by this `delete`
+3 | *a = 3;
+  | ^^ this reference is null
+");
+}
+
+#[test]
+fn only_allocations_are_deleted_once() {
+    assert_fails(&format!("{}let a = new(Foo(1));\nlet b = a;\ndelete(a);\ndelete(b);\n", FOO), "\
+error: double delete
+  --> /dev/stdin:5:8
+  |
+4 | delete(a);
+  | --------- first deleted here
+5 | delete(b);
+  |        ^ this reference was already deleted
+");
+    assert_fails(&format!("{}let f = Foo(1);\ndelete(&f);\n", FOO), "\
+error: cannot delete a reference to a stack value
+  --> /dev/stdin:3:8
+  |
+3 | delete(&f);
+  |        ^^ this refers to the variable `f`
+  |        -- borrowed here
+  = note: only references returned by `new` can be deleted
+");
+    assert_fails("delete(5);\n", "\
+error: cannot delete a value of type i32
+  --> /dev/stdin:1:8
+  |
+1 | delete(5);
+  |        ^ not a reference
+  = note: only references returned by `new` can be deleted
+");
+    assert_fails("let a = new(1);\ndelete(a, a);\n", "\
+error: function `delete` takes 1 argument but 2 were supplied
+  --> /dev/stdin:2:1
+  |
+2 | delete(a, a);
+  | ^^^^^^^^^^^^ expected 1 argument
+");
+}
+
+#[test]
+fn pointer_casts() {
+    assert_prints(&format!("{}let f = Foo(1);\nlet p = &f as *Foo;\nprint(p.x);\nlet q = new(Foo(2)) as *Foo;\nprint(q.x);\ndelete(q);", FOO), "1\n2");
+    assert_fails(&format!("{}let f = Foo(1);\nlet p = &f as *Foo;\ndelete(p);\n", FOO), "\
+error: cannot delete a reference to a stack value
+  --> /dev/stdin:4:8
+  |
+3 | let p = &f as *Foo;
+  |         -- borrowed here
+4 | delete(p);
+  |        ^ this refers to the variable `f`
+  = note: only references returned by `new` can be deleted
+");
+    assert_fails("let a = 1;\nprint(a as *int);\n", "\
+error: cannot cast i32 as *i32
+  --> /dev/stdin:2:7
+  |
+2 | print(a as *int);
+  |       ^^^^^^^^^ invalid cast
+");
+}
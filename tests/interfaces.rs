@@ -0,0 +1,200 @@
+#![allow(clippy::needless_return)]
+mod common;
+
+use common::*;
+
+const SHAPES: &str = "\
+interface Shape {
+    fn area(self) -> int;
+    fn describe(self) { print(\"area\", self.area()); }
+}
+struct Sq { s: int }
+struct Rect { w: int, h: int }
+impl Shape for Sq { fn area(self) -> int { return self.s * self.s; } }
+impl Shape for Rect { fn area(self) -> int { return self.w * self.h; } }
+";
+
+#[test]
+fn methods_are_dispatched_on_the_struct() {
+    assert_prints(&format!("{}\
+fn total(a: Shape, b: Shape) -> int {{ return a.area() + b.area(); }}
+let s = Sq(3);
+let r = Rect(2, 5);
+print(total(s, r));
+r.describe();
+for x in [Sq(1), Sq(2)] {{ x.describe(); }}
+", SHAPES), "19\narea 10\narea 1\narea 4");
+}
+
+#[test]
+fn methods_are_dispatched_through_references() {
+    assert_prints(&format!("{}\
+fn show(x: &Shape) {{ print(x.area()); }}
+let q = Sq(2);
+let r = Rect(2, 5);
+show(&q);
+show(&r);
+show(new(Sq(3)));
+let s: &Shape = &r;
+let p: *Shape = new(Rect(1, 4)) as *Rect;
+print(s.area(), p.area());
+", SHAPES), "4\n10\n9\n10 4");
+    assert_fails("interface S { fn f(self); }\nstruct Q { s: int }\nfn g(x: &S) { x.f(); }\nlet q = Q(1);\ng(&q);\n", "\
+error: mismatched types: expected ref S, found ref Q
+  --> /dev/stdin:5:3
+  |
+3 | fn g(x: &S) { x.f(); }
+  |         -- parameter `x` declared here
+5 | g(&q);
+  |   ^^ expected ref S, found ref Q
+");
+}
+
+#[test]
+fn structs_must_implement_the_interface() {
+    assert_fails("interface S { fn f(self); }\nstruct Q { s: int }\nfn g(x: S) { x.f(); }\ng(Q(1));\n", "\
+error: mismatched types: expected S, found Q
+  --> /dev/stdin:4:3
+  |
+3 | fn g(x: S) { x.f(); }
+  |         - parameter `x` declared here
+4 | g(Q(1));
+  |   ^^^^ expected S, found Q
+");
+    assert_fails("interface S { fn f(self); }\nfn g(x: S) { x.nope(); }\n", "\
+error: no method named `nope` found for interface `S`
+  --> /dev/stdin:2:14
+  |
+2 | fn g(x: S) { x.nope(); }
+  |              ^^^^^^ method not found
+");
+}
+
+#[test]
+fn impls_are_checked_against_the_interface() {
+    let interface = "interface S {\n    fn area(self) -> int;\n    fn name(self) -> int;\n}\nstruct Q { s: int }\n";
+    assert_fails(&format!("{}impl S for Q {{\n    fn area(self) -> int {{ return 1; }}\n}}\n", interface), "\
+error: not all methods of `S` are implemented: `name`
+  --> /dev/stdin:6:12
+  |
+3 |     fn name(self) -> int;
+  |     --------------------- `name` declared here
+6 | impl S for Q {
+  |            ^ missing `name`
+");
+    assert_fails(&format!("\
+{}impl S for Q {{
+    fn area(self, k: int) -> int {{ return k; }}
+    fn name(self) -> int {{ return 0; }}
+    fn extra(self) {{}}
+}}
+", interface), "\
+error: method `area` has 2 parameters but the declaration in interface `S` has 1 parameter
+  --> /dev/stdin:7:5
+  |
+2 |     fn area(self) -> int;
+  |     --------------------- declared here
+7 |     fn area(self, k: int) -> int { return k; }
+  |     ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ expected 1 parameter
+
+error: method `extra` is not a member of interface `S`
+  --> /dev/stdin:9:5
+  |
+9 |     fn extra(self) {}
+  |     ^^^^^^^^^^^^^^^^^ not a member of `S`
+");
+    assert_fails(&format!("{}impl T for Q {{}}\n", interface), "\
+error: cannot find interface `T` in this scope
+  --> /dev/stdin:6:6
+  |
+6 | impl T for Q {}
+  |      ^ not an interface
+");
+    assert_fails(&format!("\
+{}impl S for Q {{
+    fn area(self) -> int {{ return 1; }}
+    fn name(self) -> int {{ return 0; }}
+}}
+impl S for Q {{}}
+", interface), "\
+error: conflicting implementations of `S` for `Q`
+   --> /dev/stdin:10:6
+   |
+10 | impl S for Q {}
+   |      ^ implemented again here
+");
+}
+
+#[test]
+fn derived_interfaces() {
+    assert_prints("\
+#[derive(Copy, Eq, Debug)]
+struct V { x: int, y: int }
+let a = V(1, 2);
+let mut b = a;
+b.x = 5;
+print(a, b, a == b, a == V(1, 2));
+print(a.debug());
+", "V { x: 1, y: 2 } V { x: 5, y: 2 } 0 1\nV { x: 1, y: 2 }");
+    assert_fails("struct P { x: int }\nprint(P(1) == P(1));\n", "\
+error: cannot compare P with `==`
+  --> /dev/stdin:2:7
+  |
+2 | print(P(1) == P(1));
+  |       ^^^^ P does not implement `Eq`
+  = note: implement `Eq` for it, or derive it with `#[derive(Eq)]`
+");
+}
+
+#[test]
+fn only_copy_structs_are_copied() {
+    assert_prints("struct P { x: int }\nlet a = P(1);\nlet b = &a;\nprint(b.x);", "1");
+    assert_fails("struct P { x: int }\nlet a = P(1);\nlet b = a;\n", "\
+error: cannot copy `a`, as `P` does not implement `Copy`
+  --> /dev/stdin:3:9
+  |
+3 | let b = a;
+  |         ^ copied here
+  = note: derive it with `#[derive(Copy)]`, or take a reference with `&a`
+");
+}
+
+#[test]
+fn derive_errors() {
+    assert_fails("#[derive(Copy, Copy)]\nstruct P { x: int }\n", "\
+error: `Copy` is derived twice
+  --> /dev/stdin:1:16
+  |
+1 | #[derive(Copy, Copy)]
+  |                ^^^^ derived again here
+  |          ---- first derived here
+");
+    assert_fails("#[derive(Hash)]\nstruct P { x: int }\n", "\
+error: cannot derive `Hash`
+  --> /dev/stdin:1:10
+  |
+1 | #[derive(Hash)]
+  |          ^^^^ not a derivable interface
+  = note: the derivable interfaces are Copy, Eq, Debug
+");
+    assert_fails("enum E { A, B }\n#[derive(Eq)]\nstruct P { e: E }\n", "\
+error: cannot derive `Eq` for `P`
+  --> /dev/stdin:3:15
+  |
+2 | #[derive(Eq)]
+  |          -- derived here
+3 | struct P { e: E }
+  |               ^ `E` does not implement `Eq`
+  = note: the variants of an enum cannot be compared with `==`
+");
+    assert_fails("struct In { v: int }\n#[derive(Copy)]\nstruct P { i: In }\n", "\
+error: cannot derive `Copy` for `P`
+  --> /dev/stdin:3:15
+  |
+2 | #[derive(Copy)]
+  |          ---- derived here
+3 | struct P { i: In }
+  |               ^^ `In` does not implement `Copy`
+  = note: implement `Copy` for `In`, or derive it with `#[derive(Copy)]`
+");
+}
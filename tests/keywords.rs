@@ -0,0 +1,48 @@
+#![allow(clippy::needless_return)]
+mod common;
+
+use common::*;
+
+// keep in sync with `lexer::KEYWORDS`
+const KEYWORDS: [&str; 26] = [
+    "for", "if", "while", "loop", "until", "return", "continue", "break", "to", "downto", "fn", "else", "elif",
+    "let", "mut", "in", "step", "do", "struct", "class", "impl", "interface", "enum", "match", "true", "false",
+];
+
+#[test]
+fn keyword_prefixed_identifiers() {
+    for k in KEYWORDS.iter() {
+        let source = format!("let {k}x = 1; let {k}_y = 2; let {k}3 = 3; print({k}x, {k}_y, {k}3);", k = k);
+        assert_prints(&source, "1 2 3");
+    }
+}
+
+#[test]
+fn keyword_suffixed_identifiers() {
+    for k in KEYWORDS.iter() {
+        let source = format!("let x{k} = 1; let _{k} = 2; print(x{k}, _{k});", k = k);
+        assert_prints(&source, "1 2");
+    }
+}
+
+#[test]
+fn words_starting_with_keywords() {
+    assert_prints(
+        "let format = 1; let iffy = 2; let toggle = 3; let breakfast = 4; let elapsed = 5; let fnord = 6; let loops = 7; let returned = 8;
+         print(format, iffy, toggle, breakfast, elapsed, fnord, loops, returned);",
+        "1 2 3 4 5 6 7 8",
+    );
+}
+
+#[test]
+fn keywords_are_still_keywords() {
+    assert_prints(
+        "fn iffy(elsewhere) -> int { let mut r = 2; if elsewhere { r = 1; } else { r = 2; } return r; }
+         let mut whiley = 0; while whiley { whiley = 0; }
+         print(iffy(1), iffy(0));",
+        "1 2",
+    );
+    let (ok, _, stderr) = run("if = 1;");
+    assert!(!ok);
+    assert!(stderr.contains("expected a condition after 'if'"), "{}", stderr);
+}
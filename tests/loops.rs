@@ -0,0 +1,112 @@
+#![allow(clippy::needless_return)]
+mod common;
+
+use common::*;
+
+#[test]
+fn loop_breaks_with_a_value() {
+    assert_prints("\
+let mut i = 0;
+let x = loop {
+    i = i + 1;
+    if i == 3 {
+        break i * 10;
+    }
+};
+print(x);
+", "30");
+    assert_prints("let x = loop { break; };\nprint(x);", "void");
+}
+
+#[test]
+fn do_until_runs_at_least_once() {
+    assert_prints("let mut i = 0;\ndo {\n    i = i + 1;\n    print(i);\n} until i >= 2;", "1\n2");
+    assert_prints("let mut n = 0;\ndo { n = n + 1; } until 1;\nprint(n);", "1");
+    assert_prints("\
+let mut i = 0;
+do {
+    i = i + 1;
+    if i == 2 {
+        continue;
+    }
+    print(i);
+} until i >= 3;
+", "1\n3");
+}
+
+#[test]
+fn break_and_continue() {
+    assert_prints("for i = 0 to 4 {\n    if i % 2 == 0 {\n        continue;\n    }\n    print(i);\n}", "1\n3");
+    assert_prints("let mut i = 0;\nwhile 1 {\n    i = i + 1;\n    if i > 2 {\n        break;\n    }\n}\nprint(i);", "3");
+}
+
+#[test]
+fn labels() {
+    assert_prints("\
+'outer: for i = 0 to 2 {
+    for j = 0 to 2 {
+        if j == 1 {
+            continue 'outer;
+        }
+        if i == 2 {
+            break 'outer;
+        }
+        print(i, j);
+    }
+}
+", "0 0\n1 0");
+    assert_prints("'a: loop {\n    loop {\n        break 'a;\n    }\n}\nprint(\"out\");", "out");
+}
+
+#[test]
+fn jumps_need_a_loop() {
+    assert_fails("break;\nfor i = 0 to 1 {\n    continue 'nope;\n}\n", "\
+error: `break` outside of a loop
+  --> /dev/stdin:1:1
+  |
+1 | break;
+  | ^^^^^^ cannot `break` outside of a loop
+
+error: use of undeclared label `'nope`
+  --> /dev/stdin:3:14
+  |
+3 |     continue 'nope;
+  |              ^^^^^ no enclosing loop has this label
+");
+    assert_fails("fn f() {\n    continue;\n}\nloop {\n    f();\n}\n", "\
+error: `continue` outside of a loop
+  --> /dev/stdin:2:5
+  |
+2 |     continue;
+  |     ^^^^^^^^^ cannot `continue` outside of a loop
+");
+}
+
+#[test]
+fn break_values() {
+    assert_fails("while 1 {\n    break 5;\n}\n", "\
+error: `break` with a value from a `while` loop
+  --> /dev/stdin:2:11
+  |
+2 |     break 5;
+  |           ^ can only break with a value from `loop`
+");
+    assert_fails("let x = loop {\n    let y = match 1 { _ => { break 2; } };\n};\n", "\
+error: `break` cannot leave a `match` used as a value
+  --> /dev/stdin:2:30
+  |
+2 |     let y = match 1 { _ => { break 2; } };
+  |                              ^^^^^^^^ this would skip the value of the inner `match`
+");
+}
+
+#[test]
+fn labels_need_a_loop() {
+    assert_fails("'a: print(1);\n", "\
+error: expected a loop after the label, found identifier `print`
+  --> /dev/stdin:1:5
+  |
+1 | 'a: print(1);
+  |     ^^^^^ expected a loop after the label
+");
+}
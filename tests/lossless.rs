@@ -0,0 +1,45 @@
+#![allow(clippy::needless_return)]
+mod common;
+
+use common::*;
+use std::fs;
+
+/// Lexes `source` losslessly and prints it back
+fn reprint(source: &str) -> String {
+    let (ok, stdout, stderr) = run_with(&["--reprint"], source);
+    assert!(ok, "{}", stderr);
+    return stdout;
+}
+
+fn samples() -> Vec<(String, String)> {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/samples");
+    let mut samples = vec![];
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|x| x == "alg") {
+            let text = fs::read_to_string(&path).unwrap();
+            samples.push((path.display().to_string(), text));
+        }
+    }
+    assert!(!samples.is_empty());
+    return samples;
+}
+
+#[test]
+fn samples_round_trip() {
+    for (name, text) in samples() {
+        assert_eq!(reprint(&text), text, "reprint of {}", name);
+    }
+}
+
+/// Truncated programs leave comments, strings and literals unterminated,
+/// which must round trip as well
+#[test]
+fn truncated_samples_round_trip() {
+    for (name, text) in samples() {
+        for (i, _) in text.char_indices().filter(|(i, _)| i % 7 == 0) {
+            let prefix = &text[..i];
+            assert_eq!(reprint(prefix), prefix, "reprint of {} cut at byte {}", name, i);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+#![allow(clippy::needless_return)]
+mod common;
+
+use common::*;
+
+#[test]
+fn radix_prefixes_and_separators() {
+    assert_prints("print(0x6C, 0o744, 0b1001_1100, 1_000_000);", "108 484 156 1000000");
+}
+
+#[test]
+fn suffixes() {
+    assert_eq!(token_kinds("8888888888ul 1i64 255u8 1.5f32 3f64"), vec![
+        "INTEGER(8888888888, Some(U64))",
+        "INTEGER(1, Some(I64))",
+        "INTEGER(255, Some(U8))",
+        "FLOAT(1.5, Some(F32))",
+        "FLOAT(3.0, Some(F64))",
+    ]);
+    assert_prints("print(8888888888ul, 8888888888i64, 255u8, 1.5f32, 3f64);", "8888888888 8888888888 255 1.5 3");
+}
+
+#[test]
+fn exponents() {
+    assert_prints("print(1e-3, 2.5e2, 1E3);", "0.001 250 1000");
+}
+
+#[test]
+fn tuple_elements_are_not_floats() {
+    assert_eq!(token_kinds("1..2"), vec!["INTEGER(1, None)", "OPERATOR(\"..\")", "INTEGER(2, None)"]);
+    assert_prints("let t = ((1, 2), 3);\nprint(t.0.1);", "2");
+}
+
+#[test]
+fn signed_minimums_are_written_negated() {
+    assert_prints("let a = -128i8;\nlet b = -9223372036854775808i64;\nprint(a, b);", "-128 -9223372036854775808");
+    assert_prints("match -128i8 {\n    -128i8 => print(1),\n    _ => print(2),\n}", "1");
+    assert_fails("let a = 128i8;\nlet b = 1 - 128i8;\n", "\
+error: literal out of range for i8, whose maximum is 127
+  --> /dev/stdin:1:9
+  |
+1 | let a = 128i8;
+  |         ^^^^^ the range of i8 is -128..=127
+
+error: literal out of range for i8, whose maximum is 127
+  --> /dev/stdin:2:13
+  |
+2 | let b = 1 - 128i8;
+  |             ^^^^^ the range of i8 is -128..=127
+");
+}
+
+#[test]
+fn invalid_literals() {
+    assert_fails("let a = 256u8;
+let b = 99999999999999999999999;
+let c = 0x;
+let d = 0b102;
+let e = 1i7;
+", "\
+error: literal out of range for u8, whose maximum is 255
+  --> /dev/stdin:1:9
+  |
+1 | let a = 256u8;
+  |         ^^^^^ invalid token
+
+error: integer literal is too large, it does not fit in u64
+  --> /dev/stdin:2:9
+  |
+2 | let b = 99999999999999999999999;
+  |         ^^^^^^^^^^^^^^^^^^^^^^^ invalid token
+
+error: no digits in the hexadecimal literal
+  --> /dev/stdin:3:9
+  |
+3 | let c = 0x;
+  |         ^^ invalid token
+
+error: invalid digit `2` in the binary literal
+  --> /dev/stdin:4:9
+  |
+4 | let d = 0b102;
+  |         ^^^^^ invalid token
+
+error: invalid suffix `i7` for a number literal
+  --> /dev/stdin:5:9
+  |
+5 | let e = 1i7;
+  |         ^^^ invalid token
+");
+}
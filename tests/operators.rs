@@ -0,0 +1,87 @@
+#![allow(clippy::needless_return)]
+mod common;
+
+use common::*;
+
+#[test]
+fn longest_match() {
+    assert_eq!(token_kinds("** **= <<= >>= -> :: => != && || ... !"), vec![
+        "OPERATOR(\"**\")",
+        "OPERATOR(\"**=\")",
+        "OPERATOR(\"<<=\")",
+        "OPERATOR(\">>=\")",
+        "OPERATOR(\"->\")",
+        "OPERATOR(\"::\")",
+        "OPERATOR(\"=>\")",
+        "OPERATOR(\"!=\")",
+        "OPERATOR(\"&&\")",
+        "OPERATOR(\"||\")",
+        "OPERATOR(\"..\")",
+        "OPERATOR(\".\")",
+        "OPERATOR(\"!\")",
+    ]);
+}
+
+#[test]
+fn operators_without_spaces() {
+    assert_eq!(token_kinds("a-->b"), vec![
+        "IDENTIFIER(ID#a)",
+        "OPERATOR(\"-\")",
+        "OPERATOR(\"->\")",
+        "IDENTIFIER(ID#b)",
+    ]);
+}
+
+#[test]
+fn arithmetic_and_bitwise_operators() {
+    assert_prints(
+        "print(2 ** 10, 7 % 3, ~0, 6 & 3, 6 | 3, 6 ^ 3, 1 << 3, 16 >> 2);",
+        "1024 1 -1 2 7 5 8 4",
+    );
+    assert_prints(
+        "print(1 == 1, 1 != 1, 1 < 2, 2 <= 1, 3 > 2, 3 >= 4, !0, 1 && 0, 0 || 2);",
+        "1 0 1 0 1 0 1 0 1",
+    );
+}
+
+#[test]
+fn compound_assignment() {
+    assert_prints("let mut a = 7;\na += 3; a -= 1; a *= 2; a /= 4; a %= 3;\nprint(a);", "1");
+    assert_prints("let mut b = 1;\nb <<= 4; b |= 3; b &= 6; b ^= 1; b >>= 1;\nprint(b);", "1");
+    assert_prints("let mut c = 3;\nc **= 2;\nprint(c);", "9");
+}
+
+#[test]
+fn compound_assignment_evaluates_the_place_once() {
+    assert_prints("\
+let mut n = 0;
+fn g() -> int { n += 1; return 0; }
+let mut a = [5, 6];
+a[g()] += 1;
+struct P { x: int }
+let mut p = P(1);
+p.x *= 3;
+let r = new(2);
+*r **= 3;
+print(a, n, p.x, *r);
+", "[6, 6] 1 3 8");
+    assert_fails("let c = [1];\nc[0] += 1;\n", "\
+error: cannot assign to `c[0]`, as `c` is not declared as mutable
+  --> /dev/stdin:2:1
+  |
+2 | c[0] += 1;
+  | ^^^^^^^^^ cannot assign
+  = note: consider making this binding mutable: `mut c`
+");
+}
+
+#[test]
+fn unknown_characters() {
+    assert_fails("let a = 1 @ 2;", "\
+error: unknown character '@'
+  --> /dev/stdin:1:11
+  |
+1 | let a = 1 @ 2;
+  |           ^ invalid token
+");
+}
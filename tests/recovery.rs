@@ -0,0 +1,168 @@
+#![allow(clippy::needless_return)]
+mod common;
+
+use common::*;
+
+#[test]
+fn every_statement_is_reported() {
+    assert_fails("print(1));\nprint(2);\nlet = 3;\nfn f( { }\nlet z = 3 )\nprint(z);\n", "\
+error: expected ';' after the statement, found `)`
+  --> /dev/stdin:1:9
+  |
+1 | print(1));
+  |         ^ expected ';' here
+
+error: expected a name after 'let', found `=`
+  --> /dev/stdin:3:5
+  |
+3 | let = 3;
+  |     ^ expected a name after 'let'
+
+error: expected ',' or ')' in the argument list of a function, found `{`
+  --> /dev/stdin:4:7
+  |
+4 | fn f( { }
+  |       ^ expected ',' or ')' in the argument list of a function
+
+error: expected ';' after the statement, found `)`
+  --> /dev/stdin:5:10
+  |
+5 | let z = 3 )
+  |          ^ expected ';' here
+");
+}
+
+#[test]
+fn recovery_inside_blocks() {
+    assert_fails("fn f() {\n    let a = 1 +;\n    let b = 2 * ];\n}\nlet c = ;\n", "\
+error: expected an expression after '+', found `;`
+  --> /dev/stdin:2:16
+  |
+2 |     let a = 1 +;
+  |                ^ expected an expression after '+'
+
+error: expected an expression after '*', found `]`
+  --> /dev/stdin:3:17
+  |
+3 |     let b = 2 * ];
+  |                 ^ expected an expression after '*'
+
+error: expected an expression after '=', found `;`
+  --> /dev/stdin:5:9
+  |
+5 | let c = ;
+  |         ^ expected an expression after '='
+");
+    assert_fails("if 1 { let a = ; } else { let b = ]; }\nwhile 1 { ) }\n", "\
+error: expected an expression after '=', found `;`
+  --> /dev/stdin:1:16
+  |
+1 | if 1 { let a = ; } else { let b = ]; }
+  |                ^ expected an expression after '='
+
+error: expected an expression after '=', found `]`
+  --> /dev/stdin:1:35
+  |
+1 | if 1 { let a = ; } else { let b = ]; }
+  |                                   ^ expected an expression after '='
+
+error: expected a statement, found `)`
+  --> /dev/stdin:2:11
+  |
+2 | while 1 { ) }
+  |           ^ expected a statement
+");
+}
+
+#[test]
+fn unclosed_parentheses_stop_at_the_statement() {
+    assert_fails("let y = (1 + ;\nprint(\"x\" 1);\n", "\
+error: expected an expression after '+', found `;`
+  --> /dev/stdin:1:14
+  |
+1 | let y = (1 + ;
+  |              ^ expected an expression after '+'
+
+error: expected ',' or ')' in the argument list of a function call, found integer `1`
+  --> /dev/stdin:2:11
+  |
+2 | print(\"x\" 1);
+  |           ^ expected ',' or ')' in the argument list of a function call
+");
+}
+
+#[test]
+fn match_without_braces() {
+    assert_fails("let x = match 1 2;\nmatch 1 => 2;\nlet y = ;\n", "\
+error: expected '{' after the value of 'match', found integer `2`
+  --> /dev/stdin:1:17
+  |
+1 | let x = match 1 2;
+  |                 ^ expected '{' after the value of 'match'
+
+error: expected '{' after the value of 'match', found `=>`
+  --> /dev/stdin:2:9
+  |
+2 | match 1 => 2;
+  |         ^^ expected '{' after the value of 'match'
+
+error: expected an expression after '=', found `;`
+  --> /dev/stdin:3:9
+  |
+3 | let y = ;
+  |         ^ expected an expression after '='
+");
+}
+
+#[test]
+fn bodies_without_braces() {
+    assert_fails("if 1 2 { print(1); }\nwhile 1 print(1);\ndo print(1); until 1;\nlet y = ;\n", "\
+error: expected '{' after the condition of 'if', found integer `2`
+  --> /dev/stdin:1:6
+  |
+1 | if 1 2 { print(1); }
+  |      ^ expected '{' after the condition of 'if'
+
+error: expected '{' after the condition of 'while', found identifier `print`
+  --> /dev/stdin:2:9
+  |
+2 | while 1 print(1);
+  |         ^^^^^ expected '{' after the condition of 'while'
+
+error: expected '{' after 'do', found identifier `print`
+  --> /dev/stdin:3:4
+  |
+3 | do print(1); until 1;
+  |    ^^^^^ expected '{' after 'do'
+
+error: expected an expression after '=', found `;`
+  --> /dev/stdin:4:9
+  |
+4 | let y = ;
+  |         ^ expected an expression after '='
+");
+}
+
+#[test]
+fn unexpected_blocks_are_skipped_whole() {
+    assert_fails("fn f() -> int {\n    { { return 7; } }\n}\nlet y = ;\n", "\
+error: expected a statement, found `{`
+  --> /dev/stdin:2:5
+  |
+2 |     { { return 7; } }
+  |     ^ expected a statement
+
+error: expected an expression after '=', found `;`
+  --> /dev/stdin:4:9
+  |
+4 | let y = ;
+  |         ^ expected an expression after '='
+");
+}
+
+#[test]
+fn nothing_runs_after_a_syntax_error() {
+    let (ok, stdout, _) = run("print(1);\nlet = 2;\n");
+    assert!(!ok);
+    assert_eq!(stdout, "");
+}
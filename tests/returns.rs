@@ -0,0 +1,118 @@
+#![allow(clippy::needless_return)]
+mod common;
+
+use common::*;
+
+#[test]
+fn early_returns_leave_the_function() {
+    assert_prints("\
+fn f(a) -> int {
+    if a {
+        return 1;
+    }
+    return 2;
+}
+print(f(1), f(0));
+", "1 2");
+    assert_prints("fn f() {\n    return;\n    print(\"no\");\n}\nf();\nprint(\"yes\");", "yes");
+}
+
+#[test]
+fn returns_leave_loops() {
+    assert_prints("\
+fn find(xs: [int], v: int) -> int {
+    for i = 0 to len(xs) - 1 {
+        if xs[i] == v {
+            return i;
+        }
+    }
+    return -1;
+}
+print(find([4, 5, 6], 6), find([4], 9));
+", "2 -1");
+    assert_prints("\
+fn f() -> int {
+    let mut i = 0;
+    while 1 {
+        i = i + 1;
+        loop {
+            if i > 2 {
+                return i;
+            }
+            break;
+        }
+    }
+}
+fn g() -> int {
+    do {
+        return 4;
+    } until 1;
+}
+print(f(), g());
+", "3 4");
+}
+
+#[test]
+fn returns_leave_match_arms() {
+    assert_prints("\
+fn f(x) -> int {
+    match x {
+        1 => {
+            return 10;
+        },
+        _ => {}
+    }
+    return 0;
+}
+print(f(1), f(2));
+", "10 0");
+}
+
+#[test]
+fn lambdas_return_to_their_caller() {
+    assert_prints("\
+fn f() {
+    let g = || { return 1; };
+    print(g());
+    print(\"after\");
+}
+f();
+", "1\nafter");
+}
+
+#[test]
+fn return_ends_the_program() {
+    assert_prints("print(1);\nreturn;\nprint(2);", "1");
+    assert_fails("if 1 {\n    return 5;\n}\n", "\
+error: cannot return a value outside of a function
+  --> /dev/stdin:2:12
+  |
+2 |     return 5;
+  |            ^ unexpected return value
+  = note: `return;` ends the program without a value
+");
+}
+
+#[test]
+fn returns_cannot_skip_values() {
+    assert_fails("fn f() -> int {\n    let x = loop {\n        return 1;\n    };\n    return x;\n}\n", "\
+error: `return` cannot leave a `loop` used as a value
+  --> /dev/stdin:3:9
+  |
+3 |         return 1;
+  |         ^^^^^^^^^ this would skip the value of the `loop`
+");
+}
+
+#[test]
+fn returns_need_the_declared_value() {
+    assert_fails("fn f() -> int {\n    return;\n}\n", "\
+error: mismatched types: `f` returns i32 but no value is returned
+  --> /dev/stdin:2:5
+  |
+1 | fn f() -> int {
+  |           --- expected due to this return type
+2 |     return;
+  |     ^^^^^^^ expected a value of type i32
+");
+}
@@ -0,0 +1,78 @@
+#![allow(clippy::needless_return)]
+mod common;
+
+use common::*;
+
+/// The `--tokens` dump of `source`, one `span kind` per line
+fn tokens(source: &str) -> Vec<String> {
+    let (ok, stdout, stderr) = run_with(&["--tokens"], source);
+    assert!(ok, "{}", stderr);
+    return stdout.lines().map(|x| x.to_string()).collect();
+}
+
+#[test]
+fn tokens_have_line_and_column_ranges() {
+    assert_eq!(tokens("let x = 10;\n  print(x);\n"), vec![
+        "/dev/stdin:1:1-1:4 KEYWORD(\"let\")",
+        "/dev/stdin:1:5-1:6 IDENTIFIER(ID#x)",
+        "/dev/stdin:1:7-1:8 OPERATOR(\"=\")",
+        "/dev/stdin:1:9-1:11 INTEGER(10, None)",
+        "/dev/stdin:1:11-1:12 SEMICOLON",
+        "/dev/stdin:2:3-2:8 IDENTIFIER(ID#print)",
+        "/dev/stdin:2:8-2:9 OPERATOR(\"(\")",
+        "/dev/stdin:2:9-2:10 IDENTIFIER(ID#x)",
+        "/dev/stdin:2:10-2:11 OPERATOR(\")\")",
+        "/dev/stdin:2:11-2:12 SEMICOLON",
+        "/dev/stdin:3:1-3:1 EOF",
+    ]);
+}
+
+#[test]
+fn tabs_and_crlf_line_endings() {
+    assert_eq!(tokens("x;\r\n\ty;"), vec![
+        "/dev/stdin:1:1-1:2 IDENTIFIER(ID#x)",
+        "/dev/stdin:1:2-1:3 SEMICOLON",
+        "/dev/stdin:2:2-2:3 IDENTIFIER(ID#y)",
+        "/dev/stdin:2:3-2:4 SEMICOLON",
+        "/dev/stdin:2:4-2:4 EOF",
+    ]);
+}
+
+#[test]
+fn tokens_spanning_lines() {
+    assert_eq!(tokens("\"a\nb\" /* c\n */ d")[..2], [
+        "/dev/stdin:1:1-2:3 STRING(\"a\\nb\")",
+        "/dev/stdin:3:5-3:6 IDENTIFIER(ID#d)",
+    ]);
+}
+
+#[test]
+fn expressions_span_their_operands() {
+    assert_fails("let a = 1;\n\nlet b = a / 0;\n", "\
+error: attempt to divide by zero
+  --> /dev/stdin:3:9
+  |
+3 | let b = a / 0;
+  |         ^^^^^ division by zero
+  |             - this is 0
+");
+    assert_fails("let t = (1, 2);\nt.5;\n", "\
+error: no element 5 on type (i32, i32)
+  --> /dev/stdin:2:1
+  |
+2 | t.5;
+  | ^^^ unknown element
+");
+}
+
+#[test]
+fn spans_over_several_lines_are_underlined_to_the_end_of_the_first() {
+    assert_fails("let x: ref str = 1 +\n    2;\n", "\
+error: mismatched types: expected ref str, found i32
+  --> /dev/stdin:1:18
+  |
+1 | let x: ref str = 1 +
+  |                  ^^^ expected ref str
+  |        ------- expected due to this type
+");
+}
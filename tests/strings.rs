@@ -0,0 +1,76 @@
+#![allow(clippy::needless_return)]
+mod common;
+
+use common::*;
+
+#[test]
+fn escapes() {
+    assert_prints(r#"print("a\tb\\c\"d\u{48}\u{1F600}");"#, "a\tb\\c\"dH\u{1F600}");
+    assert_prints(r"print('x', '\u{e9}', '\'', '\\');", "x é ' \\");
+    assert_prints(r#"print("one\ntwo");"#, "one\ntwo");
+}
+
+#[test]
+fn raw_strings() {
+    assert_prints(r###"print(r"raw\n", r#"with "quotes""#);"###, "raw\\n with \"quotes\"");
+}
+
+#[test]
+fn c_strings_are_not_strings() {
+    assert_prints(r#"let s = c"hi"; print(s);"#, "hi");
+    assert_fails(r#"let s: ref str = c"x";"#, "\
+error: mismatched types: expected ref str, found ref cstr
+  --> /dev/stdin:1:18
+  |
+1 | let s: ref str = c\"x\";
+  |                  ^^^^ expected ref str, found ref cstr
+  |        ------- expected due to this type
+");
+}
+
+#[test]
+fn unterminated_string() {
+    assert_fails("let s = \"abc;\n", "\
+error: unterminated string literal
+  --> /dev/stdin:1:9
+  |
+1 | let s = \"abc;
+  |         ^^^^^ invalid token
+");
+}
+
+#[test]
+fn invalid_escapes() {
+    assert_fails(r#"let s = "a\qb";
+let t = "\u{110000}";
+let u = "\u{zz}";
+"#, r#"error: unknown escape sequence `\q`
+  --> /dev/stdin:1:9
+  |
+1 | let s = "a\qb";
+  |         ^^^^^^ invalid token
+
+error: invalid unicode escape `\u{110000}`
+  --> /dev/stdin:2:9
+  |
+2 | let t = "\u{110000}";
+  |         ^^^^^^^^^^^^ invalid token
+
+error: invalid unicode escape `\u{zz}`
+  --> /dev/stdin:3:9
+  |
+3 | let u = "\u{zz}";
+  |         ^^^^^^^^ invalid token
+"#);
+}
+
+#[test]
+fn empty_character_literal() {
+    assert_fails("let d = '';", "\
+error: empty character literal
+  --> /dev/stdin:1:9
+  |
+1 | let d = '';
+  |         ^^ invalid token
+");
+}
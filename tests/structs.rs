@@ -0,0 +1,163 @@
+#![allow(clippy::needless_return)]
+mod common;
+
+use common::*;
+
+#[test]
+fn methods_and_associated_functions() {
+    assert_prints("\
+struct P { x: int, y: int }
+impl P {
+    fn new(x: int) -> P { return P(x, 0); }
+    fn sum(self) -> int { return self.x + self.y; }
+    fn shift(mut self, d: int) { self.x = self.x + d; }
+}
+let mut p = P::new(3);
+p.y = 4;
+print(p.sum());
+p.shift(2);
+print(p.x, p.y);
+", "7\n5 4");
+}
+
+#[test]
+fn constructors() {
+    assert_prints("struct P { x: int, y: int }\nlet z = P();\nprint(z);", "P { x: 0, y: 0 }");
+    assert_prints("struct P { x: int, y: int }\nlet q = new(P(2, 1));\nq.x = 7;\nprint(q.x, q.y);", "7 1");
+    assert_fails("struct P { x: int, y: int }\nlet p = P(1);\n", "\
+error: struct `P` has 2 fields but 1 was supplied
+  --> /dev/stdin:2:9
+  |
+1 | struct P { x: int, y: int }
+  |            -------------- fields declared here
+2 | let p = P(1);
+  |         ^^^^ expected 2 arguments
+  = note: call `P()` without arguments to zero all the fields
+");
+    assert_fails("struct P { x: int }\nlet p = P(\"a\");\n", "\
+error: mismatched types: expected i32, found ref str
+  --> /dev/stdin:2:11
+  |
+1 | struct P { x: int }
+  |               --- field `x` declared here
+2 | let p = P(\"a\");
+  |           ^^^ expected i32, found ref str
+");
+}
+
+#[test]
+fn fields() {
+    assert_prints("struct A { b: B }\nstruct B { v: int }\nlet mut a = A(B(1));\na.b.v = 3;\nprint(a.b.v);", "3");
+    assert_fails("struct P { x: int }\nlet p = P(1);\nprint(p.y);\n", "\
+error: no field `y` on type P
+  --> /dev/stdin:3:7
+  |
+3 | print(p.y);
+  |       ^^^ unknown field
+");
+    assert_fails("struct P { x: int }\nlet mut p = P(1);\np.x = \"s\";\n", "\
+error: mismatched types: expected i32, found ref str
+  --> /dev/stdin:3:7
+  |
+1 | struct P { x: int }
+  |               --- expected due to the type of this field
+3 | p.x = \"s\";
+  |       ^^^ expected i32, found ref str
+");
+    assert_fails("struct P { x: int, x: int }\n", "\
+error: field `x` is already declared
+  --> /dev/stdin:1:20
+  |
+1 | struct P { x: int, x: int }
+  |                    ^^^^^^ field already declared
+  |            ------ first declared here
+");
+}
+
+#[test]
+fn immutable_objects() {
+    assert_fails("struct P { x: int }\nlet p = P(1);\np.x = 2;\n", "\
+error: cannot assign to `p.x`, as `p` is not declared as mutable
+  --> /dev/stdin:3:1
+  |
+3 | p.x = 2;
+  | ^^^^^^^ cannot assign
+  = note: consider making this binding mutable: `mut p`
+");
+    assert_fails("\
+struct P { x: int }
+impl P { fn shift(mut self, d: int) { self.x = self.x + d; } }
+let p = P(1);
+p.shift(2);
+", "\
+error: cannot borrow `p` as mutable, as `p` is not declared as mutable
+  --> /dev/stdin:4:1
+  |
+4 | p.shift(2);
+  | ^ cannot borrow as mutable
+  = note: consider making this binding mutable: `mut p`
+");
+    assert_fails("struct P { x: int }\nfn f(mut q: P) { q.x = 5; }\nlet p = P(1);\nf(p);\n", "\
+error: cannot borrow `p` as mutable, as `p` is not declared as mutable
+  --> /dev/stdin:4:3
+  |
+4 | f(p);
+  |   ^ cannot borrow as mutable
+  = note: consider making this binding mutable: `mut p`
+");
+}
+
+#[test]
+fn unknown_methods_and_functions() {
+    assert_fails("struct P { x: int }\nlet p = P(1);\np.nope();\n", "\
+error: no method named `nope` found for struct `P`
+  --> /dev/stdin:3:1
+  |
+3 | p.nope();
+  | ^^^^^^ method not found
+");
+    assert_fails("struct P { x: int }\nimpl P { fn make() -> P { return P(1); } }\nlet p = P(1);\np.make();\n", "\
+error: `make` is an associated function, not a method
+  --> /dev/stdin:4:1
+  |
+4 | p.make();
+  | ^^^^^^ this function takes no `self`
+  = note: call it as `P::make(...)`
+");
+    assert_fails("struct P { x: int }\nP::nothing();\n", "\
+error: no function named `nothing` in struct `P`
+  --> /dev/stdin:2:1
+  |
+2 | P::nothing();
+  | ^^^^^^^^^^ function not found in the impl blocks
+");
+}
+
+#[test]
+fn impl_blocks() {
+    assert_fails("impl Q {}\n", "\
+error: cannot find struct `Q` in this scope
+  --> /dev/stdin:1:6
+  |
+1 | impl Q {}
+  |      ^ not a struct
+");
+    assert_fails("struct P { x: int }\nimpl P { fn f(self) {} }\nimpl P { fn f(self) {} }\n", "\
+error: duplicate definitions with name `f`
+  --> /dev/stdin:3:10
+  |
+3 | impl P { fn f(self) {} }
+  |          ^^^^^^^^^^^^^ `f` is already declared for `P`
+");
+}
+
+#[test]
+fn syntax_errors() {
+    assert_fails("struct P { x int }\n", "\
+error: expected ':' and the type of the field, found identifier `int`
+  --> /dev/stdin:1:14
+  |
+1 | struct P { x int }
+  |              ^^^ expected ':' and the type of the field
+");
+}
@@ -0,0 +1,51 @@
+#![allow(clippy::needless_return)]
+mod common;
+
+use common::*;
+
+#[test]
+fn dump_ends_with_eof() {
+    let (ok, stdout, stderr) = run_with(&["--tokens"], "");
+    assert!(ok);
+    assert_eq!(stdout, "/dev/stdin:1:1-1:1 EOF\n");
+    assert_eq!(stderr, "");
+}
+
+#[test]
+fn dump_reports_invalid_tokens() {
+    let (ok, stdout, stderr) = run_with(&["--tokens"], "a @ b");
+    assert!(!ok);
+    assert_eq!(stdout, "\
+/dev/stdin:1:1-1:2 IDENTIFIER(ID#a)
+/dev/stdin:1:3-1:4 ERROR(\"unknown character '@'\")
+/dev/stdin:1:5-1:6 IDENTIFIER(ID#b)
+/dev/stdin:1:6-1:6 EOF
+");
+    assert_eq!(stderr, "\
+error: unknown character '@'
+  --> /dev/stdin:1:3
+  |
+1 | a @ b
+  |   ^ invalid token
+
+");
+}
+
+#[test]
+fn tokens_are_lexed_once() {
+    // tuples and lambdas are told apart by looking ahead, which must not
+    // report the invalid tokens again
+    assert_fails("let t = (1, @);\nlet u = |x| x @ 1;\n", "\
+error: unknown character '@'
+  --> /dev/stdin:1:13
+  |
+1 | let t = (1, @);
+  |             ^ invalid token
+
+error: unknown character '@'
+  --> /dev/stdin:2:15
+  |
+2 | let u = |x| x @ 1;
+  |               ^ invalid token
+");
+}
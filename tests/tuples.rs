@@ -0,0 +1,132 @@
+#![allow(clippy::needless_return)]
+mod common;
+
+use common::*;
+
+#[test]
+fn tuples_and_elements() {
+    assert_prints("\
+let t = (1, \"a\", 2.5);
+print(t.0, t.1, t.2, t);
+let one = (5,);
+print(one, ());
+let n = ((1, 2), 3);
+print(n.0.1);
+", "1 a 2.5 (1, a, 2.5)\n(5,) ()\n2");
+    assert_fails("let t = (1,);\nprint(t.1);\n", "\
+error: no element 1 on type (i32,)
+  --> /dev/stdin:2:7
+  |
+2 | print(t.1);
+  |       ^^^ unknown element
+");
+    assert_fails("fn f() -> (int,) { return (1,); }\nprint(f().1);\n", "\
+error: no element 1 on type (i32,)
+  --> /dev/stdin:2:7
+  |
+2 | print(f().1);
+  |       ^^^^^ the tuple has 1 element
+");
+}
+
+#[test]
+fn elements_are_copied_when_bound() {
+    assert_fails("struct P { a: int }\nlet t = (P(1), 2);\nlet q = t.0;\n", "\
+error: cannot copy `t.0`, as `P` does not implement `Copy`
+  --> /dev/stdin:3:9
+  |
+3 | let q = t.0;
+  |         ^^^ copied here
+  = note: derive it with `#[derive(Copy)]`
+");
+}
+
+#[test]
+fn functions_return_several_values() {
+    assert_prints("\
+fn divmod(a: int, b: int) -> (int, int) {
+    return (a / b, a % b);
+}
+let (q, r) = divmod(7, 2);
+print(q, r);
+", "3 1");
+    assert_fails("fn f() -> (int, int) { return (1, \"a\"); }\nprint(f());\n", "\
+error: mismatched types: `f` returned (i32, ref str) but is declared to return (i32, i32)
+  --> /dev/stdin:2:7
+  |
+1 | fn f() -> (int, int) { return (1, \"a\"); }
+  |           ---------- expected due to this return type
+2 | print(f());
+  |       ^^^ returned (i32, ref str)
+");
+}
+
+#[test]
+fn destructuring() {
+    assert_prints("let (a, (b, _)) = (1, (2, 3));\nprint(a, b);", "1 2");
+    assert_prints("fn swap((x, y): (int, int)) -> (int, int) { return (y, x); }\nprint(swap((1, 2)));", "(2, 1)");
+    assert_prints("for (i, c) in [(0, 'a'), (1, 'b')] { print(i, c); }", "0 a\n1 b");
+    assert_prints("let mut p = (1, 2);\np = (p.1, p.0);\nprint(p);", "(2, 1)");
+}
+
+#[test]
+fn patterns_must_fit_the_tuple() {
+    assert_fails("let (a, b) = (1, 2, 3);\n", "\
+error: mismatched types: expected a tuple with 2 elements, found (i32, i32, i32)
+  --> /dev/stdin:1:5
+  |
+1 | let (a, b) = (1, 2, 3);
+  |     ^^^^^^ expected a tuple with 2 elements
+");
+    assert_fails("fn f(x) -> int { let (a,) = x; return a; }\nprint(f((1, 2)));\n", "\
+error: mismatched types: expected a tuple with 1 element, found (i32, i32)
+  --> /dev/stdin:1:22
+  |
+1 | fn f(x) -> int { let (a,) = x; return a; }
+  |                      ^^^^ expected a tuple with 1 element
+");
+    assert_fails("let (a, b) = 5;\n", "\
+error: mismatched types: expected i32, found a tuple
+  --> /dev/stdin:1:5
+  |
+1 | let (a, b) = 5;
+  |     ^^^^^^ expected i32
+");
+}
+
+#[test]
+fn names_are_bound_once() {
+    assert_fails("let (a, a) = (1, 2);\nfor (x, (y, x)) in [(1, (2, 3))] { print(x); }\nmatch (1, 2) { (y, y) => print(y) }\n", "\
+error: identifier `a` is bound more than once in the same pattern
+  --> /dev/stdin:1:9
+  |
+1 | let (a, a) = (1, 2);
+  |         ^ used in a pattern more than once
+  |      - first bound here
+
+error: identifier `x` is bound more than once in the same pattern
+  --> /dev/stdin:2:13
+  |
+2 | for (x, (y, x)) in [(1, (2, 3))] { print(x); }
+  |             ^ used in a pattern more than once
+  |      - first bound here
+
+error: identifier `y` is bound more than once in the same pattern
+  --> /dev/stdin:3:20
+  |
+3 | match (1, 2) { (y, y) => print(y) }
+  |                    ^ used in a pattern more than once
+  |                 - first bound here
+");
+}
+
+#[test]
+fn syntax_errors() {
+    assert_fails("let t = (1, 2;\n", "\
+error: expected ',' or ')' in the elements of a tuple, found `;`
+  --> /dev/stdin:1:14
+  |
+1 | let t = (1, 2;
+  |              ^ expected ',' or ')' in the elements of a tuple
+");
+}
@@ -0,0 +1,142 @@
+#![allow(clippy::needless_return)]
+mod common;
+
+use common::*;
+
+#[test]
+fn primitives_and_aliases() {
+    assert_prints("\
+let a: i8 = -1;
+let b: uint = 3;
+let c: double = 2.5;
+let d: char = 'x';
+let e: usize = 4;
+let f: byte = 0x6C;
+let s: ref str = \"hi\";
+print(a, b, c, d, e, f, s);
+", "-1 3 2.5 x 4 108 hi");
+}
+
+#[test]
+fn arrays_tuples_and_functions() {
+    assert_prints("\
+let a: [i32; 3] = [1, 2, 3];
+let b: int[3] = [4, 5, 6];
+let c: [int; _] = [7, 8];
+let d: [u8] = [9u8];
+let t: (int, ref str) = (1, \"x\");
+let u: (int,) = (7,);
+let v: () = ();
+let f: fn(int) -> int = |x| x + 1;
+print(a, b, c, d, t, u, v, f(1));
+", "[1, 2, 3] [4, 5, 6] [7, 8] [9] (1, x) (7,) () 2");
+}
+
+#[test]
+fn pointers_and_references() {
+    assert_prints("let p = new(5);\nlet r: &int = p;\nlet q: *int = new(6) as *int;\nprint(*r, *q);", "5 6");
+}
+
+#[test]
+fn array_length_binders() {
+    assert_prints("\
+fn sum(a: [int; n]) -> int {
+    let mut t = 0;
+    for x in a {
+        t = t + x;
+    }
+    return t * n;
+}
+fn first(a: int[n]) {
+    print(n, a[0]);
+}
+print(sum([1, 2, 3]));
+first([7, 8]);
+", "18\n2 7");
+    assert_fails("let a: int[] = [1, 2];\nprint(len(a));\n", "\
+error: cannot take the length of a C-like array
+  --> /dev/stdin:2:11
+  |
+2 | print(len(a));
+  |           ^ this array does not store its length
+  = note: use a Rust-like array `[T]`, or bind its length with `T[n]` and use `n`
+");
+}
+
+#[test]
+fn mismatched_types() {
+    assert_fails("let a: [int; 2] = [1, 2, 3];\n", "\
+error: mismatched types: expected [i32; 2], found [i32; 3]
+  --> /dev/stdin:1:19
+  |
+1 | let a: [int; 2] = [1, 2, 3];
+  |                   ^^^^^^^^^ expected [i32; 2]
+  |        -------- expected due to this type
+");
+    assert_fails("let t: (int, (u8, str)) = (1, (2, \"a\"));\n", "\
+error: mismatched types: expected str, found ref str
+  --> /dev/stdin:1:35
+  |
+1 | let t: (int, (u8, str)) = (1, (2, \"a\"));
+  |                                   ^^^ expected str
+  |                   --- expected due to this type
+");
+}
+
+#[test]
+fn unknown_types() {
+    assert_fails("let x: Map<str, int> = 1;\nstruct S { a: [Bar; 2] }\nfn f(x: Foo) -> int { return x; }\n", "\
+error: cannot find type `Map` in this scope
+  --> /dev/stdin:1:8
+  |
+1 | let x: Map<str, int> = 1;
+  |        ^^^^^^^^^^^^^ not found in this scope
+
+error: cannot find type `Bar` in this scope
+  --> /dev/stdin:2:16
+  |
+2 | struct S { a: [Bar; 2] }
+  |                ^^^ not found in this scope
+
+error: cannot find type `Foo` in this scope
+  --> /dev/stdin:3:9
+  |
+3 | fn f(x: Foo) -> int { return x; }
+  |         ^^^ not found in this scope
+");
+}
+
+#[test]
+fn syntax_errors() {
+    assert_fails("let a: [int; ] = 1;\nlet b: (int = 1;\nlet c: fn(int -> int = 1;\nlet d: fn(int) -> = 1;\nlet e: * = 1;\n", "\
+error: expected an array length after ';', found `]`
+  --> /dev/stdin:1:14
+  |
+1 | let a: [int; ] = 1;
+  |              ^ expected an array length after ';'
+
+error: expected ',' or ')' in a tuple type, found `=`
+  --> /dev/stdin:2:13
+  |
+2 | let b: (int = 1;
+  |             ^ expected ',' or ')' in a tuple type
+
+error: expected ',' or ')' in the argument list of a function type, found `->`
+  --> /dev/stdin:3:15
+  |
+3 | let c: fn(int -> int = 1;
+  |               ^^ expected ',' or ')' in the argument list of a function type
+
+error: expected a type after '->', found `=`
+  --> /dev/stdin:4:19
+  |
+4 | let d: fn(int) -> = 1;
+  |                   ^ expected a type after '->'
+
+error: expected a type after '*', found `=`
+  --> /dev/stdin:5:10
+  |
+5 | let e: * = 1;
+  |          ^ expected a type after '*'
+");
+}
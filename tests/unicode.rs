@@ -0,0 +1,40 @@
+#![allow(clippy::needless_return)]
+mod common;
+
+use common::*;
+
+#[test]
+fn identifiers_and_strings() {
+    assert_prints("let größe = \"日本語\";\nlet 名前 = '字';\nprint(größe, 名前, len(größe));", "日本語 字 3");
+}
+
+#[test]
+fn columns_count_characters() {
+    assert_eq!(token_kinds("größe")[0], "IDENTIFIER(ID#größe)");
+    let (ok, stdout, _) = run_with(&["--tokens"], "größe = 1;");
+    assert!(ok);
+    assert_eq!(stdout.lines().nth(1).unwrap(), "/dev/stdin:1:7-1:8 OPERATOR(\"=\")");
+}
+
+#[test]
+fn underlines_fit_wide_characters() {
+    assert_fails("let 日本 = 1; let y: ref str = 日本;", "\
+error: mismatched types: expected ref str, found i32
+  --> /dev/stdin:1:30
+  |
+1 | let 日本 = 1; let y: ref str = 日本;
+  |                                ^^^^ expected ref str, found i32
+  |                      ------- expected due to this type
+");
+}
+
+#[test]
+fn unknown_characters() {
+    assert_fails("let a = 1 € 2;", "\
+error: unknown character '€'
+  --> /dev/stdin:1:11
+  |
+1 | let a = 1 € 2;
+  |           ^ invalid token
+");
+}